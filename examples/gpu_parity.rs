@@ -0,0 +1,113 @@
+use rust_webgpu_visual_engine::particles::{
+    check_random_parity, EmitterConfig, ForceConfig, ForceField, Integrator, ParityHarness,
+    ParityTolerance, ParticleSimConfig, ParticleStepInput,
+};
+
+fn main() {
+    if let Err(err) = pollster::block_on(run()) {
+        eprintln!("gpu_parity failed: {err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    // Prefer the software adapter so the check runs the same on machines without a GPU.
+    let instance = wgpu::Instance::default();
+    let mut adapter = None;
+    for force_fallback_adapter in [true, false] {
+        adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::LowPower,
+                compatible_surface: None,
+                force_fallback_adapter,
+            })
+            .await;
+        if adapter.is_some() {
+            break;
+        }
+    }
+    let adapter = adapter.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::NotFound, "no wgpu adapter found")
+    })?;
+    println!("gpu_parity adapter: {}", adapter.get_info().name);
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("particle.gpu_parity.device"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await?;
+
+    let random = check_random_parity(&device, &queue, 4096)?;
+    println!(
+        "  random samples={} hash_mismatches={} max_noise_error={:.2e}",
+        random.samples, random.hash_mismatches, random.max_noise_error
+    );
+    let mut failed = random.hash_mismatches > 0 || random.max_noise_error > 1e-4;
+
+    let mut force = ForceConfig::default();
+    force.fields.push(ForceField::Vortex {
+        position: [0.0; 3],
+        axis: [0.0, 1.0, 0.0],
+        strength: 2.0,
+        inward_strength: 1.0,
+        radius: 0.0,
+        falloff: 1.0,
+    });
+    force.fields.push(ForceField::Wind {
+        direction: [1.0, 0.0, 0.0],
+        strength: 0.3,
+        turbulence: 0.5,
+        turbulence_frequency: 2.0,
+    });
+    for integrator in [
+        Integrator::SemiImplicitEuler,
+        Integrator::Verlet,
+        Integrator::Rk4,
+    ] {
+        // Lifetimes outlast the run, so no slot is freed and respawned in a different order.
+        let config = ParticleSimConfig {
+            max_particles: 1024,
+            spawn_rate_per_second: 600.0,
+            lifetime_seconds: 100.0,
+            integrator,
+            seed: 7,
+            ..ParticleSimConfig::default()
+        };
+        let mut harness = ParityHarness::new(&device, &queue, config, ParityTolerance::default())?;
+        for _ in 0..120 {
+            harness.step(
+                &device,
+                &queue,
+                ParticleStepInput {
+                    dt_seconds: 1.0 / 60.0,
+                    emitter: EmitterConfig::default(),
+                    force,
+                },
+            )?;
+        }
+        let report = harness.report();
+        println!(
+            "  {:?}: compared={} max_position_error={:.2e} max_velocity_error={:.2e} mismatches={}",
+            integrator,
+            report.compared,
+            report.max_position_error,
+            report.max_velocity_error,
+            report.mismatches
+        );
+        if let Some(mismatch) = report.first_mismatch {
+            println!("    first mismatch: {mismatch:?}");
+        }
+        failed |= !report.passed();
+    }
+
+    if failed {
+        return Err("CPU and GPU paths diverged".into());
+    }
+    println!("gpu_parity ok");
+    Ok(())
+}
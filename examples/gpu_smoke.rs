@@ -1,5 +1,6 @@
 use rust_webgpu_visual_engine::particles::{
-    ForceConfig, ParticleGpuSim, ParticleSimConfig, ParticleStepInput, ParticleWorkgroup,
+    EmitterConfig, ForceConfig, ParticleGpuSim, ParticleSimConfig, ParticleStepInput,
+    ParticleWorkgroup,
 };
 
 fn main() {
@@ -30,7 +31,7 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
             &wgpu::DeviceDescriptor {
                 label: Some("particle.gpu_smoke.device"),
                 required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::downlevel_defaults(),
+                required_limits: wgpu::Limits::default(),
             },
             None,
         )
@@ -40,31 +41,59 @@ async fn run() -> Result<(), Box<dyn std::error::Error>> {
         max_particles: 8192,
         ..ParticleSimConfig::default()
     };
-    let sim = ParticleGpuSim::init(&device, &queue, config, ParticleWorkgroup::default())?;
+    let mut sim = ParticleGpuSim::init(&device, &queue, config, ParticleWorkgroup::default())?;
+    sim.set_particle_readback(&device, 256);
 
+    // Reads particles back every step without waiting, as a debug overlay would.
+    let mut readbacks = 0;
+    let mut latest_readback = None;
     for _ in 0..120 {
         sim.step(
             &device,
             &queue,
             ParticleStepInput {
                 dt_seconds: 1.0 / 120.0,
+                emitter: EmitterConfig::default(),
                 force: ForceConfig::default(),
             },
         );
+        sim.request_particles(&device, &queue);
+        if let Some(readback) = sim.poll_particles(&device)? {
+            readbacks += 1;
+            latest_readback = Some(readback);
+        }
     }
 
-    let sample = sim.readback_debug_sample(&device, &queue, 128)?;
-    let alive = sample
-        .iter()
-        .filter(|p| p.age_seconds.is_finite() && p.age_seconds < p.lifetime_seconds)
-        .count();
+    sim.request_stats(&device, &queue);
+    let stats = loop {
+        device.poll(wgpu::Maintain::Wait);
+        if let Some(stats) = sim.poll_stats(&device)? {
+            break stats;
+        }
+    };
 
     println!(
-        "gpu_smoke ok: particle_count={} sampled={} alive_in_sample={}",
+        "gpu_smoke ok: particle_count={} step={} alive={} spawned={} died={} max_speed={:.3} non_finite={}",
         sim.particle_count(),
-        sample.len(),
-        alive
+        stats.step_index,
+        stats.alive_count,
+        stats.spawned_this_step,
+        stats.died_this_step,
+        stats.max_speed,
+        stats.non_finite_count
+    );
+    println!(
+        "  bounds min={:?} max={:?}",
+        stats.bounds_min, stats.bounds_max
     );
+    if let Some(readback) = latest_readback {
+        println!(
+            "  particle readbacks={} latest_step={} alive_in_sample={}",
+            readbacks,
+            readback.step_index,
+            readback.particles.iter().filter(|p| p.is_alive()).count()
+        );
+    }
 
     Ok(())
 }
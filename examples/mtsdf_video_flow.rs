@@ -6,7 +6,10 @@ use rust_webgpu_visual_engine::timeline::VideoTimeline;
 fn main() {
     let timeline = VideoTimeline::mtsdf_reference_sequence();
     let mut config = ParticleSimConfig::default();
-    let emitter = EmitterConfig::default();
+    let emitter = EmitterConfig {
+        bursts: timeline.burst_schedule(),
+        ..EmitterConfig::default()
+    };
     let mut state = ParticleState::new(config);
     let mut clock = SimulationClock::new(1.0 / 120.0);
 
@@ -0,0 +1,175 @@
+// Boids passes of `ParticleSimMode::Boids`, prepended with `SpatialHashGrid::query_wgsl(0, 0)`.
+// Each kernel runs one thread per particle slot; `BoidsConfig::steer_reference` in boids.rs is
+// the CPU mirror.
+
+struct Particle {
+  position : vec3<f32>,
+  age : f32,
+  velocity : vec3<f32>,
+  lifetime : f32,
+}
+
+// Mirrors `GpuBoidsParams` in boids.rs.
+struct BoidsParams {
+  separation_radius : f32,
+  alignment_radius : f32,
+  cohesion_radius : f32,
+  separation_weight : f32,
+  alignment_weight : f32,
+  cohesion_weight : f32,
+  attractor_weight : f32,
+  min_speed : f32,
+  max_speed : f32,
+  max_turn_rate : f32,
+  dt : f32,
+  particle_count : u32,
+  force_field_count : u32,
+  _pad0 : u32,
+  _pad1 : u32,
+  _pad2 : u32,
+}
+
+// Mirrors `GpuForceField` in forces.rs; only `FIELD_POINT` fields are read here.
+struct ForceField {
+  position : vec3<f32>,
+  kind : u32,
+  axis : vec3<f32>,
+  strength : f32,
+  radius : f32,
+  falloff : f32,
+  param0 : f32,
+  param1 : f32,
+}
+
+const FIELD_POINT : u32 = 0u;
+
+@group(0) @binding(3)
+var<storage, read_write> particles : array<Particle>;
+
+@group(0) @binding(4)
+var<storage, read_write> steered : array<vec4<f32>>;
+
+@group(0) @binding(5)
+var<uniform> boids : BoidsParams;
+
+@group(0) @binding(6)
+var<storage, read> force_fields : array<ForceField>;
+
+fn alive_slot(slot: u32) -> bool {
+  return slot < boids.particle_count && particles[slot].age < particles[slot].lifetime;
+}
+
+fn safe_normalize(v: vec3<f32>) -> vec3<f32> {
+  let len_sq = dot(v, v);
+  if (len_sq <= 1e-8) {
+    return vec3<f32>(0.0);
+  }
+  return v * inverseSqrt(len_sq);
+}
+
+// Same as `radial_weight` in particles_update.wgsl.
+fn radial_weight(distance: f32, radius: f32, falloff: f32) -> f32 {
+  if (radius <= 0.0) {
+    return 1.0;
+  }
+  let t = 1.0 - distance / radius;
+  if (t <= 0.0) {
+    return 0.0;
+  }
+  return pow(t, falloff);
+}
+
+// Mirrors `BoidsConfig::limit_steering` in boids.rs.
+fn limit_steering(velocity: vec3<f32>, desired: vec3<f32>) -> vec3<f32> {
+  let speed = clamp(length(desired), boids.min_speed, boids.max_speed);
+  let heading = safe_normalize(velocity);
+  var goal = safe_normalize(desired);
+  if (all(goal == vec3<f32>(0.0))) {
+    goal = heading;
+  }
+  if (all(heading == vec3<f32>(0.0))) {
+    return goal * speed;
+  }
+
+  let angle = acos(clamp(dot(heading, goal), -1.0, 1.0));
+  let max_angle = boids.max_turn_rate * boids.dt;
+  if (angle <= max_angle) {
+    return goal * speed;
+  }
+  var axis = safe_normalize(cross(heading, goal));
+  if (all(axis == vec3<f32>(0.0))) {
+    var helper = vec3<f32>(0.0, 1.0, 0.0);
+    if (abs(heading.x) < 0.9) {
+      helper = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    axis = safe_normalize(cross(heading, helper));
+  }
+  let turned = heading * cos(max_angle) + cross(axis, heading) * sin(max_angle);
+  return turned * speed;
+}
+
+@compute @workgroup_size(256)
+fn steer(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let slot = gid.x;
+  if (!alive_slot(slot)) {
+    return;
+  }
+  let p = particles[slot];
+  var separation = vec3<f32>(0.0);
+  var velocity_sum = vec3<f32>(0.0);
+  var aligned = 0u;
+  var position_sum = vec3<f32>(0.0);
+  var cohesive = 0u;
+  for (var n = 0u; n < GRID_NEIGHBOR_CELLS; n = n + 1u) {
+    let range = grid_neighbor_range(p.position, n);
+    for (var i = range.x; i < range.y; i = i + 1u) {
+      let other_slot = grid_sorted_slots[i];
+      if (other_slot == slot) {
+        continue;
+      }
+      let other = particles[other_slot];
+      let offset = p.position - other.position;
+      let distance = length(offset);
+      if (distance < boids.separation_radius) {
+        separation = separation
+          + safe_normalize(offset) * (1.0 - distance / boids.separation_radius);
+      }
+      if (distance < boids.alignment_radius) {
+        velocity_sum = velocity_sum + other.velocity;
+        aligned = aligned + 1u;
+      }
+      if (distance < boids.cohesion_radius) {
+        position_sum = position_sum + other.position;
+        cohesive = cohesive + 1u;
+      }
+    }
+  }
+
+  var accel = separation * boids.separation_weight;
+  if (aligned > 0u) {
+    accel = accel + (velocity_sum / f32(aligned) - p.velocity) * boids.alignment_weight;
+  }
+  if (cohesive > 0u) {
+    accel = accel + (position_sum / f32(cohesive) - p.position) * boids.cohesion_weight;
+  }
+  if (boids.attractor_weight != 0.0) {
+    for (var f = 0u; f < boids.force_field_count; f = f + 1u) {
+      let field = force_fields[f];
+      if (field.kind == FIELD_POINT) {
+        let to_center = field.position - p.position;
+        let weight = radial_weight(length(to_center), field.radius, field.falloff);
+        accel = accel + safe_normalize(to_center)
+          * (field.strength * weight * boids.attractor_weight);
+      }
+    }
+  }
+  steered[slot] = vec4<f32>(limit_steering(p.velocity, p.velocity + accel * boids.dt), 0.0);
+}
+
+@compute @workgroup_size(256)
+fn apply(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let slot = gid.x;
+  if (alive_slot(slot)) {
+    particles[slot].velocity = steered[slot].xyz;
+  }
+}
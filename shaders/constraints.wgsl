@@ -0,0 +1,111 @@
+// XPBD distance constraints of `ParticleSimMode::Pbd`, solved after `update` and `emit`.
+// `ConstraintSolver::solve_reference` in constraints.rs is the CPU mirror. Constraints are
+// sorted into batches sharing no particle; `solve` runs once per batch and iteration, with
+// the batch selected by the dynamic offset of group 1.
+
+struct Particle {
+  position : vec3<f32>,
+  age : f32,
+  velocity : vec3<f32>,
+  lifetime : f32,
+}
+
+// Mirrors `GpuConstraint` in constraints.rs; `kind` indexes `SolveParams::compliance`.
+struct Constraint {
+  a : u32,
+  b : u32,
+  rest_length : f32,
+  kind : u32,
+}
+
+// Mirrors `GpuSolveParams` in constraints.rs.
+struct SolveParams {
+  compliance : vec4<f32>,
+  dt : f32,
+  particle_count : u32,
+  constraint_count : u32,
+  _pad0 : u32,
+}
+
+// Mirrors `GpuBatch` in constraints.rs.
+struct Batch {
+  first : u32,
+  count : u32,
+  _pad0 : u32,
+  _pad1 : u32,
+}
+
+@group(0) @binding(0)
+var<storage, read_write> particles : array<Particle>;
+
+// Slots [0, particle_count) as they were before the step.
+@group(0) @binding(1)
+var<storage, read> previous : array<Particle>;
+
+@group(0) @binding(2)
+var<storage, read> inverse_masses : array<f32>;
+
+@group(0) @binding(3)
+var<storage, read> constraints : array<Constraint>;
+
+// Accumulated XPBD multipliers, reset every step.
+@group(0) @binding(4)
+var<storage, read_write> lambdas : array<f32>;
+
+@group(0) @binding(5)
+var<uniform> params : SolveParams;
+
+@group(1) @binding(0)
+var<uniform> batch : Batch;
+
+fn is_alive(slot: u32) -> bool {
+  return particles[slot].age < particles[slot].lifetime;
+}
+
+// Puts pinned particles back and clears the multipliers.
+@compute @workgroup_size(256)
+fn begin_solve(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let i = gid.x;
+  if (i < params.particle_count && inverse_masses[i] == 0.0) {
+    particles[i].position = previous[i].position;
+    particles[i].velocity = vec3<f32>(0.0);
+  }
+  if (i < params.constraint_count) {
+    lambdas[i] = 0.0;
+  }
+}
+
+@compute @workgroup_size(256)
+fn solve(@builtin(global_invocation_id) gid: vec3<u32>) {
+  if (gid.x >= batch.count) {
+    return;
+  }
+  let index = batch.first + gid.x;
+  let c = constraints[index];
+  let alpha = params.compliance[c.kind] / (params.dt * params.dt);
+  let wa = inverse_masses[c.a];
+  let wb = inverse_masses[c.b];
+  if (wa + wb == 0.0 || !is_alive(c.a) || !is_alive(c.b)) {
+    return;
+  }
+  let offset = particles[c.a].position - particles[c.b].position;
+  let current = length(offset);
+  if (current < 1e-6) {
+    return;
+  }
+  let error = current - c.rest_length;
+  let delta = (-error - alpha * lambdas[index]) / (wa + wb + alpha);
+  lambdas[index] = lambdas[index] + delta;
+  let push = offset * (delta / current);
+  particles[c.a].position = particles[c.a].position + push * wa;
+  particles[c.b].position = particles[c.b].position - push * wb;
+}
+
+// Velocities follow the solved positions.
+@compute @workgroup_size(256)
+fn finish_solve(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let i = gid.x;
+  if (i < params.particle_count && inverse_masses[i] > 0.0 && is_alive(i)) {
+    particles[i].velocity = (particles[i].position - previous[i].position) / params.dt;
+  }
+}
@@ -0,0 +1,16 @@
+// Over-lifetime LUT baked by `LifetimeLut::bake` in curves.rs. Row 0 holds the color multiplier
+// in rgb and opacity in a, row 1 the size and speed multipliers in x and y. Texels are loaded and
+// blended by hand because `Rgba32Float` is not filterable without an extra device feature.
+const LIFETIME_LUT_WIDTH : u32 = 64u;
+const LIFETIME_ROW_COLOR : u32 = 0u;
+const LIFETIME_ROW_MOTION : u32 = 1u;
+
+// Mirrors `LifetimeLut::sample_row` in curves.rs.
+fn sample_lifetime_lut(lut: texture_2d<f32>, row: u32, normalized_age: f32) -> vec4<f32> {
+  let x = clamp(normalized_age, 0.0, 1.0) * f32(LIFETIME_LUT_WIDTH - 1u);
+  let i0 = min(u32(x), LIFETIME_LUT_WIDTH - 2u);
+  let f = x - f32(i0);
+  let a = textureLoad(lut, vec2<u32>(i0, row), 0);
+  let b = textureLoad(lut, vec2<u32>(i0 + 1u, row), 0);
+  return a * (1.0 - f) + b * f;
+}
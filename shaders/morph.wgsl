@@ -0,0 +1,65 @@
+// Morph pass of `ParticleSimMode::Morph`, one thread per particle slot. `MorphConfig::
+// apply_reference` in morph.rs is the CPU mirror.
+
+struct Particle {
+  position : vec3<f32>,
+  age : f32,
+  velocity : vec3<f32>,
+  lifetime : f32,
+}
+
+// Mirrors `GpuMorphParams` in morph.rs; `phase` is a `MorphPhase`.
+struct MorphParams {
+  centroid : vec3<f32>,
+  phase : u32,
+  stiffness : f32,
+  damping : f32,
+  release_speed : f32,
+  dt : f32,
+  particle_count : u32,
+  _pad0 : u32,
+  _pad1 : u32,
+  _pad2 : u32,
+}
+
+const PHASE_HOLD : u32 = 0u;
+const PHASE_RELEASE : u32 = 1u;
+
+@group(0) @binding(0)
+var<storage, read_write> particles : array<Particle>;
+
+// Per slot: target in xyz, w = 1 when the slot has one.
+@group(0) @binding(1)
+var<storage, read> targets : array<vec4<f32>>;
+
+@group(0) @binding(2)
+var<uniform> morph : MorphParams;
+
+// Mirrors `normalize_or_zero` in math.rs.
+fn normalize_or_zero(v : vec3<f32>) -> vec3<f32> {
+  let len_sq = dot(v, v);
+  if (len_sq <= 1e-8) {
+    return vec3<f32>(0.0);
+  }
+  return v * inverseSqrt(len_sq);
+}
+
+@compute @workgroup_size(256)
+fn pull(@builtin(global_invocation_id) gid : vec3<u32>) {
+  let slot = gid.x;
+  if (slot >= morph.particle_count) {
+    return;
+  }
+  var p = particles[slot];
+  let target_slot = targets[slot];
+  if (p.age >= p.lifetime || target_slot.w == 0.0) {
+    return;
+  }
+  if (morph.phase == PHASE_HOLD) {
+    let accel = (target_slot.xyz - p.position) * morph.stiffness - p.velocity * morph.damping;
+    p.velocity = p.velocity + accel * morph.dt;
+  } else if (morph.phase == PHASE_RELEASE) {
+    p.velocity = p.velocity + normalize_or_zero(p.position - morph.centroid) * morph.release_speed;
+  }
+  particles[slot].velocity = p.velocity;
+}
@@ -1,3 +1,7 @@
+// `ParticleAttributes`, `PARTICLE_ATTRIBUTE_STRIDE`, `load_attributes` and `store_attributes`
+// are generated from the attribute schema and prepended by `ParticleGpuSim::init`, followed by
+// lifetime_lut.wgsl.
+
 struct Particle {
   position : vec3<f32>,
   age : f32,
@@ -5,17 +9,151 @@ struct Particle {
   lifetime : f32,
 }
 
+// Mirrors `ParticleAttributes` in attributes.rs: every attribute, whether stored or not.
+struct AttributeValues {
+  color : vec4<f32>,
+  size : f32,
+  rotation : f32,
+  angular_velocity : f32,
+  seed : u32,
+  user : array<f32, 4>,
+}
+
+// Mirrors `GpuEmitter` in gpu.rs; `params` packs the shape's scalars (radius, half extents,
+// cone angle, line length, ring thickness, image half extents) and `axis` its normal or axis.
+struct Emitter {
+  center : vec3<f32>,
+  shape : u32,
+  params : vec4<f32>,
+  axis : vec3<f32>,
+  mode : u32,
+  direction : vec3<f32>,
+  direction_kind : u32,
+  color : vec4<f32>,
+  size : f32,
+  size_variance : f32,
+  angular_speed : f32,
+  pixel_color : u32,
+}
+
+// Mirrors `GpuForceField` in forces.rs; see there for how each `kind` uses the params.
+struct ForceField {
+  position : vec3<f32>,
+  kind : u32,
+  axis : vec3<f32>,
+  strength : f32,
+  radius : f32,
+  falloff : f32,
+  param0 : f32,
+  param1 : f32,
+}
+
+// Mirrors `GpuCollider` in collision.rs; see there for how each `kind` uses the fields.
+struct Collider {
+  position : vec3<f32>,
+  kind : u32,
+  rotation : vec4<f32>,
+  extents : vec3<f32>,
+  restitution : f32,
+  friction : f32,
+  kill_on_contact : u32,
+  _pad0 : vec2<u32>,
+}
+
+// Mirrors `GpuSubEmitter` in sub_emitter.rs; bit `EVENT_*` of `triggers` enables that kind.
+struct SubEmitter {
+  emitter : Emitter,
+  lifetime : f32,
+  inherit_velocity : f32,
+  initial_speed : f32,
+  min_impact_speed : f32,
+  particles_per_event : u32,
+  triggers : u32,
+  _pad0 : vec2<u32>,
+}
+
 struct SimUniform {
   dt : f32,
+  // Exponential decay per second.
   drag : f32,
   spawn_rate : f32,
   lifetime : f32,
   gravity : vec3<f32>,
-  _pad0 : f32,
-  attractor : vec3<f32>,
-  attractor_strength : f32,
+  spawn_count : u32,
   noise_strength : f32,
-  _pad1 : vec3<f32>,
+  noise_frequency : f32,
+  noise_time_scale : f32,
+  noise_octaves : u32,
+  initial_speed : f32,
+  time : f32,
+  force_field_count : u32,
+  // Velocity already set by a prepass (boids, held morphs): skip forces and drag.
+  steered : u32,
+  sdf_bounds_min : vec3<f32>,
+  collider_count : u32,
+  sdf_bounds_max : vec3<f32>,
+  sdf_enabled : u32,
+  container_min : vec3<f32>,
+  container_enabled : u32,
+  container_max : vec3<f32>,
+  container_restitution : f32,
+  vector_field_bounds_min : vec3<f32>,
+  vector_field_enabled : u32,
+  vector_field_bounds_max : vec3<f32>,
+  // `INTEGRATOR_*`.
+  integrator : u32,
+  seed : u32,
+  // `NON_FINITE_*`.
+  non_finite_policy : u32,
+  _pad0 : u32,
+  _pad1 : u32,
+  emitter : Emitter,
+  sub_emitter : SubEmitter,
+}
+
+// Mirrors `GpuParticleCounters` in gpu.rs.
+struct Counters {
+  dead_count : atomic<u32>,
+  alive_count : u32,
+  alive_next : atomic<u32>,
+  list_parity : u32,
+  emit_count : u32,
+  emit_base : u32,
+}
+
+// Mirrors `GpuSimStats` in stats.rs. Bounds hold `order_key` values so they reduce as `u32`.
+struct Stats {
+  alive_count : u32,
+  spawned : u32,
+  died : atomic<u32>,
+  non_finite : atomic<u32>,
+  max_speed_bits : atomic<u32>,
+  bounds_min : array<atomic<u32>, 3>,
+  bounds_max : array<atomic<u32>, 3>,
+  // Never cleared by `begin_update`.
+  non_finite_total : atomic<u32>,
+}
+
+// Mirrors `ParticleEvent` in sub_emitter.rs.
+struct ParticleEvent {
+  position : vec3<f32>,
+  kind : u32,
+  velocity : vec3<f32>,
+  _pad0 : u32,
+}
+
+// Appended by `update`, consumed by `sub_emit`; `count` may exceed the capacity, which is
+// `arrayLength(&events.items)`, and the overflow is dropped.
+struct EventQueue {
+  count : atomic<u32>,
+  items : array<ParticleEvent>,
+}
+
+// Mirrors `GpuDispatchArgs` in gpu.rs; read back by `dispatch_workgroups_indirect`.
+struct DispatchArgs {
+  update : array<u32, 3>,
+  emit : array<u32, 3>,
+  sub_emit : array<u32, 3>,
 }
 
 @group(0) @binding(0)
@@ -24,6 +162,92 @@ var<storage, read_write> particles : array<Particle>;
 @group(0) @binding(1)
 var<uniform> sim : SimUniform;
 
+// [0, N) dead stack, then two alive lists of N entries each, selected by `list_parity`.
+// Alive-list entries of sub-emitted particles have `SUB_EMITTED_SLOT_BIT` set.
+@group(0) @binding(2)
+var<storage, read_write> indices : array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> counters : Counters;
+
+@group(0) @binding(4)
+var<storage, read_write> stats : Stats;
+
+// Shared with `MAX_FORCE_FIELDS` in forces.rs; only the first `sim.force_field_count` are live.
+const MAX_FORCE_FIELDS : u32 = 16u;
+
+@group(0) @binding(5)
+var<storage, read> force_fields : array<ForceField, MAX_FORCE_FIELDS>;
+
+// Shared with `MAX_COLLIDERS` in collision.rs; only the first `sim.collider_count` are live.
+const MAX_COLLIDERS : u32 = 16u;
+
+@group(0) @binding(6)
+var<storage, read> colliders : array<Collider, MAX_COLLIDERS>;
+
+// Signed distances on grid nodes spanning `sim.sdf_bounds_*`; loaded, never filtered.
+@group(0) @binding(7)
+var sdf_volume : texture_3d<f32>;
+
+@group(0) @binding(9)
+var lifetime_lut : texture_2d<f32>;
+
+// `SpawnImage` colors, and its row CDFs followed by the marginal CDF in row `height`; see
+// `SpawnImage::cdf_texels`.
+@group(0) @binding(11)
+var spawn_image : texture_2d<f32>;
+
+@group(0) @binding(12)
+var spawn_cdf : texture_2d<f32>;
+
+// `VectorFieldVolume` vectors at voxel centers spanning `sim.vector_field_bounds_*`; loaded,
+// never filtered.
+@group(0) @binding(13)
+var vector_field : texture_3d<f32>;
+
+// Indexed by particle slot, like `particles`; unused when `PARTICLE_ATTRIBUTE_STRIDE` is zero.
+@group(0) @binding(8)
+var<storage, read_write> attributes : array<ParticleAttributes>;
+
+@group(0) @binding(10)
+var<storage, read_write> events : EventQueue;
+
+// Only bound for the single-thread `begin_*` kernels: a buffer used for an indirect
+// dispatch cannot also be writable storage within that same dispatch. Their group 0 holds just
+// `sim`, `counters`, `stats` and `events`, so they must not touch the other bindings.
+@group(1) @binding(0)
+var<storage, read_write> dispatch_args : DispatchArgs;
+
+const WORKGROUP_SIZE : u32 = 256u;
+const SIGN_BIT : u32 = 0x80000000u;
+const EXPONENT_MASK : u32 = 0x7f800000u;
+const SUB_EMITTED_SLOT_BIT : u32 = 0x80000000u;
+const EVENT_DEATH : u32 = 0u;
+const EVENT_COLLISION : u32 = 1u;
+// Matches `NonFinitePolicy::gpu_kind` in watchdog.rs; 0 only counts.
+const NON_FINITE_KILL : u32 = 1u;
+const NON_FINITE_RESPAWN : u32 = 2u;
+
+// Per-workgroup partial stats, flushed to `stats` with one global atomic each. Workgroup memory
+// starts zeroed, so the minimum is kept as an inverted key and reduced with `atomicMax`.
+var<workgroup> wg_died : atomic<u32>;
+var<workgroup> wg_non_finite : atomic<u32>;
+var<workgroup> wg_max_speed_bits : atomic<u32>;
+var<workgroup> wg_bounds_min_inv : array<atomic<u32>, 3>;
+var<workgroup> wg_bounds_max : array<atomic<u32>, 3>;
+const TAU : f32 = 6.283185307179586;
+
+const SHAPE_SPHERE : u32 = 0u;
+const SHAPE_BOX : u32 = 1u;
+const SHAPE_DISC : u32 = 2u;
+const SHAPE_CONE : u32 = 3u;
+const SHAPE_LINE : u32 = 4u;
+const SHAPE_RING : u32 = 5u;
+const SHAPE_IMAGE : u32 = 6u;
+const MODE_SURFACE : u32 = 1u;
+const DIRECTION_ALONG : u32 = 1u;
+const DIRECTION_RANDOM : u32 = 2u;
+
 fn safe_normalize(v: vec3<f32>) -> vec3<f32> {
   let len_sq = dot(v, v);
   if (len_sq < 1e-8) {
@@ -32,30 +256,806 @@ fn safe_normalize(v: vec3<f32>) -> vec3<f32> {
   return v * inverseSqrt(len_sq);
 }
 
-@compute @workgroup_size(256)
-fn main(@builtin(global_invocation_id) gid: vec3<u32>) {
-  let i = gid.x;
-  if (i >= arrayLength(&particles)) {
+const INTEGRATOR_VERLET : u32 = 1u;
+const INTEGRATOR_RK4 : u32 = 2u;
+
+const FIELD_POINT : u32 = 0u;
+const FIELD_VORTEX : u32 = 1u;
+const FIELD_WIND : u32 = 2u;
+const FIELD_DRAG_ZONE : u32 = 3u;
+const FIELD_VECTOR_FORCE : u32 = 4u;
+const FIELD_VECTOR_VELOCITY : u32 = 5u;
+const WIND_GUST_TIME_SCALE : f32 = 1.0;
+
+fn radial_weight(distance: f32, radius: f32, falloff: f32) -> f32 {
+  if (radius <= 0.0) {
+    return 1.0;
+  }
+  let t = 1.0 - distance / radius;
+  if (t <= 0.0) {
+    return 0.0;
+  }
+  return pow(t, falloff);
+}
+
+// Mirrors `ForceField::acceleration` in forces.rs.
+fn vector_voxel(cell: vec3<u32>) -> vec3<f32> {
+  return textureLoad(vector_field, vec3<i32>(cell), 0).xyz;
+}
+
+// Mirrors `VectorFieldVolume::sample` in vector_field.rs: trilinear vector in xyz, w = 1 inside
+// the bounds and 0 outside.
+fn sample_vector_field(local: vec3<f32>) -> vec4<f32> {
+  if (sim.vector_field_enabled == 0u) {
+    return vec4<f32>(0.0);
+  }
+  let resolution = textureDimensions(vector_field);
+  let extent = sim.vector_field_bounds_max - sim.vector_field_bounds_min;
+  let uvw = (local - sim.vector_field_bounds_min) / extent;
+  if (any(extent <= vec3<f32>(0.0)) || any(uvw < vec3<f32>(0.0)) || any(uvw > vec3<f32>(1.0))) {
+    return vec4<f32>(0.0);
+  }
+  let last = resolution - vec3<u32>(1u);
+  let grid = clamp(uvw * vec3<f32>(resolution) - 0.5, vec3<f32>(0.0), vec3<f32>(last));
+  let cell = min(vec3<u32>(floor(grid)), max(resolution, vec3<u32>(2u)) - vec3<u32>(2u));
+  let f = grid - vec3<f32>(cell);
+  // Axes with a single voxel have `f` 0, so the clamped neighbor is never weighted.
+  let next = min(cell + vec3<u32>(1u), last);
+
+  let c000 = vector_voxel(cell);
+  let c100 = vector_voxel(vec3<u32>(next.x, cell.y, cell.z));
+  let c010 = vector_voxel(vec3<u32>(cell.x, next.y, cell.z));
+  let c110 = vector_voxel(vec3<u32>(next.x, next.y, cell.z));
+  let c001 = vector_voxel(vec3<u32>(cell.x, cell.y, next.z));
+  let c101 = vector_voxel(vec3<u32>(next.x, cell.y, next.z));
+  let c011 = vector_voxel(vec3<u32>(cell.x, next.y, next.z));
+  let c111 = vector_voxel(next);
+  let vector = mix(
+    mix(mix(c000, c100, f.x), mix(c010, c110, f.x), f.y),
+    mix(mix(c001, c101, f.x), mix(c011, c111, f.x), f.y),
+    f.z,
+  );
+  return vec4<f32>(vector, 1.0);
+}
+
+fn force_field_accel(
+  field: ForceField,
+  position: vec3<f32>,
+  velocity: vec3<f32>,
+  time: f32,
+) -> vec3<f32> {
+  switch field.kind {
+    case FIELD_POINT: {
+      let to_center = field.position - position;
+      let weight = radial_weight(length(to_center), field.radius, field.falloff);
+      return safe_normalize(to_center) * (field.strength * weight);
+    }
+    case FIELD_VORTEX: {
+      let axis = safe_normalize(field.axis);
+      let offset = position - field.position;
+      let radial = offset - axis * dot(offset, axis);
+      let weight = radial_weight(length(radial), field.radius, field.falloff);
+      let outward = safe_normalize(radial);
+      return cross(axis, outward) * (field.strength * weight)
+        + outward * (-field.param0 * weight);
+    }
+    case FIELD_WIND: {
+      let gust = curl_noise(position, time * WIND_GUST_TIME_SCALE, field.param1, 1u);
+      return safe_normalize(field.axis) * field.strength + gust * field.param0;
+    }
+    case FIELD_DRAG_ZONE: {
+      let weight = radial_weight(length(position - field.position), field.radius, field.falloff);
+      return velocity * (-field.strength * weight);
+    }
+    case FIELD_VECTOR_FORCE, FIELD_VECTOR_VELOCITY: {
+      let rotation = vec4<f32>(field.axis, field.falloff);
+      let inverse = vec4<f32>(-field.axis, field.falloff);
+      let local = rotate(inverse, position - field.position) * (1.0 / field.radius);
+      let sample = sample_vector_field(local);
+      if (sample.w == 0.0) {
+        return vec3<f32>(0.0);
+      }
+      let vector = rotate(rotation, sample.xyz) * field.strength;
+      if (field.kind == FIELD_VECTOR_FORCE) {
+        return vector;
+      }
+      return (vector - velocity) * field.param0;
+    }
+    default: {
+      return vec3<f32>(0.0);
+    }
+  }
+}
+
+const COLLIDER_PLANE : u32 = 0u;
+const COLLIDER_SPHERE : u32 = 1u;
+const COLLIDER_BOX : u32 = 2u;
+const COLLIDER_SDF : u32 = 3u;
+const SDF_OUTSIDE_DISTANCE : f32 = 1.0e6;
+
+fn normal_or_y(v: vec3<f32>) -> vec3<f32> {
+  let n = safe_normalize(v);
+  if (all(n == vec3<f32>(0.0))) {
+    return vec3<f32>(0.0, 1.0, 0.0);
+  }
+  return n;
+}
+
+fn rotate(q: vec4<f32>, v: vec3<f32>) -> vec3<f32> {
+  let t = 2.0 * cross(q.xyz, v);
+  return v + t * q.w + cross(q.xyz, t);
+}
+
+fn box_distance(local: vec3<f32>, half_extents: vec3<f32>) -> vec4<f32> {
+  let q = abs(local) - half_extents;
+  let sign = select(vec3<f32>(1.0), vec3<f32>(-1.0), local < vec3<f32>(0.0));
+  let outside = max(q, vec3<f32>(0.0));
+  let outside_len = length(outside);
+  if (outside_len > 0.0) {
+    return vec4<f32>(sign * outside / outside_len, outside_len);
+  }
+  var axis = 0u;
+  for (var i = 1u; i < 3u; i = i + 1u) {
+    if (q[i] > q[axis]) {
+      axis = i;
+    }
+  }
+  var normal = vec3<f32>(0.0);
+  normal[axis] = sign[axis];
+  return vec4<f32>(normal, q[axis]);
+}
+
+fn sdf_node(cell: vec3<u32>, offset: vec3<u32>) -> f32 {
+  return textureLoad(sdf_volume, vec3<i32>(cell + offset), 0).r;
+}
+
+// Mirrors `SdfVolume::sample` in collision.rs.
+fn sample_sdf(position: vec3<f32>) -> vec4<f32> {
+  let far = vec4<f32>(0.0, 1.0, 0.0, SDF_OUTSIDE_DISTANCE);
+  let resolution = textureDimensions(sdf_volume);
+  let extent = sim.sdf_bounds_max - sim.sdf_bounds_min;
+  let last = vec3<f32>(resolution - vec3<u32>(1u));
+  if (any(extent <= vec3<f32>(0.0))) {
+    return far;
+  }
+  let grid = (position - sim.sdf_bounds_min) / extent * last;
+  if (any(grid < vec3<f32>(0.0)) || any(grid > last)) {
+    return far;
+  }
+  let cell = min(vec3<u32>(floor(grid)), resolution - vec3<u32>(2u));
+  let f = grid - vec3<f32>(cell);
+  let scale = last / extent;
+
+  let c000 = sdf_node(cell, vec3<u32>(0u, 0u, 0u));
+  let c100 = sdf_node(cell, vec3<u32>(1u, 0u, 0u));
+  let c010 = sdf_node(cell, vec3<u32>(0u, 1u, 0u));
+  let c110 = sdf_node(cell, vec3<u32>(1u, 1u, 0u));
+  let c001 = sdf_node(cell, vec3<u32>(0u, 0u, 1u));
+  let c101 = sdf_node(cell, vec3<u32>(1u, 0u, 1u));
+  let c011 = sdf_node(cell, vec3<u32>(0u, 1u, 1u));
+  let c111 = sdf_node(cell, vec3<u32>(1u, 1u, 1u));
+
+  let distance = mix(
+    mix(mix(c000, c100, f.x), mix(c010, c110, f.x), f.y),
+    mix(mix(c001, c101, f.x), mix(c011, c111, f.x), f.y),
+    f.z,
+  );
+  let gradient = vec3<f32>(
+    mix(mix(c100 - c000, c110 - c010, f.y), mix(c101 - c001, c111 - c011, f.y), f.z),
+    mix(mix(c010 - c000, c110 - c100, f.x), mix(c011 - c001, c111 - c101, f.x), f.z),
+    mix(mix(c001 - c000, c101 - c100, f.x), mix(c011 - c010, c111 - c110, f.x), f.y),
+  ) * scale;
+  return vec4<f32>(normal_or_y(gradient), distance);
+}
+
+// Mirrors `Collider::distance` in collision.rs: outward normal in xyz, signed distance in w.
+fn collider_distance(c: Collider, position: vec3<f32>) -> vec4<f32> {
+  switch c.kind {
+    case COLLIDER_PLANE: {
+      return vec4<f32>(c.rotation.xyz, dot(position - c.position, c.rotation.xyz));
+    }
+    case COLLIDER_SPHERE: {
+      let offset = position - c.position;
+      return vec4<f32>(normal_or_y(offset), length(offset) - c.extents.x);
+    }
+    case COLLIDER_BOX: {
+      let inverse = vec4<f32>(-c.rotation.xyz, c.rotation.w);
+      let local = box_distance(rotate(inverse, position - c.position), c.extents);
+      return vec4<f32>(rotate(c.rotation, local.xyz), local.w);
+    }
+    case COLLIDER_SDF: {
+      if (sim.sdf_enabled == 0u) {
+        return vec4<f32>(0.0, 1.0, 0.0, SDF_OUTSIDE_DISTANCE);
+      }
+      return sample_sdf(position);
+    }
+    default: {
+      return vec4<f32>(0.0, 1.0, 0.0, SDF_OUTSIDE_DISTANCE);
+    }
+  }
+}
+
+// Returned by `resolve_collisions` when a kill collider was touched.
+const COLLISION_KILLED : f32 = -1.0;
+
+// Mirrors `resolve_collisions` in collision.rs. Returns the largest approach speed along a
+// contact normal, zero without an impact, or `COLLISION_KILLED`.
+fn resolve_collisions(p: ptr<function, Particle>) -> f32 {
+  var impact_speed = 0.0;
+  for (var i = 0u; i < sim.collider_count; i = i + 1u) {
+    let c = colliders[i];
+    let hit = collider_distance(c, (*p).position);
+    if (hit.w >= 0.0) {
+      continue;
+    }
+    if (c.kill_on_contact != 0u) {
+      return COLLISION_KILLED;
+    }
+    (*p).position = (*p).position - hit.xyz * hit.w;
+    let normal_speed = dot((*p).velocity, hit.xyz);
+    if (normal_speed < 0.0) {
+      impact_speed = max(impact_speed, -normal_speed);
+      let normal_velocity = hit.xyz * normal_speed;
+      let tangent_velocity = (*p).velocity - normal_velocity;
+      (*p).velocity = tangent_velocity * (1.0 - c.friction) - normal_velocity * c.restitution;
+    }
+  }
+  return impact_speed;
+}
+
+// Mirrors `SphConfig::contain` in sph.rs: clamps into the container and reflects the outward
+// velocity component.
+fn contain(p: ptr<function, Particle>) {
+  let below = (*p).position < sim.container_min;
+  let above = (*p).position > sim.container_max;
+  let outward = (below & ((*p).velocity < vec3<f32>(0.0)))
+    | (above & ((*p).velocity > vec3<f32>(0.0)));
+  (*p).position = clamp((*p).position, sim.container_min, sim.container_max);
+  (*p).velocity = select(
+    (*p).velocity,
+    (*p).velocity * -sim.container_restitution,
+    outward,
+  );
+}
+
+struct EmitterSample {
+  position : vec3<f32>,
+  direction : vec3<f32>,
+  tint : vec4<f32>,
+}
+
+const SEED_STRIDE : u32 = 0x9e3779b9u;
+
+// Mirrors `spawn_hash_key` in emitter.rs.
+fn spawn_hash_key(slot: u32, remaining: u32, stride: u32) -> u32 {
+  return slot + remaining * stride + sim.seed * SEED_STRIDE;
+}
+
+// Mirrors `spawn_randoms` in emitter.rs.
+fn spawn_randoms(slot: u32, remaining: u32) -> array<f32, 6> {
+  return array<f32, 6>(
+    hash01(spawn_hash_key(slot, remaining, 17u)),
+    hash01(spawn_hash_key(slot, remaining, 73u)),
+    hash01(spawn_hash_key(slot, remaining, 193u)),
+    hash01(spawn_hash_key(slot, remaining, 311u)),
+    hash01(spawn_hash_key(slot, remaining, 467u)),
+    hash01(spawn_hash_key(slot, remaining, 619u)),
+  );
+}
+
+fn unit_sphere(a: f32, b: f32) -> vec3<f32> {
+  let z = 1.0 - 2.0 * a;
+  let xy = sqrt(max(1.0 - z * z, 0.0));
+  let phi = TAU * b;
+  return vec3<f32>(xy * cos(phi), xy * sin(phi), z);
+}
+
+fn axis_or_z(v: vec3<f32>) -> vec3<f32> {
+  let axis = safe_normalize(v);
+  if (all(axis == vec3<f32>(0.0))) {
+    return vec3<f32>(0.0, 0.0, 1.0);
+  }
+  return axis;
+}
+
+fn circle_direction(n: vec3<f32>, t: f32) -> vec3<f32> {
+  let sign = select(-1.0, 1.0, n.z >= 0.0);
+  let a = -1.0 / (sign + n.z);
+  let b = n.x * n.y * a;
+  let tangent = vec3<f32>(1.0 + sign * n.x * n.x * a, sign * b, -sign * n.x);
+  let bitangent = vec3<f32>(b, sign + n.y * n.y * a, -n.y);
+  let angle = TAU * t;
+  return tangent * cos(angle) + bitangent * sin(angle);
+}
+
+fn box_surface(h: vec3<f32>, r: array<f32, 6>) -> EmitterSample {
+  let areas = vec3<f32>(h.y * h.z, h.x * h.z, h.x * h.y);
+  var pick = r[3] * (areas.x + areas.y + areas.z);
+  var axis = 2u;
+  for (var i = 0u; i < 3u; i = i + 1u) {
+    if (pick < areas[i]) {
+      axis = i;
+      break;
+    }
+    pick = pick - areas[i];
+  }
+  var side = 0.0;
+  if (areas[axis] > 0.0) {
+    side = pick / areas[axis];
+  }
+  let sign = select(1.0, -1.0, side < 0.5);
+
+  var out : EmitterSample;
+  out.position[axis] = sign * h[axis];
+  out.position[(axis + 1u) % 3u] = (2.0 * r[0] - 1.0) * h[(axis + 1u) % 3u];
+  out.position[(axis + 2u) % 3u] = (2.0 * r[1] - 1.0) * h[(axis + 2u) % 3u];
+  out.direction = vec3<f32>(0.0);
+  out.direction[axis] = sign;
+  return out;
+}
+
+// Largest f32 below 1; mirrors `BELOW_ONE` in spawn_image.rs.
+const BELOW_ONE : f32 = 0.99999994;
+
+// First entry of CDF row `row` (of `len` entries) exceeding `u`; mirrors `search_cdf` in
+// spawn_image.rs.
+fn search_cdf(row: u32, len: u32, u: f32) -> u32 {
+  let v = min(u, BELOW_ONE);
+  var lo = 0u;
+  var hi = len - 1u;
+  while (lo < hi) {
+    let mid = (lo + hi) / 2u;
+    if (textureLoad(spawn_cdf, vec2<u32>(mid, row), 0).x > v) {
+      hi = mid;
+    } else {
+      lo = mid + 1u;
+    }
+  }
+  return lo;
+}
+
+// Mirrors `SpawnImage::sample` in spawn_image.rs: returns the position in [0, 1]², y down the
+// rows, and writes the pixel color to `color`.
+fn sample_spawn_image(r: array<f32, 6>, color: ptr<function, vec4<f32>>) -> vec2<f32> {
+  let size = textureDimensions(spawn_image);
+  let row = search_cdf(size.y, size.y, r[0]);
+  let column = search_cdf(row, size.x, r[1]);
+  *color = textureLoad(spawn_image, vec2<u32>(column, row), 0);
+  return vec2<f32>((f32(column) + r[2]) / f32(size.x), (f32(row) + r[3]) / f32(size.y));
+}
+
+// Mirrors `sample_emitter` in emitter.rs. `position` is relative to the emitter center.
+fn sample_shape(e: Emitter, r: array<f32, 6>) -> EmitterSample {
+  let surface = e.mode == MODE_SURFACE;
+  var out : EmitterSample;
+  out.tint = vec4<f32>(1.0);
+  switch e.shape {
+    case SHAPE_BOX: {
+      if (surface) {
+        out = box_surface(e.params.xyz, r);
+      } else {
+        out.position = (2.0 * vec3<f32>(r[0], r[1], r[2]) - 1.0) * e.params.xyz;
+        out.direction = safe_normalize(out.position);
+      }
+    }
+    case SHAPE_DISC: {
+      let radial = circle_direction(axis_or_z(e.axis), r[0]);
+      let dist = select(e.params.x * sqrt(r[1]), e.params.x, surface);
+      out.position = radial * dist;
+      out.direction = radial;
+    }
+    case SHAPE_CONE: {
+      let axis = axis_or_z(e.axis);
+      let radial = circle_direction(axis, r[0]);
+      let rim_fraction = select(sqrt(r[1]), 1.0, surface);
+      let tilt = e.params.y * rim_fraction;
+      out.position = radial * (e.params.x * rim_fraction);
+      out.direction = axis * cos(tilt) + radial * sin(tilt);
+    }
+    case SHAPE_LINE: {
+      let axis = axis_or_z(e.axis);
+      out.position = axis * (e.params.x * (r[0] - 0.5));
+      out.direction = axis;
+    }
+    case SHAPE_RING: {
+      let axis = axis_or_z(e.axis);
+      let radial = circle_direction(axis, r[0]);
+      let tube = select(e.params.y * sqrt(r[1]), e.params.y, surface);
+      let tube_angle = TAU * r[2];
+      out.position = radial * (e.params.x + tube * cos(tube_angle)) + axis * (tube * sin(tube_angle));
+      out.direction = radial;
+    }
+    case SHAPE_IMAGE: {
+      var color : vec4<f32>;
+      let uv = sample_spawn_image(r, &color);
+      if (e.pixel_color != 0u) {
+        out.tint = color;
+      }
+      out.position = vec3<f32>(
+        (2.0 * uv.x - 1.0) * e.params.x,
+        (1.0 - 2.0 * uv.y) * e.params.y,
+        0.0,
+      );
+      out.direction = vec3<f32>(0.0, 0.0, 1.0);
+    }
+    default: {
+      let dir = unit_sphere(r[0], r[1]);
+      out.position = dir * select(e.params.x * pow(r[2], 1.0 / 3.0), e.params.x, surface);
+      out.direction = dir;
+    }
+  }
+  return out;
+}
+
+fn sample_emitter(e: Emitter, r: array<f32, 6>) -> EmitterSample {
+  var out = sample_shape(e, r);
+  out.position = e.center + out.position;
+  if (e.direction_kind == DIRECTION_ALONG) {
+    out.direction = safe_normalize(e.direction);
+  } else if (e.direction_kind == DIRECTION_RANDOM) {
+    out.direction = unit_sphere(r[4], r[5]);
+  }
+  return out;
+}
+
+// Mirrors `ParticleState::spawn`.
+fn spawn_particle(sample: EmitterSample) -> Particle {
+  var p : Particle;
+  p.position = sample.position;
+  p.age = 0.0;
+  p.velocity = sample.direction * sim.initial_speed;
+  p.lifetime = sim.lifetime;
+  return p;
+}
+
+// Matches `ParticleAttributes::default` in attributes.rs.
+fn default_attributes() -> AttributeValues {
+  var v : AttributeValues;
+  v.color = vec4<f32>(1.0);
+  v.size = 1.0;
+  return v;
+}
+
+// Mirrors `ParticleAttributes::spawn` in attributes.rs.
+fn spawn_attributes(e: Emitter, tint: vec4<f32>, slot: u32, remaining: u32) -> AttributeValues {
+  let seed = hash_u32(spawn_hash_key(slot, remaining, 829u));
+  var v = default_attributes();
+  v.color = e.color * tint;
+  v.size = max(e.size * (1.0 + e.size_variance * (2.0 * hash01(seed) - 1.0)), 0.0);
+  v.rotation = TAU * hash01(seed + 1u);
+  v.angular_velocity = e.angular_speed * (2.0 * hash01(seed + 2u) - 1.0);
+  v.seed = seed;
+  return v;
+}
+
+// Mirrors `SubEmitterConfig::spawn` in sub_emitter.rs.
+fn spawn_child(event: ParticleEvent, sample: EmitterSample) -> Particle {
+  let sub = sim.sub_emitter;
+
+  var p : Particle;
+  p.position = event.position + sample.position;
+  p.age = 0.0;
+  p.velocity = event.velocity * sub.inherit_velocity + sample.direction * sub.initial_speed;
+  p.lifetime = sub.lifetime;
+  return p;
+}
+
+// Mirrors `SubEmitterConfig::record` in sub_emitter.rs, except that which events overflow the
+// queue depends on thread scheduling.
+fn push_event(p: Particle, kind: u32) {
+  if ((sim.sub_emitter.triggers & (1u << kind)) == 0u) {
     return;
   }
+  let index = atomicAdd(&events.count, 1u);
+  if (index < arrayLength(&events.items)) {
+    events.items[index] = ParticleEvent(p.position, kind, p.velocity, 0u);
+  }
+}
 
-  var p = particles[i];
-  if (p.age >= p.lifetime) {
-    particles[i] = p;
+fn queued_events() -> u32 {
+  return min(atomicLoad(&events.count), arrayLength(&events.items));
+}
+
+fn alive_in_base() -> u32 {
+  return arrayLength(&particles) * (1u + counters.list_parity);
+}
+
+fn alive_out_base() -> u32 {
+  return arrayLength(&particles) * (2u - counters.list_parity);
+}
+
+fn group_count(items: u32) -> u32 {
+  return (items + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+}
+
+fn is_finite3(v: vec3<f32>) -> bool {
+  let exponents = bitcast<vec3<u32>>(v) & vec3<u32>(EXPONENT_MASK);
+  return all(exponents != vec3<u32>(EXPONENT_MASK));
+}
+
+// Maps floats to `u32` keys with the same ordering; see `order_key` in stats.rs.
+fn order_key(value: f32) -> u32 {
+  let bits = bitcast<u32>(value);
+  if ((bits & SIGN_BIT) != 0u) {
+    return ~bits;
+  }
+  return bits | SIGN_BIT;
+}
+
+fn record_alive(p: Particle) {
+  if (!is_finite3(p.position) || !is_finite3(p.velocity)) {
+    atomicAdd(&wg_non_finite, 1u);
     return;
   }
+  atomicMax(&wg_max_speed_bits, bitcast<u32>(length(p.velocity)));
+  for (var axis = 0u; axis < 3u; axis = axis + 1u) {
+    let key = order_key(p.position[axis]);
+    atomicMax(&wg_bounds_min_inv[axis], ~key);
+    atomicMax(&wg_bounds_max[axis], key);
+  }
+}
+
+// Called by one invocation after a `workgroupBarrier`.
+fn flush_workgroup_stats() {
+  atomicAdd(&stats.died, atomicLoad(&wg_died));
+  atomicAdd(&stats.non_finite, atomicLoad(&wg_non_finite));
+  atomicAdd(&stats.non_finite_total, atomicLoad(&wg_non_finite));
+  atomicMax(&stats.max_speed_bits, atomicLoad(&wg_max_speed_bits));
+  for (var axis = 0u; axis < 3u; axis = axis + 1u) {
+    atomicMin(&stats.bounds_min[axis], ~atomicLoad(&wg_bounds_min_inv[axis]));
+    atomicMax(&stats.bounds_max[axis], atomicLoad(&wg_bounds_max[axis]));
+  }
+}
+
+fn kill_particle(slot: u32, p: Particle) {
+  particles[slot] = p;
+  indices[atomicAdd(&counters.dead_count, 1u)] = slot;
+  atomicAdd(&wg_died, 1u);
+}
+
+// Everything but drag, `t` seconds into the step; mirrors the closure in
+// `ParticleState::step_reference`.
+fn acceleration(position: vec3<f32>, velocity: vec3<f32>, t: f32) -> vec3<f32> {
+  let time = sim.time + t;
+  var fields = vec3<f32>(0.0);
+  for (var i = 0u; i < sim.force_field_count; i = i + 1u) {
+    fields = fields + force_field_accel(force_fields[i], position, velocity, time);
+  }
+  let swirl = curl_noise(
+    position,
+    time * sim.noise_time_scale,
+    sim.noise_frequency,
+    sim.noise_octaves,
+  ) * sim.noise_strength;
+  return sim.gravity + fields + swirl;
+}
+
+fn dragged_acceleration(position: vec3<f32>, velocity: vec3<f32>, t: f32) -> vec3<f32> {
+  return acceleration(position, velocity, t) - velocity * sim.drag;
+}
+
+// Mirrors `Integrator::step` in integrator.rs; positions move at `speed` times the velocity.
+fn integrate(p: ptr<function, Particle>, speed: f32) {
+  let dt = sim.dt;
+  let x = (*p).position;
+  let v = (*p).velocity;
+  switch sim.integrator {
+    case INTEGRATOR_VERLET: {
+      let a0 = dragged_acceleration(x, v, 0.0);
+      let next_position = x + (v + a0 * (0.5 * dt)) * (speed * dt);
+      let a1 = dragged_acceleration(next_position, v + a0 * dt, dt);
+      (*p).position = next_position;
+      (*p).velocity = v + (a0 + a1) * (0.5 * dt);
+    }
+    case INTEGRATOR_RK4: {
+      let half = 0.5 * dt;
+      let k1x = v * speed;
+      let k1v = dragged_acceleration(x, v, 0.0);
+      let k2x = (v + k1v * half) * speed;
+      let k2v = dragged_acceleration(x + k1x * half, v + k1v * half, half);
+      let k3x = (v + k2v * half) * speed;
+      let k3v = dragged_acceleration(x + k2x * half, v + k2v * half, half);
+      let k4x = (v + k3v * dt) * speed;
+      let k4v = dragged_acceleration(x + k3x * dt, v + k3v * dt, dt);
+      (*p).position = x + ((k1x + k4x) + (k2x + k3x) * 2.0) * (dt / 6.0);
+      (*p).velocity = v + ((k1v + k4v) + (k2v + k3v) * 2.0) * (dt / 6.0);
+    }
+    default: {
+      let velocity = v * exp(-sim.drag * dt) + acceleration(x, v, 0.0) * dt;
+      (*p).position = x + velocity * (speed * dt);
+      (*p).velocity = velocity;
+    }
+  }
+}
 
+fn update_particle(k: u32) {
+  let entry = indices[alive_in_base() + k];
+  let slot = entry & ~SUB_EMITTED_SLOT_BIT;
+  // Sub-emitted particles never raise events, so chains cannot cascade.
+  let raises_events = (entry & SUB_EMITTED_SLOT_BIT) == 0u;
+  var p = particles[slot];
   p.age = p.age + sim.dt;
   if (p.age >= p.lifetime) {
-    particles[i] = p;
+    kill_particle(slot, p);
+    if (raises_events) {
+      push_event(p, EVENT_DEATH);
+    }
+    return;
+  }
+
+  let motion = sample_lifetime_lut(lifetime_lut, LIFETIME_ROW_MOTION, p.age / p.lifetime);
+  // Boids and held morphs arrive already steered by boids.wgsl or morph.wgsl; only move them.
+  if (sim.steered == 0u) {
+    integrate(&p, motion.y);
+  } else {
+    p.position = p.position + p.velocity * (motion.y * sim.dt);
+  }
+  let impact_speed = resolve_collisions(&p);
+  if (impact_speed == COLLISION_KILLED) {
+    p.age = p.lifetime;
+    kill_particle(slot, p);
+    if (raises_events) {
+      push_event(p, EVENT_COLLISION);
+    }
+    return;
+  }
+  if (raises_events && impact_speed > 0.0 && impact_speed >= sim.sub_emitter.min_impact_speed) {
+    push_event(p, EVENT_COLLISION);
+  }
+  if (sim.container_enabled != 0u) {
+    contain(&p);
+  }
+  // Left in place, `record_alive` counts non-finite particles; the other policies count here.
+  if (sim.non_finite_policy != 0u && (!is_finite3(p.position) || !is_finite3(p.velocity))) {
+    atomicAdd(&wg_non_finite, 1u);
+    if (sim.non_finite_policy == NON_FINITE_RESPAWN) {
+      // Remaining count 0 is a stream regular spawns never draw from.
+      spawn_primary(slot, 0u);
+    } else {
+      p.age = p.lifetime;
+      kill_particle(slot, p);
+    }
     return;
   }
+  particles[slot] = p;
+  record_alive(p);
+  if (PARTICLE_ATTRIBUTE_STRIDE > 0u) {
+    var a = load_attributes(slot);
+    a.rotation = a.rotation + a.angular_velocity * sim.dt;
+    store_attributes(slot, a);
+  }
+
+  indices[alive_out_base() + atomicAdd(&counters.alive_next, 1u)] = entry;
+}
+
+// Fills `slot` from the primary emitter and lists it alive; mirrors `spawned` in simulation.rs.
+fn spawn_primary(slot: u32, remaining: u32) {
+  let sample = sample_emitter(sim.emitter, spawn_randoms(slot, remaining));
+  let p = spawn_particle(sample);
+  particles[slot] = p;
+  if (PARTICLE_ATTRIBUTE_STRIDE > 0u) {
+    store_attributes(slot, spawn_attributes(sim.emitter, sample.tint, slot, remaining));
+  }
+  record_alive(p);
+  indices[alive_out_base() + atomicAdd(&counters.alive_next, 1u)] = slot;
+}
+
+fn emit_particle(k: u32) {
+  let slot = indices[counters.emit_base + counters.emit_count - 1u - k];
+  // Spawn count still owed when this slot is filled, as in `ParticleState::spawn`.
+  spawn_primary(slot, sim.spawn_count - k);
+}
+
+// Thread `k` spawns child `k % particles_per_event` of event `k / particles_per_event`; mirrors
+// `ParticleState::spawn_sub_emitted`.
+fn sub_emit_particle(k: u32) {
+  let per_event = sim.sub_emitter.particles_per_event;
+  let requested = queued_events() * per_event;
+  let slot = indices[counters.emit_base + counters.emit_count - 1u - k];
+  let sample = sample_emitter(sim.sub_emitter.emitter, spawn_randoms(slot, requested - k));
+  let p = spawn_child(events.items[k / per_event], sample);
+  particles[slot] = p;
+  if (PARTICLE_ATTRIBUTE_STRIDE > 0u) {
+    store_attributes(
+      slot,
+      spawn_attributes(sim.sub_emitter.emitter, sample.tint, slot, requested - k),
+    );
+  }
+  record_alive(p);
+  indices[alive_out_base() + atomicAdd(&counters.alive_next, 1u)] = slot | SUB_EMITTED_SLOT_BIT;
+}
 
-  let to_attr = sim.attractor - p.position;
-  let attraction = safe_normalize(to_attr) * sim.attractor_strength;
-  let accel = sim.gravity + attraction;
-  p.velocity = p.velocity * sim.drag + accel * sim.dt;
-  p.position = p.position + p.velocity * sim.dt;
+// Last step's output list becomes this step's input list; also clears the step's stats.
+@compute @workgroup_size(1)
+fn begin_update() {
+  let alive = atomicLoad(&counters.alive_next);
+  counters.alive_count = alive;
+  atomicStore(&counters.alive_next, 0u);
+  counters.list_parity = 1u - counters.list_parity;
+  dispatch_args.update = array<u32, 3>(group_count(alive), 1u, 1u);
+  atomicStore(&events.count, 0u);
 
-  particles[i] = p;
+  stats.alive_count = 0u;
+  stats.spawned = 0u;
+  atomicStore(&stats.died, 0u);
+  atomicStore(&stats.non_finite, 0u);
+  atomicStore(&stats.max_speed_bits, 0u);
+  for (var axis = 0u; axis < 3u; axis = axis + 1u) {
+    atomicStore(&stats.bounds_min[axis], 0xffffffffu);
+    atomicStore(&stats.bounds_max[axis], 0u);
+  }
+}
+
+@compute @workgroup_size(256)
+fn update(
+  @builtin(global_invocation_id) gid: vec3<u32>,
+  @builtin(local_invocation_index) lid: u32,
+) {
+  if (gid.x < counters.alive_count) {
+    update_particle(gid.x);
+  }
+
+  workgroupBarrier();
+  if (lid == 0u) {
+    flush_workgroup_stats();
+  }
+}
+
+// Reserves the top `emit_count` entries of the dead stack so `emit` can pop them without atomics.
+@compute @workgroup_size(1)
+fn begin_emit() {
+  let dead = atomicLoad(&counters.dead_count);
+  let emit = min(sim.spawn_count, dead);
+  counters.emit_count = emit;
+  counters.emit_base = dead - emit;
+  atomicStore(&counters.dead_count, dead - emit);
+  dispatch_args.emit = array<u32, 3>(group_count(emit), 1u, 1u);
+
+  stats.spawned = emit;
+  stats.alive_count = atomicLoad(&counters.alive_next) + emit;
+}
+
+@compute @workgroup_size(256)
+fn emit(
+  @builtin(global_invocation_id) gid: vec3<u32>,
+  @builtin(local_invocation_index) lid: u32,
+) {
+  if (gid.x < counters.emit_count) {
+    emit_particle(gid.x);
+  }
+
+  workgroupBarrier();
+  if (lid == 0u) {
+    flush_workgroup_stats();
+  }
+}
+
+// Same reservation as `begin_emit`, for the children of this step's events. Reuses the emit
+// range, which `emit` no longer needs.
+@compute @workgroup_size(1)
+fn begin_sub_emit() {
+  let dead = atomicLoad(&counters.dead_count);
+  let emit = min(queued_events() * sim.sub_emitter.particles_per_event, dead);
+  counters.emit_count = emit;
+  counters.emit_base = dead - emit;
+  atomicStore(&counters.dead_count, dead - emit);
+  dispatch_args.sub_emit = array<u32, 3>(group_count(emit), 1u, 1u);
+
+  stats.spawned = stats.spawned + emit;
+  stats.alive_count = stats.alive_count + emit;
+}
+
+@compute @workgroup_size(256)
+fn sub_emit(
+  @builtin(global_invocation_id) gid: vec3<u32>,
+  @builtin(local_invocation_index) lid: u32,
+) {
+  if (gid.x < counters.emit_count) {
+    sub_emit_particle(gid.x);
+  }
+
+  workgroupBarrier();
+  if (lid == 0u) {
+    flush_workgroup_stats();
+  }
 }
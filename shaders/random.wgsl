@@ -0,0 +1,93 @@
+// Deterministic randomness shared with the CPU reference: `hash_u32` and `hash01` are
+// bit-identical to math.rs and `curl_noise` mirrors noise.rs. Exposed to Rust as `RANDOM_WGSL`.
+
+// Must stay bit-identical to `hash_u32` in math.rs.
+fn hash_u32(seed: u32) -> u32 {
+  var x = seed * 747796405u + 2891336453u;
+  x = x ^ (x >> 16u);
+  x = x * 2246822519u;
+  return x ^ (x >> 13u);
+}
+
+fn hash01(seed: u32) -> f32 {
+  return f32(hash_u32(seed)) / 4294967295.0;
+}
+
+// Curl noise: mirrors noise.rs, including lattice hash constants and per-channel offsets.
+const MAX_NOISE_OCTAVES : u32 = 6u;
+const TIME_DRIFT : vec3<f32> = vec3<f32>(0.31, 0.71, -0.53);
+
+fn lattice_value(cell: vec3<i32>, seed: u32) -> f32 {
+  var h = bitcast<u32>(cell.x) * 0x8da6b343u;
+  h = h ^ (bitcast<u32>(cell.y) * 0xd8163841u);
+  h = h ^ (bitcast<u32>(cell.z) * 0xcb1ab31fu);
+  h = h ^ (seed * 0x165667b1u);
+  h = h ^ (h >> 15u);
+  h = h * 0x2c1b3c6du;
+  h = h ^ (h >> 12u);
+  h = h * 0x297a2d39u;
+  h = h ^ (h >> 15u);
+  return f32(h >> 8u) * (2.0 / 16777215.0) - 1.0;
+}
+
+fn value_noise_gradient(p: vec3<f32>, seed: u32) -> vec3<f32> {
+  let cell_f = floor(p);
+  let f = p - cell_f;
+  let u = f * f * f * (f * (f * 6.0 - 15.0) + 10.0);
+  let du = 30.0 * f * f * (f * (f - 2.0) + 1.0);
+  let i = vec3<i32>(cell_f);
+
+  let a = lattice_value(i, seed);
+  let b = lattice_value(i + vec3<i32>(1, 0, 0), seed);
+  let c = lattice_value(i + vec3<i32>(0, 1, 0), seed);
+  let d = lattice_value(i + vec3<i32>(1, 1, 0), seed);
+  let e = lattice_value(i + vec3<i32>(0, 0, 1), seed);
+  let f1 = lattice_value(i + vec3<i32>(1, 0, 1), seed);
+  let g = lattice_value(i + vec3<i32>(0, 1, 1), seed);
+  let h = lattice_value(i + vec3<i32>(1, 1, 1), seed);
+
+  let k1 = b - a;
+  let k2 = c - a;
+  let k3 = e - a;
+  let k4 = a - b - c + d;
+  let k5 = a - c - e + g;
+  let k6 = a - b - e + f1;
+  let k7 = -a + b + c - d + e - f1 - g + h;
+
+  return du * vec3<f32>(
+    k1 + k4 * u.y + k6 * u.z + k7 * u.y * u.z,
+    k2 + k5 * u.z + k4 * u.x + k7 * u.z * u.x,
+    k3 + k6 * u.x + k5 * u.y + k7 * u.x * u.y,
+  );
+}
+
+// `drift_time` is time already multiplied by the field's time scale.
+fn curl_noise(position: vec3<f32>, drift_time: f32, base_frequency: f32, octave_count: u32) -> vec3<f32> {
+  let octaves = min(octave_count, MAX_NOISE_OCTAVES);
+  let drift = TIME_DRIFT * drift_time;
+  let offsets = array<vec3<f32>, 3>(
+    vec3<f32>(0.0, 0.0, 0.0),
+    vec3<f32>(31.416, -17.23, 47.853),
+    vec3<f32>(-59.12, 83.71, 12.64),
+  );
+
+  var curl = vec3<f32>(0.0);
+  var frequency = base_frequency;
+  var amplitude = 1.0;
+  var total_amplitude = 0.0;
+  for (var octave = 0u; octave < octaves; octave = octave + 1u) {
+    let gx = value_noise_gradient(position * frequency + offsets[0] + drift, octave * 3u);
+    let gy = value_noise_gradient(position * frequency + offsets[1] + drift, octave * 3u + 1u);
+    let gz = value_noise_gradient(position * frequency + offsets[2] + drift, octave * 3u + 2u);
+    curl = curl + amplitude * vec3<f32>(gz.y - gy.z, gx.z - gz.x, gy.x - gx.y);
+
+    total_amplitude = total_amplitude + amplitude;
+    frequency = frequency * 2.0;
+    amplitude = amplitude * 0.5;
+  }
+
+  if (total_amplitude <= 0.0) {
+    return vec3<f32>(0.0);
+  }
+  return curl / total_amplitude;
+}
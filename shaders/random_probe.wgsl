@@ -0,0 +1,26 @@
+// Evaluates the shared randomness of random.wgsl for `check_random_parity` in parity.rs, which
+// prepends it.
+
+// Mirrors `GpuRandomSample` in parity.rs.
+struct RandomSample {
+  noise : vec3<f32>,
+  hash : u32,
+}
+
+const PROBE_OCTAVES : u32 = 3u;
+const PROBE_FREQUENCY : f32 = 1.3;
+
+@group(0) @binding(0)
+var<storage, read_write> samples : array<RandomSample>;
+
+// Inputs mirror `probe_input` in parity.rs.
+@compute @workgroup_size(64)
+fn probe(@builtin(global_invocation_id) id : vec3<u32>) {
+  let i = id.x;
+  if (i >= arrayLength(&samples)) {
+    return;
+  }
+  let position = vec3<f32>(hash01(3u * i), hash01(3u * i + 1u), hash01(3u * i + 2u)) * 16.0 - 8.0;
+  let noise = curl_noise(position, f32(i) * 0.01, PROBE_FREQUENCY, PROBE_OCTAVES);
+  samples[i] = RandomSample(noise, hash_u32(i * 2654435761u));
+}
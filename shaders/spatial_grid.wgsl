@@ -0,0 +1,41 @@
+// Spatial hash grid queries; mirrors `SpatialHashGrid` in spatial_grid.rs. The `grid`,
+// `grid_cell_ranges` and `grid_sorted_slots` bindings are declared by the generated source of
+// `SpatialHashGrid::query_wgsl`, which this file is appended to. Typical use, with a radius no
+// larger than `grid.cell_size`:
+//
+//   for (var n = 0u; n < GRID_NEIGHBOR_CELLS; n = n + 1u) {
+//     let range = grid_neighbor_range(position, n);
+//     for (var i = range.x; i < range.y; i = i + 1u) {
+//       let other = grid_sorted_slots[i];
+//       // Candidates include far corners and hash collisions: check the distance.
+//     }
+//   }
+const GRID_NEIGHBOR_CELLS : u32 = 27u;
+
+// Mirrors `SpatialHashGrid::cell_of`.
+fn grid_cell_of(position: vec3<f32>) -> vec3<i32> {
+  return vec3<i32>(floor(position / grid.cell_size));
+}
+
+// Mirrors `SpatialHashGrid::bucket_of`.
+fn grid_hash(cell: vec3<i32>) -> u32 {
+  let c = bitcast<vec3<u32>>(cell);
+  return ((c.x * 73856093u) ^ (c.y * 19349663u) ^ (c.z * 83492791u)) % grid.table_size;
+}
+
+fn grid_neighbor_cell(center: vec3<i32>, n: u32) -> vec3<i32> {
+  return center + vec3<i32>(i32(n % 3u), i32(n / 3u % 3u), i32(n / 9u)) - vec3<i32>(1);
+}
+
+// `grid_sorted_slots` range of the `n`th cell of the 3x3x3 block around `position`. Empty when an
+// earlier cell of the block shares its bucket, so each candidate is visited once.
+fn grid_neighbor_range(position: vec3<f32>, n: u32) -> vec2<u32> {
+  let center = grid_cell_of(position);
+  let bucket = grid_hash(grid_neighbor_cell(center, n));
+  for (var m = 0u; m < n; m = m + 1u) {
+    if (grid_hash(grid_neighbor_cell(center, m)) == bucket) {
+      return vec2<u32>(0u);
+    }
+  }
+  return grid_cell_ranges[bucket];
+}
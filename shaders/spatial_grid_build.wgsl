@@ -0,0 +1,93 @@
+// Counting-sort build of the spatial hash grid: `clear_counts`, `count`, `scan` and `scatter`,
+// dispatched in that order by `SpatialHashGridGpu::encode_build`. Prepended with the grid
+// bindings and spatial_grid.wgsl.
+
+struct Particle {
+  position : vec3<f32>,
+  age : f32,
+  velocity : vec3<f32>,
+  lifetime : f32,
+}
+
+@group(0) @binding(3)
+var<storage, read> particles : array<Particle>;
+
+@group(0) @binding(4)
+var<storage, read_write> cell_counts : array<atomic<u32>>;
+
+// Per slot: bucket, or `GRID_NO_BUCKET` for dead particles, and rank within the bucket.
+@group(0) @binding(5)
+var<storage, read_write> slot_entries : array<vec2<u32>>;
+
+const WORKGROUP_SIZE : u32 = 256u;
+const GRID_NO_BUCKET : u32 = 0xffffffffu;
+
+var<workgroup> chunk_sums : array<u32, WORKGROUP_SIZE>;
+
+@compute @workgroup_size(256)
+fn clear_counts(@builtin(global_invocation_id) gid: vec3<u32>) {
+  if (gid.x < grid.table_size) {
+    atomicStore(&cell_counts[gid.x], 0u);
+  }
+}
+
+@compute @workgroup_size(256)
+fn count(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let slot = gid.x;
+  if (slot >= grid.particle_count) {
+    return;
+  }
+  let p = particles[slot];
+  if (p.age >= p.lifetime) {
+    slot_entries[slot] = vec2<u32>(GRID_NO_BUCKET, 0u);
+    return;
+  }
+  let bucket = grid_hash(grid_cell_of(p.position));
+  slot_entries[slot] = vec2<u32>(bucket, atomicAdd(&cell_counts[bucket], 1u));
+}
+
+// Single workgroup: each thread sums a contiguous chunk of buckets, the chunk sums are scanned in
+// workgroup memory, then each thread writes the ranges of its chunk.
+@compute @workgroup_size(256)
+fn scan(@builtin(local_invocation_index) lid: u32) {
+  let chunk = (grid.table_size + WORKGROUP_SIZE - 1u) / WORKGROUP_SIZE;
+  let begin = min(lid * chunk, grid.table_size);
+  let end = min(begin + chunk, grid.table_size);
+  var total = 0u;
+  for (var b = begin; b < end; b = b + 1u) {
+    total = total + atomicLoad(&cell_counts[b]);
+  }
+  chunk_sums[lid] = total;
+  workgroupBarrier();
+
+  // Hillis-Steele inclusive scan.
+  for (var offset = 1u; offset < WORKGROUP_SIZE; offset = offset * 2u) {
+    var carry = 0u;
+    if (lid >= offset) {
+      carry = chunk_sums[lid - offset];
+    }
+    workgroupBarrier();
+    chunk_sums[lid] = chunk_sums[lid] + carry;
+    workgroupBarrier();
+  }
+
+  var start = chunk_sums[lid] - total;
+  for (var b = begin; b < end; b = b + 1u) {
+    let n = atomicLoad(&cell_counts[b]);
+    grid_cell_ranges[b] = vec2<u32>(start, start + n);
+    start = start + n;
+  }
+}
+
+@compute @workgroup_size(256)
+fn scatter(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let slot = gid.x;
+  if (slot >= grid.particle_count) {
+    return;
+  }
+  let entry = slot_entries[slot];
+  if (entry.x == GRID_NO_BUCKET) {
+    return;
+  }
+  grid_sorted_slots[grid_cell_ranges[entry.x].x + entry.y] = slot;
+}
@@ -0,0 +1,116 @@
+// SPH passes of `ParticleSimMode::Sph`, prepended with `SpatialHashGrid::query_wgsl(0, 0)`.
+// Each kernel runs one thread per particle slot; `SphConfig::apply_reference` in sph.rs is the
+// CPU mirror.
+
+struct Particle {
+  position : vec3<f32>,
+  age : f32,
+  velocity : vec3<f32>,
+  lifetime : f32,
+}
+
+// Mirrors `GpuSphParams` in sph.rs; the kernel coefficients are precomputed there.
+struct SphParams {
+  smoothing_radius : f32,
+  particle_mass : f32,
+  rest_density : f32,
+  stiffness : f32,
+  viscosity : f32,
+  max_speed : f32,
+  dt : f32,
+  poly6 : f32,
+  spiky_gradient : f32,
+  viscosity_laplacian : f32,
+  particle_count : u32,
+  _pad0 : u32,
+}
+
+@group(0) @binding(3)
+var<storage, read_write> particles : array<Particle>;
+
+// Density in x, pressure in y.
+@group(0) @binding(4)
+var<storage, read_write> densities : array<vec4<f32>>;
+
+@group(0) @binding(5)
+var<storage, read_write> accelerations : array<vec4<f32>>;
+
+@group(0) @binding(6)
+var<uniform> sph : SphParams;
+
+fn alive_slot(slot: u32) -> bool {
+  return slot < sph.particle_count && particles[slot].age < particles[slot].lifetime;
+}
+
+fn pressure(density: f32) -> f32 {
+  return max(sph.stiffness * (density - sph.rest_density), 0.0);
+}
+
+@compute @workgroup_size(256)
+fn density(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let slot = gid.x;
+  if (!alive_slot(slot)) {
+    return;
+  }
+  let position = particles[slot].position;
+  let h2 = sph.smoothing_radius * sph.smoothing_radius;
+  var rho = 0.0;
+  for (var n = 0u; n < GRID_NEIGHBOR_CELLS; n = n + 1u) {
+    let range = grid_neighbor_range(position, n);
+    for (var i = range.x; i < range.y; i = i + 1u) {
+      let d = particles[grid_sorted_slots[i]].position - position;
+      let w = h2 - dot(d, d);
+      if (w >= 0.0) {
+        rho = rho + sph.particle_mass * sph.poly6 * w * w * w;
+      }
+    }
+  }
+  densities[slot] = vec4<f32>(rho, pressure(rho), 0.0, 0.0);
+}
+
+@compute @workgroup_size(256)
+fn forces(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let slot = gid.x;
+  if (!alive_slot(slot)) {
+    return;
+  }
+  let p = particles[slot];
+  let h = sph.smoothing_radius;
+  let pressure_i = densities[slot].y;
+  var accel = vec3<f32>(0.0);
+  for (var n = 0u; n < GRID_NEIGHBOR_CELLS; n = n + 1u) {
+    let range = grid_neighbor_range(p.position, n);
+    for (var i = range.x; i < range.y; i = i + 1u) {
+      let other = grid_sorted_slots[i];
+      if (other == slot) {
+        continue;
+      }
+      let offset = p.position - particles[other].position;
+      let r = length(offset);
+      if (r > h || r <= 0.0) {
+        continue;
+      }
+      let falloff = h - r;
+      let share = sph.particle_mass / densities[other].x;
+      let push = share * 0.5 * (pressure_i + densities[other].y) * sph.spiky_gradient
+        * falloff * falloff / r;
+      let pull = share * sph.viscosity * sph.viscosity_laplacian * falloff;
+      accel = accel + offset * push + (particles[other].velocity - p.velocity) * pull;
+    }
+  }
+  accelerations[slot] = vec4<f32>(accel / densities[slot].x, 0.0);
+}
+
+@compute @workgroup_size(256)
+fn apply(@builtin(global_invocation_id) gid: vec3<u32>) {
+  let slot = gid.x;
+  if (!alive_slot(slot)) {
+    return;
+  }
+  var v = particles[slot].velocity + accelerations[slot].xyz * sph.dt;
+  let speed = length(v);
+  if (speed > sph.max_speed) {
+    v = v * (sph.max_speed / speed);
+  }
+  particles[slot].velocity = v;
+}
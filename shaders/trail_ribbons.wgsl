@@ -0,0 +1,95 @@
+// Ribbon rendering for `ParticleTrailRenderer`. Each instance is one particle slot drawn as a
+// triangle strip of `2 * history_len` vertices, two per history sample, newest first. Samples past
+// the slot's count collapse onto its oldest one with zero width. `ribbon_vertices` in trails.rs is
+// the CPU mirror.
+
+// Mirrors `GpuTrailParams` in trails.rs.
+struct TrailParams {
+  color : vec4<f32>,
+  history_len : u32,
+  head : u32,
+  particle_count : u32,
+  _pad0 : u32,
+  head_width : f32,
+  tail_width : f32,
+  head_alpha : f32,
+  tail_alpha : f32,
+}
+
+// Mirrors `GpuTrailCamera` in trails.rs.
+struct TrailCamera {
+  view_proj : mat4x4<f32>,
+  position : vec3<f32>,
+  _pad0 : f32,
+}
+
+@group(0) @binding(0)
+var<storage, read> history : array<vec4<f32>>;
+
+@group(0) @binding(1)
+var<storage, read> counts : array<u32>;
+
+@group(0) @binding(2)
+var<uniform> trail : TrailParams;
+
+@group(0) @binding(3)
+var<uniform> camera : TrailCamera;
+
+struct VertexOutput {
+  @builtin(position) clip_position : vec4<f32>,
+  @location(0) alpha : f32,
+}
+
+// The `age`-th newest sample of `slot`.
+fn trail_sample(slot : u32, age : u32) -> vec3<f32> {
+  let ring = (trail.head + trail.history_len - age) % trail.history_len;
+  return history[slot * trail.history_len + ring].xyz;
+}
+
+// Mirrors `normalize_or_zero` in math.rs.
+fn normalize_or_zero(v : vec3<f32>) -> vec3<f32> {
+  let len_sq = dot(v, v);
+  if (len_sq <= 1e-8) {
+    return vec3<f32>(0.0);
+  }
+  return v * inverseSqrt(len_sq);
+}
+
+@vertex
+fn vs_main(
+  @builtin(vertex_index) vertex_index : u32,
+  @builtin(instance_index) slot : u32,
+) -> VertexOutput {
+  var out : VertexOutput;
+  let count = counts[slot];
+  if (count < 2u) {
+    // Zero-area strip: nothing is rasterized.
+    out.clip_position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    out.alpha = 0.0;
+    return out;
+  }
+
+  let last = count - 1u;
+  let i = min(vertex_index / 2u, last);
+  let p = trail_sample(slot, i);
+  let newer = trail_sample(slot, max(i, 1u) - 1u);
+  let older = trail_sample(slot, min(i + 1u, last));
+  let side = normalize_or_zero(cross(newer - older, camera.position - p));
+  let t = f32(i) / f32(last);
+  var half_width = 0.5 * mix(trail.head_width, trail.tail_width, t);
+  if (vertex_index / 2u > last) {
+    half_width = 0.0;
+  }
+  if ((vertex_index & 1u) == 1u) {
+    half_width = -half_width;
+  }
+
+  out.clip_position = camera.view_proj * vec4<f32>(p + side * half_width, 1.0);
+  out.alpha = mix(trail.head_alpha, trail.tail_alpha, t);
+  return out;
+}
+
+@fragment
+fn fs_main(in : VertexOutput) -> @location(0) vec4<f32> {
+  return vec4<f32>(trail.color.rgb, trail.color.a * in.alpha);
+}
@@ -0,0 +1,55 @@
+// Trail history recording for `ParticleSimConfig::trails`, one thread per particle slot.
+// `TrailHistory::record` in trails.rs is the CPU mirror.
+
+struct Particle {
+  position : vec3<f32>,
+  age : f32,
+  velocity : vec3<f32>,
+  lifetime : f32,
+}
+
+// Mirrors `GpuTrailParams` in trails.rs.
+struct TrailParams {
+  color : vec4<f32>,
+  history_len : u32,
+  head : u32,
+  particle_count : u32,
+  _pad0 : u32,
+  head_width : f32,
+  tail_width : f32,
+  head_alpha : f32,
+  tail_alpha : f32,
+}
+
+@group(0) @binding(0)
+var<storage, read> particles : array<Particle>;
+
+// `history_len` entries per slot, indexed by the ring position `head`.
+@group(0) @binding(1)
+var<storage, read_write> history : array<vec4<f32>>;
+
+@group(0) @binding(2)
+var<storage, read_write> counts : array<u32>;
+
+@group(0) @binding(3)
+var<uniform> trail : TrailParams;
+
+@compute @workgroup_size(256)
+fn record(@builtin(global_invocation_id) gid : vec3<u32>) {
+  let slot = gid.x;
+  if (slot >= trail.particle_count) {
+    return;
+  }
+  let p = particles[slot];
+  if (p.age >= p.lifetime) {
+    counts[slot] = 0u;
+    return;
+  }
+  // Age zero at the end of a step means the slot was (re)spawned during it.
+  var count = counts[slot];
+  if (p.age == 0.0) {
+    count = 0u;
+  }
+  history[slot * trail.history_len + trail.head] = vec4<f32>(p.position, 1.0);
+  counts[slot] = min(count + 1u, trail.history_len);
+}
@@ -1,5 +1,5 @@
 use rust_webgpu_visual_engine::particles::{
-    EmitterConfig, ForceConfig, ParticleSimConfig, ParticleState, SimulationClock,
+    soak_reference, EmitterConfig, ForceConfig, ParticleSimConfig, ParticleState, SimulationClock,
 };
 
 fn main() {
@@ -12,6 +12,36 @@ fn main() {
     let force = ForceConfig::default();
 
     let mut state = ParticleState::new(config);
+    if let Some(threads) = parse_arg(&args, "--threads") {
+        state.set_worker_threads(threads as usize);
+    }
+    if let Some(minutes) = parse_arg(&args, "--soak-minutes") {
+        println!(
+            "Particle sim soak run: {:.1} simulated min @ {:.1}Hz (max_particles={})",
+            minutes, fps, config.max_particles
+        );
+        let report = soak_reference(
+            &mut state,
+            config,
+            emitter,
+            force,
+            1.0 / fps.max(1.0),
+            minutes,
+        );
+        println!(
+            "steps={} simulated={:.1}s alive={} non_finite={} first_at={:?}",
+            report.steps,
+            report.simulated_seconds,
+            report.final_alive_count,
+            report.violations,
+            report.first_violation_seconds
+        );
+        if !report.passed() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut clock = SimulationClock::new(1.0 / fps.max(1.0));
     let mut elapsed = 0.0f32;
 
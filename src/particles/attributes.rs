@@ -0,0 +1,328 @@
+use std::f32::consts::TAU;
+use std::fmt::Write as _;
+
+use super::config::EmitterConfig;
+use super::emitter::spawn_hash_key;
+use super::math::{hash01, hash_u32};
+
+/// Upper bound on `ParticleAttributeSchema::user_floats`.
+pub const MAX_USER_FLOATS: u32 = 4;
+
+/// Opt-in per-particle attributes stored next to the fixed 32-byte `Particle`, in a parallel
+/// buffer whose stride and WGSL struct both come from `layout()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParticleAttributeSchema {
+    /// RGBA color, seeded from `EmitterConfig::color`.
+    pub color: bool,
+    /// Sprite size, seeded from `EmitterConfig::size` and `size_variance`.
+    pub size: bool,
+    /// Rotation in radians plus the angular velocity that advances it every step.
+    pub rotation: bool,
+    /// Stable per-particle `u32` for shading variation.
+    pub seed: bool,
+    /// Zero-initialized floats for custom kernels and shaders, capped at `MAX_USER_FLOATS`.
+    pub user_floats: u32,
+}
+
+impl ParticleAttributeSchema {
+    pub fn all() -> Self {
+        Self {
+            color: true,
+            size: true,
+            rotation: true,
+            seed: true,
+            user_floats: MAX_USER_FLOATS,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layout().stride_bytes == 0
+    }
+
+    /// Byte offsets following WGSL storage layout rules: `color` is a `vec4<f32>` placed first,
+    /// everything else is 4-byte scalars, and the stride is rounded up to the struct alignment.
+    pub fn layout(&self) -> ParticleAttributeLayout {
+        let mut cursor = 0u32;
+        let mut place = |enabled: bool, bytes: u32| {
+            enabled.then(|| {
+                let offset = cursor;
+                cursor += bytes;
+                offset
+            })
+        };
+        let user_floats = self.user_floats.min(MAX_USER_FLOATS);
+        let color = place(self.color, 16);
+        let size = place(self.size, 4);
+        let rotation = place(self.rotation, 8);
+        let seed = place(self.seed, 4);
+        let user = place(user_floats > 0, 4 * user_floats);
+        let align = if self.color { 16 } else { 4 };
+
+        ParticleAttributeLayout {
+            color,
+            size,
+            rotation,
+            seed,
+            user,
+            user_floats,
+            stride_bytes: cursor.div_ceil(align) * align,
+        }
+    }
+}
+
+/// Resolved byte offsets of each enabled attribute within one particle's record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParticleAttributeLayout {
+    pub color: Option<u32>,
+    pub size: Option<u32>,
+    /// Rotation, followed by angular velocity.
+    pub rotation: Option<u32>,
+    pub seed: Option<u32>,
+    pub user: Option<u32>,
+    pub user_floats: u32,
+    /// Zero when no attribute is enabled.
+    pub stride_bytes: u32,
+}
+
+impl ParticleAttributeLayout {
+    /// WGSL prepended to particles_update.wgsl: the `ParticleAttributes` struct with only the
+    /// enabled fields, plus `load_attributes`/`store_attributes` converting it to and from the
+    /// full `AttributeValues` the kernels work with.
+    pub fn wgsl_source(&self) -> String {
+        let mut fields = String::new();
+        let mut load = String::new();
+        let mut store = String::new();
+        let mut field = |name: &str, ty: &str| {
+            let _ = writeln!(fields, "  {name} : {ty},");
+            let _ = writeln!(load, "  v.{name} = attributes[slot].{name};");
+            let _ = writeln!(store, "  attributes[slot].{name} = v.{name};");
+        };
+        if self.color.is_some() {
+            field("color", "vec4<f32>");
+        }
+        if self.size.is_some() {
+            field("size", "f32");
+        }
+        if self.rotation.is_some() {
+            field("rotation", "f32");
+            field("angular_velocity", "f32");
+        }
+        if self.seed.is_some() {
+            field("seed", "u32");
+        }
+        if self.user.is_some() {
+            let _ = writeln!(fields, "  user : array<f32, {}>,", self.user_floats);
+            for i in 0..self.user_floats {
+                let _ = writeln!(load, "  v.user[{i}] = attributes[slot].user[{i}];");
+                let _ = writeln!(store, "  attributes[slot].user[{i}] = v.user[{i}];");
+            }
+        }
+        if fields.is_empty() {
+            // WGSL has no empty structs; the buffer is never indexed in this case.
+            fields.push_str("  _unused : u32,\n");
+        }
+
+        let mut source = String::new();
+        let _ = write!(
+            source,
+            "// Generated by `ParticleAttributeLayout::wgsl_source`.\n\
+             const PARTICLE_ATTRIBUTE_STRIDE : u32 = {stride}u;\n\n\
+             struct ParticleAttributes {{\n{fields}}}\n\n\
+             fn load_attributes(slot: u32) -> AttributeValues {{\n  \
+             var v = default_attributes();\n{load}  return v;\n}}\n\n\
+             fn store_attributes(slot: u32, v: AttributeValues) {{\n{store}}}\n\n",
+            stride = self.stride_bytes,
+        );
+        source
+    }
+
+    /// Packs `values` into one `stride_bytes` record, skipping disabled attributes.
+    pub fn encode(&self, values: &ParticleAttributes, out: &mut [u8]) {
+        let mut put = |offset: u32, bits: u32| {
+            let at = offset as usize;
+            out[at..at + 4].copy_from_slice(&bits.to_le_bytes());
+        };
+        if let Some(offset) = self.color {
+            for (i, c) in values.color.iter().enumerate() {
+                put(offset + 4 * i as u32, c.to_bits());
+            }
+        }
+        if let Some(offset) = self.size {
+            put(offset, values.size.to_bits());
+        }
+        if let Some(offset) = self.rotation {
+            put(offset, values.rotation.to_bits());
+            put(offset + 4, values.angular_velocity.to_bits());
+        }
+        if let Some(offset) = self.seed {
+            put(offset, values.seed);
+        }
+        if let Some(offset) = self.user {
+            for i in 0..self.user_floats {
+                put(offset + 4 * i, values.user[i as usize].to_bits());
+            }
+        }
+    }
+
+    /// Inverse of `encode`; disabled attributes come back as `ParticleAttributes::default()`.
+    pub fn decode(&self, record: &[u8]) -> ParticleAttributes {
+        let get = |offset: u32| {
+            let at = offset as usize;
+            u32::from_le_bytes([record[at], record[at + 1], record[at + 2], record[at + 3]])
+        };
+        let mut values = ParticleAttributes::default();
+        if let Some(offset) = self.color {
+            for (i, c) in values.color.iter_mut().enumerate() {
+                *c = f32::from_bits(get(offset + 4 * i as u32));
+            }
+        }
+        if let Some(offset) = self.size {
+            values.size = f32::from_bits(get(offset));
+        }
+        if let Some(offset) = self.rotation {
+            values.rotation = f32::from_bits(get(offset));
+            values.angular_velocity = f32::from_bits(get(offset + 4));
+        }
+        if let Some(offset) = self.seed {
+            values.seed = get(offset);
+        }
+        if let Some(offset) = self.user {
+            for i in 0..self.user_floats {
+                values.user[i as usize] = f32::from_bits(get(offset + 4 * i));
+            }
+        }
+        values
+    }
+}
+
+/// Every attribute a particle can carry; the schema decides which of them are stored.
+/// Mirrors `AttributeValues` in particles_update.wgsl.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleAttributes {
+    pub color: [f32; 4],
+    pub size: f32,
+    pub rotation: f32,
+    pub angular_velocity: f32,
+    pub seed: u32,
+    pub user: [f32; MAX_USER_FLOATS as usize],
+}
+
+impl Default for ParticleAttributes {
+    /// Matches `default_attributes` in particles_update.wgsl.
+    fn default() -> Self {
+        Self {
+            color: [1.0; 4],
+            size: 1.0,
+            rotation: 0.0,
+            angular_velocity: 0.0,
+            seed: 0,
+            user: [0.0; MAX_USER_FLOATS as usize],
+        }
+    }
+}
+
+impl ParticleAttributes {
+    /// Spawn-time values, the emitter color multiplied by the sample's `tint`; mirrors
+    /// `spawn_attributes` in particles_update.wgsl.
+    pub(crate) fn spawn(
+        emitter: &EmitterConfig,
+        tint: [f32; 4],
+        sim_seed: u32,
+        slot: u32,
+        remaining: u32,
+    ) -> Self {
+        let seed = hash_u32(spawn_hash_key(sim_seed, slot, remaining, 829));
+        let size_jitter = 2.0 * hash01(seed) - 1.0;
+        let spin_jitter = 2.0 * hash01(seed.wrapping_add(2)) - 1.0;
+        Self {
+            color: [0, 1, 2, 3].map(|c| emitter.color[c] * tint[c]),
+            size: (emitter.size * (1.0 + emitter.size_variance * size_jitter)).max(0.0),
+            rotation: TAU * hash01(seed.wrapping_add(1)),
+            angular_velocity: emitter.angular_speed * spin_jitter,
+            seed,
+            user: [0.0; MAX_USER_FLOATS as usize],
+        }
+    }
+
+    /// Per-step update; mirrors the attribute block of `update_particle`.
+    pub(crate) fn advance(&mut self, dt: f32) {
+        self.rotation += self.angular_velocity * dt;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParticleAttributeSchema, ParticleAttributes};
+
+    #[test]
+    fn layout_follows_wgsl_alignment() {
+        assert_eq!(ParticleAttributeSchema::default().layout().stride_bytes, 0);
+        let all = ParticleAttributeSchema::all().layout();
+        assert_eq!(all.color, Some(0));
+        assert_eq!(all.size, Some(16));
+        assert_eq!(all.rotation, Some(20));
+        assert_eq!(all.seed, Some(28));
+        assert_eq!(all.user, Some(32));
+        assert_eq!(all.stride_bytes, 48);
+
+        // Scalars only: no vec4 in the struct, so no padding to 16.
+        let scalars = ParticleAttributeSchema {
+            size: true,
+            user_floats: 2,
+            ..ParticleAttributeSchema::default()
+        }
+        .layout();
+        assert_eq!(scalars.user, Some(4));
+        assert_eq!(scalars.stride_bytes, 12);
+        let color_only = ParticleAttributeSchema {
+            color: true,
+            seed: true,
+            ..ParticleAttributeSchema::default()
+        }
+        .layout();
+        assert_eq!(color_only.stride_bytes, 32);
+    }
+
+    #[test]
+    fn encode_decode_round_trips_enabled_fields() {
+        let layout = ParticleAttributeSchema {
+            color: false,
+            rotation: true,
+            seed: true,
+            user_floats: 3,
+            ..ParticleAttributeSchema::default()
+        }
+        .layout();
+        let values = ParticleAttributes {
+            color: [0.1, 0.2, 0.3, 0.4],
+            rotation: 1.5,
+            angular_velocity: -2.0,
+            seed: 0xdead_beef,
+            user: [7.0, 8.0, 9.0, 10.0],
+            ..ParticleAttributes::default()
+        };
+        let mut record = vec![0u8; layout.stride_bytes as usize];
+        layout.encode(&values, &mut record);
+        let decoded = layout.decode(&record);
+        assert_eq!(decoded.color, ParticleAttributes::default().color);
+        assert_eq!(decoded.rotation, 1.5);
+        assert_eq!(decoded.angular_velocity, -2.0);
+        assert_eq!(decoded.seed, 0xdead_beef);
+        assert_eq!(decoded.user, [7.0, 8.0, 9.0, 0.0]);
+    }
+
+    #[test]
+    fn generated_wgsl_declares_only_enabled_fields() {
+        let source = ParticleAttributeSchema {
+            size: true,
+            user_floats: 2,
+            ..ParticleAttributeSchema::default()
+        }
+        .layout()
+        .wgsl_source();
+        assert!(source.contains("const PARTICLE_ATTRIBUTE_STRIDE : u32 = 12u;"));
+        assert!(source.contains("  size : f32,"));
+        assert!(source.contains("  user : array<f32, 2>,"));
+        assert!(!source.contains("color"));
+    }
+}
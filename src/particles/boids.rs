@@ -0,0 +1,458 @@
+use std::borrow::Cow;
+use std::mem::size_of;
+
+use bytemuck::{bytes_of, Pod, Zeroable};
+
+use super::forces::{ForceField, ForceFieldList};
+use super::math::{add, cross, dot, mul_scalar, normalize_or_zero, sub};
+use super::simulation::Particle;
+use super::spatial_grid::{
+    layout_entry, storage_binding, uniform_binding, SpatialGridConfig, SpatialHashGrid,
+    SpatialHashGridGpu,
+};
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Reynolds flocking in place of the ballistic forces: every step, separation, alignment and
+/// cohesion with neighbors steer each particle's velocity, which is then integrated as usual.
+/// Gravity, noise, drag and force fields do not act on boids; `ForceField::Point` fields steer
+/// them instead, scaled by `attractor_weight`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoidsConfig {
+    /// Neighbors closer than this push the particle away, harder the closer they are.
+    pub separation_radius: f32,
+    /// Neighbors within this radius pull the particle's velocity towards their mean velocity.
+    pub alignment_radius: f32,
+    /// Neighbors within this radius pull the particle towards their centroid.
+    pub cohesion_radius: f32,
+    pub separation_weight: f32,
+    pub alignment_weight: f32,
+    pub cohesion_weight: f32,
+    /// Scale of the point force fields' acceleration; zero ignores them.
+    pub attractor_weight: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    /// Largest change of heading per second, in radians; `f32::INFINITY` turns freely.
+    pub max_turn_rate: f32,
+    /// Bucket count of the neighbor grid; see `SpatialGridConfig::table_size`.
+    pub grid_table_size: u32,
+}
+
+impl Default for BoidsConfig {
+    fn default() -> Self {
+        Self {
+            separation_radius: 0.05,
+            alignment_radius: 0.15,
+            cohesion_radius: 0.2,
+            separation_weight: 4.0,
+            alignment_weight: 3.0,
+            cohesion_weight: 3.0,
+            attractor_weight: 1.0,
+            min_speed: 0.4,
+            max_speed: 1.0,
+            max_turn_rate: std::f32::consts::TAU,
+            grid_table_size: 1 << 14,
+        }
+    }
+}
+
+impl BoidsConfig {
+    /// Cell size covers the widest rule, so the 3x3x3 query block sees every neighbor.
+    pub fn grid_config(&self) -> SpatialGridConfig {
+        SpatialGridConfig {
+            cell_size: self.neighbor_radius(),
+            table_size: self.grid_table_size,
+        }
+    }
+
+    fn neighbor_radius(&self) -> f32 {
+        self.separation_radius
+            .max(self.alignment_radius)
+            .max(self.cohesion_radius)
+    }
+
+    /// CPU reference of the `steer` and `apply` kernels in boids.wgsl: replaces the velocity of
+    /// every alive particle with its steered velocity.
+    pub(crate) fn steer_reference(
+        &self,
+        particles: &mut [Particle],
+        fields: &ForceFieldList,
+        dt: f32,
+    ) {
+        let grid = SpatialHashGrid::build(self.grid_config(), particles);
+        let radius = self.neighbor_radius();
+
+        let mut steered = vec![[0.0f32; 3]; particles.len()];
+        for (i, p) in particles.iter().enumerate() {
+            if !p.is_alive() {
+                continue;
+            }
+            let mut separation = [0.0; 3];
+            let mut velocity_sum = [0.0; 3];
+            let mut aligned = 0u32;
+            let mut position_sum = [0.0; 3];
+            let mut cohesive = 0u32;
+            grid.for_each_neighbor(particles, p.position, radius, |j| {
+                let j = j as usize;
+                if j == i {
+                    return;
+                }
+                let other = &particles[j];
+                let offset = sub(p.position, other.position);
+                let distance = dot(offset, offset).sqrt();
+                if distance < self.separation_radius {
+                    separation = add(
+                        separation,
+                        mul_scalar(
+                            normalize_or_zero(offset),
+                            1.0 - distance / self.separation_radius,
+                        ),
+                    );
+                }
+                if distance < self.alignment_radius {
+                    velocity_sum = add(velocity_sum, other.velocity);
+                    aligned += 1;
+                }
+                if distance < self.cohesion_radius {
+                    position_sum = add(position_sum, other.position);
+                    cohesive += 1;
+                }
+            });
+
+            let mut accel = mul_scalar(separation, self.separation_weight);
+            if aligned > 0 {
+                let mean_velocity = mul_scalar(velocity_sum, 1.0 / aligned as f32);
+                accel = add(
+                    accel,
+                    mul_scalar(sub(mean_velocity, p.velocity), self.alignment_weight),
+                );
+            }
+            if cohesive > 0 {
+                let centroid = mul_scalar(position_sum, 1.0 / cohesive as f32);
+                accel = add(
+                    accel,
+                    mul_scalar(sub(centroid, p.position), self.cohesion_weight),
+                );
+            }
+            if self.attractor_weight != 0.0 {
+                for field in fields.as_slice() {
+                    if let ForceField::Point { .. } = field {
+                        let pull = field.acceleration(p.position, p.velocity, 0.0);
+                        accel = add(accel, mul_scalar(pull, self.attractor_weight));
+                    }
+                }
+            }
+            steered[i] =
+                self.limit_steering(p.velocity, add(p.velocity, mul_scalar(accel, dt)), dt);
+        }
+
+        for (p, velocity) in particles.iter_mut().zip(steered) {
+            if p.is_alive() {
+                p.velocity = velocity;
+            }
+        }
+    }
+
+    /// Turns `velocity` towards `desired` by at most `max_turn_rate * dt` and clamps the speed;
+    /// mirrors `limit_steering` in boids.wgsl.
+    fn limit_steering(&self, velocity: [f32; 3], desired: [f32; 3], dt: f32) -> [f32; 3] {
+        let speed = dot(desired, desired)
+            .sqrt()
+            .clamp(self.min_speed, self.max_speed);
+        let heading = normalize_or_zero(velocity);
+        let mut goal = normalize_or_zero(desired);
+        if goal == [0.0; 3] {
+            goal = heading;
+        }
+        if heading == [0.0; 3] {
+            return mul_scalar(goal, speed);
+        }
+
+        let angle = dot(heading, goal).clamp(-1.0, 1.0).acos();
+        let max_angle = self.max_turn_rate * dt;
+        if angle <= max_angle {
+            return mul_scalar(goal, speed);
+        }
+        let mut axis = normalize_or_zero(cross(heading, goal));
+        if axis == [0.0; 3] {
+            // Reversing: any axis perpendicular to the heading will do.
+            let helper = if heading[0].abs() < 0.9 {
+                [1.0, 0.0, 0.0]
+            } else {
+                [0.0, 1.0, 0.0]
+            };
+            axis = normalize_or_zero(cross(heading, helper));
+        }
+        let turned = add(
+            mul_scalar(heading, max_angle.cos()),
+            mul_scalar(cross(axis, heading), max_angle.sin()),
+        );
+        mul_scalar(turned, speed)
+    }
+}
+
+/// Mirrors `BoidsParams` in boids.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuBoidsParams {
+    separation_radius: f32,
+    alignment_radius: f32,
+    cohesion_radius: f32,
+    separation_weight: f32,
+    alignment_weight: f32,
+    cohesion_weight: f32,
+    attractor_weight: f32,
+    min_speed: f32,
+    max_speed: f32,
+    max_turn_rate: f32,
+    dt: f32,
+    particle_count: u32,
+    force_field_count: u32,
+    _pad0: [u32; 3],
+}
+
+impl GpuBoidsParams {
+    fn new(config: &BoidsConfig, particle_count: u32, force_field_count: u32, dt: f32) -> Self {
+        Self {
+            separation_radius: config.separation_radius,
+            alignment_radius: config.alignment_radius,
+            cohesion_radius: config.cohesion_radius,
+            separation_weight: config.separation_weight,
+            alignment_weight: config.alignment_weight,
+            cohesion_weight: config.cohesion_weight,
+            attractor_weight: config.attractor_weight,
+            min_speed: config.min_speed,
+            max_speed: config.max_speed,
+            max_turn_rate: config.max_turn_rate,
+            dt,
+            particle_count,
+            force_field_count,
+            _pad0: [0; 3],
+        }
+    }
+}
+
+/// GPU side of `ParticleSimMode::Boids`, encoded by `ParticleGpuSim` ahead of the update kernel:
+/// rebuilds the neighbor grid, then runs the `steer` and `apply` kernels.
+pub(crate) struct BoidsPasses {
+    config: BoidsConfig,
+    particle_count: u32,
+    grid: SpatialHashGridGpu,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    steer: wgpu::ComputePipeline,
+    apply: wgpu::ComputePipeline,
+}
+
+impl BoidsPasses {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particle_buffer: &wgpu::Buffer,
+        force_field_buffer: &wgpu::Buffer,
+        particle_count: u32,
+        config: BoidsConfig,
+    ) -> Self {
+        let grid = SpatialHashGridGpu::new(
+            device,
+            queue,
+            particle_buffer,
+            particle_count,
+            config.grid_config(),
+        );
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.boids.params"),
+            size: size_of::<GpuBoidsParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Steered velocities are staged so `steer` reads every neighbor's pre-step velocity.
+        let steered_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.boids.steered"),
+            size: (size_of::<[f32; 4]>() as u64 * particle_count as u64).max(16),
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let mut layout_entries = SpatialHashGridGpu::query_layout_entries(0).to_vec();
+        layout_entries.extend([
+            layout_entry(3, storage_binding(false)),
+            layout_entry(4, storage_binding(false)),
+            layout_entry(5, uniform_binding()),
+            layout_entry(6, storage_binding(true)),
+        ]);
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.boids.bgl"),
+            entries: &layout_entries,
+        });
+        let mut entries = grid.query_entries(0).to_vec();
+        for (binding, buffer) in [
+            (3, particle_buffer),
+            (4, &steered_buffer),
+            (5, &params_buffer),
+            (6, force_field_buffer),
+        ] {
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.boids.bg"),
+            layout: &bind_group_layout,
+            entries: &entries,
+        });
+
+        let shader_source = SpatialHashGrid::query_wgsl(0, 0)
+            + include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/boids.wgsl"));
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles.boids.shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shader_source)),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles.boids.pl"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("particles.boids.{entry_point}.pipeline")),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            })
+        };
+
+        Self {
+            config,
+            particle_count,
+            grid,
+            params_buffer,
+            bind_group,
+            steer: create_pipeline("steer"),
+            apply: create_pipeline("apply"),
+        }
+    }
+
+    /// Expects the step's force fields to be in the buffer passed to `new` already.
+    pub(crate) fn encode(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        force_field_count: u32,
+        dt: f32,
+    ) {
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytes_of(&GpuBoidsParams::new(
+                &self.config,
+                self.particle_count,
+                force_field_count,
+                dt,
+            )),
+        );
+        self.grid.encode_build(encoder);
+
+        let groups = self.particle_count.div_ceil(WORKGROUP_SIZE);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particles.boids.pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        for pipeline in [&self.steer, &self.apply] {
+            pass.set_pipeline(pipeline);
+            pass.dispatch_workgroups(groups, 1, 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BoidsConfig;
+    use crate::particles::math::{dot, normalize_or_zero, sub};
+    use crate::particles::{
+        Burst, BurstSchedule, EmitterConfig, EmitterDirection, EmitterShape, ForceConfig, Particle,
+        ParticleSimConfig, ParticleSimMode, ParticleState,
+    };
+
+    #[test]
+    fn steering_respects_turn_rate_and_speed_limits() {
+        let boids = BoidsConfig {
+            max_turn_rate: 1.0,
+            ..BoidsConfig::default()
+        };
+        let dt = 0.1;
+        // Even a full reversal only turns the heading by `max_turn_rate * dt`.
+        for desired in [[-5.0, 0.001, 0.0], [-5.0, 0.0, 0.0]] {
+            let v = boids.limit_steering([0.5, 0.0, 0.0], desired, dt);
+            let speed = dot(v, v).sqrt();
+            assert!((speed - boids.max_speed).abs() < 1e-5);
+            assert!((v[0] / speed - (1.0 * dt).cos()).abs() < 1e-4);
+        }
+        let slow = boids.limit_steering([0.5, 0.0, 0.0], [0.01, 0.0, 0.0], dt);
+        assert!((slow[0] - boids.min_speed).abs() < 1e-6);
+    }
+
+    /// Mean cosine between each boid's heading and the mean heading of its neighbors.
+    fn local_alignment(particles: &[Particle], radius: f32) -> f32 {
+        let alive: Vec<&Particle> = particles.iter().filter(|p| p.is_alive()).collect();
+        let mut total = 0.0;
+        for a in &alive {
+            let mut neighbor_heading = [0.0; 3];
+            for b in &alive {
+                let d = sub(a.position, b.position);
+                let distance = dot(d, d).sqrt();
+                if distance > 0.0 && distance < radius {
+                    let h = normalize_or_zero(b.velocity);
+                    neighbor_heading = [
+                        neighbor_heading[0] + h[0],
+                        neighbor_heading[1] + h[1],
+                        neighbor_heading[2] + h[2],
+                    ];
+                }
+            }
+            total += dot(
+                normalize_or_zero(a.velocity),
+                normalize_or_zero(neighbor_heading),
+            );
+        }
+        total / alive.len() as f32
+    }
+
+    #[test]
+    fn flock_alignment_increases_over_time() {
+        let boids = BoidsConfig::default();
+        let config = ParticleSimConfig {
+            max_particles: 256,
+            spawn_rate_per_second: 0.0,
+            lifetime_seconds: 1.0e6,
+            mode: ParticleSimMode::Boids(boids),
+            ..ParticleSimConfig::default()
+        };
+        let emitter = EmitterConfig {
+            shape: EmitterShape::Sphere { radius: 0.3 },
+            direction: EmitterDirection::Random,
+            initial_speed: 0.5,
+            bursts: BurstSchedule::from_slice(&[Burst::once(0.0, 200)]),
+            ..EmitterConfig::default()
+        };
+        let force = ForceConfig::default();
+
+        let mut state = ParticleState::new(config);
+        state.step_reference(1.0 / 60.0, config, emitter, force);
+        let initial = local_alignment(&state.particles, boids.alignment_radius);
+        for _ in 0..240 {
+            state.step_reference(1.0 / 60.0, config, emitter, force);
+        }
+        let settled = local_alignment(&state.particles, boids.alignment_radius);
+        assert!(initial < 0.3, "random headings start aligned: {initial}");
+        assert!(settled > 0.9, "flock did not align: {settled}");
+
+        for p in state.particles.iter().filter(|p| p.is_alive()) {
+            let speed = dot(p.velocity, p.velocity).sqrt();
+            assert!(speed >= boids.min_speed - 1e-5 && speed <= boids.max_speed + 1e-5);
+        }
+    }
+}
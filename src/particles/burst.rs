@@ -0,0 +1,202 @@
+/// Capacity of `BurstSchedule`.
+pub const MAX_BURSTS: usize = 16;
+
+/// Upper bound on schedule periods crossed by one `count_between` window, so a tiny
+/// `period_seconds` cannot stall a step.
+const MAX_PERIODS_PER_WINDOW: u32 = 64;
+
+/// `count` particles at `time_seconds`, then every `interval_seconds` for `cycles` firings in
+/// total. `cycles == 0` repeats forever, and an `interval_seconds <= 0` fires once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Burst {
+    pub time_seconds: f32,
+    pub count: u32,
+    pub interval_seconds: f32,
+    pub cycles: u32,
+}
+
+impl Burst {
+    pub fn once(time_seconds: f32, count: u32) -> Self {
+        Self {
+            time_seconds,
+            count,
+            interval_seconds: 0.0,
+            cycles: 1,
+        }
+    }
+
+    /// Firings with `start <= t < end`.
+    fn firings_between(&self, start: f32, end: f32) -> u32 {
+        if end <= start {
+            return 0;
+        }
+        if self.interval_seconds <= 0.0 {
+            return u32::from((start..end).contains(&self.time_seconds));
+        }
+        let first = ((start - self.time_seconds) / self.interval_seconds)
+            .ceil()
+            .max(0.0);
+        let mut past_last = ((end - self.time_seconds) / self.interval_seconds)
+            .ceil()
+            .max(0.0);
+        if self.cycles > 0 {
+            past_last = past_last.min(self.cycles as f32);
+        }
+        (past_last - first).max(0.0) as u32
+    }
+}
+
+/// Fixed-capacity burst list, evaluated against simulated time so the CPU and GPU sims,
+/// which advance time identically, emit identical counts. With `period_seconds > 0` the
+/// schedule repeats, matching a looping `VideoTimeline`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurstSchedule {
+    bursts: [Burst; MAX_BURSTS],
+    len: usize,
+    period_seconds: f32,
+}
+
+impl BurstSchedule {
+    pub const fn new() -> Self {
+        Self {
+            bursts: [Burst {
+                time_seconds: 0.0,
+                count: 0,
+                interval_seconds: 0.0,
+                cycles: 1,
+            }; MAX_BURSTS],
+            len: 0,
+            period_seconds: 0.0,
+        }
+    }
+
+    /// Takes at most `MAX_BURSTS` bursts; the rest are dropped.
+    pub fn from_slice(bursts: &[Burst]) -> Self {
+        let mut schedule = Self::new();
+        for burst in bursts.iter().take(MAX_BURSTS) {
+            schedule.push(*burst);
+        }
+        schedule
+    }
+
+    /// Repeats the whole schedule every `period_seconds`; zero or less disables repetition.
+    pub fn with_period(mut self, period_seconds: f32) -> Self {
+        self.period_seconds = period_seconds.max(0.0);
+        self
+    }
+
+    pub fn period_seconds(&self) -> f32 {
+        self.period_seconds
+    }
+
+    /// Returns `false` and leaves the schedule unchanged when it is full.
+    pub fn push(&mut self, burst: Burst) -> bool {
+        if self.len == MAX_BURSTS {
+            return false;
+        }
+        self.bursts[self.len] = burst;
+        self.len += 1;
+        true
+    }
+
+    pub fn as_slice(&self) -> &[Burst] {
+        &self.bursts[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Particles due in the window `[start_seconds, end_seconds)`.
+    pub fn count_between(&self, start_seconds: f32, end_seconds: f32) -> u32 {
+        if self.is_empty() || end_seconds <= start_seconds {
+            return 0;
+        }
+        if self.period_seconds <= 0.0 {
+            return self.count_in_cycle(start_seconds, end_seconds);
+        }
+
+        let period = self.period_seconds;
+        let mut total = 0u32;
+        let mut index = (start_seconds / period).floor();
+        for _ in 0..MAX_PERIODS_PER_WINDOW {
+            let offset = index * period;
+            if offset >= end_seconds {
+                break;
+            }
+            let local_start = (start_seconds - offset).max(0.0);
+            let local_end = (end_seconds - offset).min(period);
+            total = total.saturating_add(self.count_in_cycle(local_start, local_end));
+            index += 1.0;
+        }
+        total
+    }
+
+    fn count_in_cycle(&self, start: f32, end: f32) -> u32 {
+        self.as_slice().iter().fold(0u32, |total, burst| {
+            total.saturating_add(
+                burst
+                    .firings_between(start, end)
+                    .saturating_mul(burst.count),
+            )
+        })
+    }
+}
+
+impl Default for BurstSchedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Burst, BurstSchedule};
+
+    fn total_over_steps(schedule: &BurstSchedule, dt: f32, steps: u32) -> u32 {
+        let mut t = 0.0f32;
+        let mut total = 0;
+        for _ in 0..steps {
+            total += schedule.count_between(t, t + dt);
+            t += dt;
+        }
+        total
+    }
+
+    #[test]
+    fn bursts_fire_once_per_cycle_in_the_step_containing_them() {
+        let schedule = BurstSchedule::from_slice(&[
+            Burst::once(0.25, 100),
+            Burst {
+                time_seconds: 0.5,
+                count: 10,
+                interval_seconds: 0.1,
+                cycles: 3,
+            },
+        ]);
+        assert_eq!(schedule.count_between(0.24, 0.26), 100);
+        assert_eq!(schedule.count_between(0.26, 0.5), 0);
+        assert_eq!(schedule.count_between(0.5, 0.51), 10);
+        assert_eq!(total_over_steps(&schedule, 1.0 / 60.0, 120), 130);
+        assert_eq!(total_over_steps(&schedule, 1.0 / 7.0, 14), 130);
+    }
+
+    #[test]
+    fn periodic_schedule_repeats_and_endless_bursts_keep_firing() {
+        let looping = BurstSchedule::from_slice(&[Burst::once(0.0, 5)]).with_period(1.0);
+        assert_eq!(total_over_steps(&looping, 1.0 / 64.0, 64 * 3), 15);
+
+        let endless = BurstSchedule::from_slice(&[Burst {
+            time_seconds: 0.0,
+            count: 2,
+            interval_seconds: 0.25,
+            cycles: 0,
+        }]);
+        assert_eq!(endless.count_between(0.0, 1.0), 8);
+        assert_eq!(endless.count_between(10.0, 10.5), 4);
+    }
+}
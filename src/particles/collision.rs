@@ -0,0 +1,540 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::math::{add, dot, mul_scalar, normalize_or_zero, quaternion_or_identity, rotate, sub};
+
+/// Capacity of `ColliderList`, and of the GPU collider storage buffer.
+pub const MAX_COLLIDERS: usize = 16;
+
+/// Distance reported for points outside an `SdfVolume`, so they never register contact.
+const SDF_OUTSIDE_DISTANCE: f32 = 1.0e6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderShape {
+    /// Infinite plane through `point`; particles are kept on the side `normal` points to.
+    Plane {
+        point: [f32; 3],
+        normal: [f32; 3],
+    },
+    Sphere {
+        center: [f32; 3],
+        radius: f32,
+    },
+    /// Box rotated by the unit quaternion `rotation` (`[x, y, z, w]`) around its center.
+    Box {
+        center: [f32; 3],
+        half_extents: [f32; 3],
+        rotation: [f32; 4],
+    },
+    /// The sim's baked `SdfVolume`; never collides while no volume is set.
+    SdfVolume,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    /// Fraction of the normal speed kept after a bounce; 0 sticks, 1 is perfectly elastic.
+    pub restitution: f32,
+    /// Fraction of the tangential speed removed on contact.
+    pub friction: f32,
+    /// Kill particles on contact instead of bouncing them.
+    pub kill_on_contact: bool,
+}
+
+impl Collider {
+    pub fn new(shape: ColliderShape) -> Self {
+        Self {
+            shape,
+            restitution: 0.5,
+            friction: 0.1,
+            kill_on_contact: false,
+        }
+    }
+
+    /// Signed distance to the surface and the outward surface normal; mirrors
+    /// `collider_distance` in particles_update.wgsl.
+    fn distance(&self, position: [f32; 3], sdf: Option<&SdfVolume>) -> ([f32; 3], f32) {
+        match self.shape {
+            ColliderShape::Plane { point, normal } => {
+                let normal = normal_or_y(normal);
+                (normal, dot(sub(position, point), normal))
+            }
+            ColliderShape::Sphere { center, radius } => {
+                let offset = sub(position, center);
+                (normal_or_y(offset), dot(offset, offset).sqrt() - radius)
+            }
+            ColliderShape::Box {
+                center,
+                half_extents,
+                rotation,
+            } => {
+                let rotation = quaternion_or_identity(rotation);
+                let inverse = [-rotation[0], -rotation[1], -rotation[2], rotation[3]];
+                let local = rotate(inverse, sub(position, center));
+                let (local_normal, distance) = box_distance(local, half_extents);
+                (rotate(rotation, local_normal), distance)
+            }
+            ColliderShape::SdfVolume => match sdf {
+                Some(volume) => volume.sample(position),
+                None => ([0.0, 1.0, 0.0], SDF_OUTSIDE_DISTANCE),
+            },
+        }
+    }
+}
+
+/// Result of running one particle against every collider.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CollisionOutcome {
+    Free,
+    Bounced {
+        position: [f32; 3],
+        velocity: [f32; 3],
+        /// Largest approach speed along a contact normal; zero when only resting or sliding.
+        impact_speed: f32,
+    },
+    Killed,
+}
+
+/// Pushes penetrating particles back to the surface and reflects the approaching part of their
+/// velocity; mirrors `resolve_collisions` in particles_update.wgsl.
+pub(crate) fn resolve_collisions(
+    colliders: &ColliderList,
+    sdf: Option<&SdfVolume>,
+    mut position: [f32; 3],
+    mut velocity: [f32; 3],
+) -> CollisionOutcome {
+    let mut touched = false;
+    let mut impact_speed = 0.0f32;
+    for collider in colliders.as_slice() {
+        let (normal, distance) = collider.distance(position, sdf);
+        if distance >= 0.0 {
+            continue;
+        }
+        if collider.kill_on_contact {
+            return CollisionOutcome::Killed;
+        }
+        touched = true;
+        position = add(position, mul_scalar(normal, -distance));
+        let normal_speed = dot(velocity, normal);
+        if normal_speed < 0.0 {
+            impact_speed = impact_speed.max(-normal_speed);
+            let normal_velocity = mul_scalar(normal, normal_speed);
+            let tangent_velocity = sub(velocity, normal_velocity);
+            velocity = sub(
+                mul_scalar(tangent_velocity, 1.0 - collider.friction.clamp(0.0, 1.0)),
+                mul_scalar(normal_velocity, collider.restitution.max(0.0)),
+            );
+        }
+    }
+    if touched {
+        CollisionOutcome::Bounced {
+            position,
+            velocity,
+            impact_speed,
+        }
+    } else {
+        CollisionOutcome::Free
+    }
+}
+
+fn normal_or_y(v: [f32; 3]) -> [f32; 3] {
+    let n = normalize_or_zero(v);
+    if n == [0.0, 0.0, 0.0] {
+        [0.0, 1.0, 0.0]
+    } else {
+        n
+    }
+}
+
+fn box_distance(local: [f32; 3], half_extents: [f32; 3]) -> ([f32; 3], f32) {
+    let q = [
+        local[0].abs() - half_extents[0],
+        local[1].abs() - half_extents[1],
+        local[2].abs() - half_extents[2],
+    ];
+    let sign = local.map(|c| if c < 0.0 { -1.0 } else { 1.0 });
+    let outside = q.map(|c| c.max(0.0));
+    let outside_len = dot(outside, outside).sqrt();
+    if outside_len > 0.0 {
+        let normal = [
+            sign[0] * outside[0],
+            sign[1] * outside[1],
+            sign[2] * outside[2],
+        ];
+        return (mul_scalar(normal, outside_len.recip()), outside_len);
+    }
+    // Inside: leave through the nearest face.
+    let mut axis = 0;
+    for i in 1..3 {
+        if q[i] > q[axis] {
+            axis = i;
+        }
+    }
+    let mut normal = [0.0; 3];
+    normal[axis] = sign[axis];
+    (normal, q[axis])
+}
+
+/// Most nodes along any axis of an `SdfVolume`, so node counts cannot overflow. Devices may allow
+/// fewer; `ParticleGpuSim::set_sdf_volume` checks their 3D texture limit.
+const MAX_SDF_RESOLUTION: u32 = 2048;
+
+/// Signed distances baked on a regular grid of `resolution` nodes spanning
+/// `bounds_min..=bounds_max`, x varying fastest. Uploaded as an `R32Float` 3D texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SdfVolume {
+    resolution: [u32; 3],
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+    distances: Vec<f32>,
+}
+
+impl SdfVolume {
+    /// Evaluates `distance` at every grid node; each axis gets at least 2 nodes. `None` if an
+    /// axis has more than 2048.
+    pub fn bake(
+        resolution: [u32; 3],
+        bounds_min: [f32; 3],
+        bounds_max: [f32; 3],
+        distance: impl Fn([f32; 3]) -> f32,
+    ) -> Option<Self> {
+        let resolution = resolution.map(|n| n.max(2));
+        let step = [0, 1, 2].map(|a| (bounds_max[a] - bounds_min[a]) / (resolution[a] - 1) as f32);
+        let mut distances = Vec::with_capacity(node_count(resolution)?);
+        for z in 0..resolution[2] {
+            for y in 0..resolution[1] {
+                for x in 0..resolution[0] {
+                    let node = [x, y, z];
+                    distances.push(distance(
+                        [0, 1, 2].map(|a| bounds_min[a] + step[a] * node[a] as f32),
+                    ));
+                }
+            }
+        }
+        Some(Self {
+            resolution,
+            bounds_min,
+            bounds_max,
+            distances,
+        })
+    }
+
+    /// Wraps precomputed distances; `None` if the count does not match `resolution` or an
+    /// axis has fewer than 2 nodes or more than 2048.
+    pub fn from_distances(
+        resolution: [u32; 3],
+        bounds_min: [f32; 3],
+        bounds_max: [f32; 3],
+        distances: Vec<f32>,
+    ) -> Option<Self> {
+        if Some(distances.len()) != node_count(resolution) {
+            return None;
+        }
+        Some(Self {
+            resolution,
+            bounds_min,
+            bounds_max,
+            distances,
+        })
+    }
+
+    pub fn resolution(&self) -> [u32; 3] {
+        self.resolution
+    }
+
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        (self.bounds_min, self.bounds_max)
+    }
+
+    pub fn distances(&self) -> &[f32] {
+        &self.distances
+    }
+
+    fn node(&self, x: u32, y: u32, z: u32) -> f32 {
+        let [nx, ny, _] = self.resolution;
+        self.distances[(x + nx * (y + ny * z)) as usize]
+    }
+
+    /// Trilinear distance and normalized analytic gradient; mirrors `sample_sdf` in
+    /// particles_update.wgsl. Points outside the bounds are reported as far away.
+    fn sample(&self, position: [f32; 3]) -> ([f32; 3], f32) {
+        let mut grid = [0.0f32; 3];
+        let mut cell = [0u32; 3];
+        let mut frac = [0.0f32; 3];
+        let mut scale = [0.0f32; 3];
+        for a in 0..3 {
+            let extent = self.bounds_max[a] - self.bounds_min[a];
+            let last = (self.resolution[a] - 1) as f32;
+            if extent <= 0.0 {
+                return ([0.0, 1.0, 0.0], SDF_OUTSIDE_DISTANCE);
+            }
+            grid[a] = (position[a] - self.bounds_min[a]) / extent * last;
+            if !(0.0..=last).contains(&grid[a]) {
+                return ([0.0, 1.0, 0.0], SDF_OUTSIDE_DISTANCE);
+            }
+            cell[a] = (grid[a].floor() as u32).min(self.resolution[a] - 2);
+            frac[a] = grid[a] - cell[a] as f32;
+            scale[a] = last / extent;
+        }
+
+        let [x, y, z] = cell;
+        let c000 = self.node(x, y, z);
+        let c100 = self.node(x + 1, y, z);
+        let c010 = self.node(x, y + 1, z);
+        let c110 = self.node(x + 1, y + 1, z);
+        let c001 = self.node(x, y, z + 1);
+        let c101 = self.node(x + 1, y, z + 1);
+        let c011 = self.node(x, y + 1, z + 1);
+        let c111 = self.node(x + 1, y + 1, z + 1);
+        let [fx, fy, fz] = frac;
+
+        let distance = lerp(
+            lerp(lerp(c000, c100, fx), lerp(c010, c110, fx), fy),
+            lerp(lerp(c001, c101, fx), lerp(c011, c111, fx), fy),
+            fz,
+        );
+        let gradient = [
+            lerp(
+                lerp(c100 - c000, c110 - c010, fy),
+                lerp(c101 - c001, c111 - c011, fy),
+                fz,
+            ) * scale[0],
+            lerp(
+                lerp(c010 - c000, c110 - c100, fx),
+                lerp(c011 - c001, c111 - c101, fx),
+                fz,
+            ) * scale[1],
+            lerp(
+                lerp(c001 - c000, c101 - c100, fx),
+                lerp(c011 - c010, c111 - c110, fx),
+                fy,
+            ) * scale[2],
+        ];
+        (normal_or_y(gradient), distance)
+    }
+}
+
+/// Nodes in a grid of `resolution`, or `None` if an axis has fewer than 2 or more than
+/// `MAX_SDF_RESOLUTION`.
+fn node_count(resolution: [u32; 3]) -> Option<usize> {
+    resolution.iter().try_fold(1usize, |count, &n| {
+        if !(2..=MAX_SDF_RESOLUTION).contains(&n) {
+            return None;
+        }
+        count.checked_mul(n as usize)
+    })
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Fixed-capacity list of colliders, tested in order each step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColliderList {
+    colliders: [Collider; MAX_COLLIDERS],
+    len: usize,
+}
+
+impl ColliderList {
+    pub const fn new() -> Self {
+        Self {
+            colliders: [Collider {
+                shape: ColliderShape::SdfVolume,
+                restitution: 0.0,
+                friction: 0.0,
+                kill_on_contact: false,
+            }; MAX_COLLIDERS],
+            len: 0,
+        }
+    }
+
+    /// Takes at most `MAX_COLLIDERS` colliders; the rest are dropped.
+    pub fn from_slice(colliders: &[Collider]) -> Self {
+        let mut list = Self::new();
+        for collider in colliders.iter().take(MAX_COLLIDERS) {
+            list.push(*collider);
+        }
+        list
+    }
+
+    /// Returns `false` and leaves the list unchanged when it is full.
+    pub fn push(&mut self, collider: Collider) -> bool {
+        if self.len == MAX_COLLIDERS {
+            return false;
+        }
+        self.colliders[self.len] = collider;
+        self.len += 1;
+        true
+    }
+
+    pub fn as_slice(&self) -> &[Collider] {
+        &self.colliders[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Default for ColliderList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Mirrors `Collider` in particles_update.wgsl; `kind` uses the `COLLIDER_*` constants there.
+/// A plane keeps its normal in `rotation.xyz` and a sphere its radius in `extents.x`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub(crate) struct GpuCollider {
+    position: [f32; 3],
+    kind: u32,
+    rotation: [f32; 4],
+    extents: [f32; 3],
+    restitution: f32,
+    friction: f32,
+    kill_on_contact: u32,
+    _pad0: [u32; 2],
+}
+
+impl GpuCollider {
+    pub(crate) fn new(collider: &Collider) -> Self {
+        let base = Self {
+            restitution: collider.restitution.max(0.0),
+            friction: collider.friction.clamp(0.0, 1.0),
+            kill_on_contact: collider.kill_on_contact as u32,
+            ..Self::zeroed()
+        };
+        match collider.shape {
+            ColliderShape::Plane { point, normal } => {
+                let normal = normal_or_y(normal);
+                Self {
+                    position: point,
+                    kind: 0,
+                    rotation: [normal[0], normal[1], normal[2], 0.0],
+                    ..base
+                }
+            }
+            ColliderShape::Sphere { center, radius } => Self {
+                position: center,
+                kind: 1,
+                extents: [radius, 0.0, 0.0],
+                ..base
+            },
+            ColliderShape::Box {
+                center,
+                half_extents,
+                rotation,
+            } => Self {
+                position: center,
+                kind: 2,
+                rotation: quaternion_or_identity(rotation),
+                extents: half_extents,
+                ..base
+            },
+            ColliderShape::SdfVolume => Self { kind: 3, ..base },
+        }
+    }
+
+    /// Always `MAX_COLLIDERS` entries; the shader only reads the first `list.len()`.
+    pub(crate) fn pack(list: &ColliderList) -> [Self; MAX_COLLIDERS] {
+        let mut packed = [Self::zeroed(); MAX_COLLIDERS];
+        for (dst, collider) in packed.iter_mut().zip(list.as_slice()) {
+            *dst = Self::new(collider);
+        }
+        packed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        resolve_collisions, Collider, ColliderList, ColliderShape, CollisionOutcome, SdfVolume,
+    };
+    use crate::particles::math::{dot, sub};
+
+    #[test]
+    fn floor_bounces_with_restitution_and_friction() {
+        let floor = Collider {
+            restitution: 0.5,
+            friction: 0.25,
+            ..Collider::new(ColliderShape::Plane {
+                point: [0.0, -1.0, 0.0],
+                normal: [0.0, 1.0, 0.0],
+            })
+        };
+        let list = ColliderList::from_slice(&[floor]);
+        let outcome = resolve_collisions(&list, None, [0.3, -1.1, 0.0], [2.0, -4.0, 0.0]);
+        let CollisionOutcome::Bounced {
+            position,
+            velocity,
+            impact_speed,
+        } = outcome
+        else {
+            panic!("expected a bounce, got {outcome:?}");
+        };
+        assert_eq!(impact_speed, 4.0);
+        assert!((position[1] + 1.0).abs() < 1e-6);
+        assert!((velocity[0] - 1.5).abs() < 1e-6);
+        assert!((velocity[1] - 2.0).abs() < 1e-6);
+
+        let killer = Collider {
+            kill_on_contact: true,
+            ..floor
+        };
+        let list = ColliderList::from_slice(&[killer]);
+        assert_eq!(
+            resolve_collisions(&list, None, [0.0, -1.5, 0.0], [0.0; 3]),
+            CollisionOutcome::Killed
+        );
+    }
+
+    #[test]
+    fn rotated_box_pushes_out_through_nearest_face() {
+        let sin_45 = std::f32::consts::FRAC_PI_4.sin();
+        let collider = Collider::new(ColliderShape::Box {
+            center: [1.0, 0.0, 0.0],
+            half_extents: [1.0, 0.2, 0.5],
+            // 90 degrees around z: the thin local y axis now points along world x.
+            rotation: [0.0, 0.0, sin_45, sin_45],
+        });
+        let list = ColliderList::from_slice(&[collider]);
+        let outcome = resolve_collisions(&list, None, [1.1, 0.5, 0.0], [0.0; 3]);
+        let CollisionOutcome::Bounced { position, .. } = outcome else {
+            panic!("expected contact, got {outcome:?}");
+        };
+        assert!((position[0] - 1.2).abs() < 1e-5, "{position:?}");
+        assert!((position[1] - 0.5).abs() < 1e-5, "{position:?}");
+    }
+
+    #[test]
+    fn baked_sphere_sdf_matches_analytic_sphere() {
+        let volume = SdfVolume::bake([17, 17, 17], [-1.0; 3], [1.0; 3], |p| {
+            dot(p, p).sqrt() - 0.5
+        })
+        .unwrap();
+        let collider = Collider::new(ColliderShape::SdfVolume);
+        let list = ColliderList::from_slice(&[collider]);
+        let p = [0.3, 0.2, -0.1];
+        let outcome = resolve_collisions(&list, Some(&volume), p, [0.0; 3]);
+        let CollisionOutcome::Bounced { position, .. } = outcome else {
+            panic!("expected contact, got {outcome:?}");
+        };
+        let radius = dot(position, position).sqrt();
+        assert!((radius - 0.5).abs() < 0.03, "radius {radius}");
+        let moved = sub(position, p);
+        assert!(dot(moved, p) > 0.0);
+        assert_eq!(
+            resolve_collisions(&list, None, p, [0.0; 3]),
+            CollisionOutcome::Free
+        );
+
+        let huge = [u32::MAX; 3];
+        assert!(SdfVolume::bake(huge, [-1.0; 3], [1.0; 3], |_| 0.0).is_none());
+        assert!(SdfVolume::from_distances(huge, [-1.0; 3], [1.0; 3], Vec::new()).is_none());
+    }
+}
@@ -1,3 +1,7 @@
+use std::mem::size_of;
+
+use super::attributes::ParticleAttributeSchema;
+
 #[derive(Debug, Clone, Copy)]
 pub struct ParticleWorkgroup {
     pub x: u32,
@@ -38,7 +42,34 @@ pub const PARTICLE_UPDATE_SHADER_PATH: &str = "shaders/particles_update.wgsl";
 #[derive(Debug, Clone, Copy)]
 pub struct ParticleBufferLayout {
     pub particle_stride_bytes: u64,
+    /// Stride of the parallel attribute buffer; zero with an empty `ParticleAttributeSchema`.
+    pub attribute_stride_bytes: u64,
     pub sim_uniform_bytes: u64,
+    pub counter_bytes: u64,
+    pub dispatch_args_bytes: u64,
+    pub stats_bytes: u64,
+    pub force_fields_bytes: u64,
+    pub colliders_bytes: u64,
+    pub particle_event_bytes: u64,
+}
+
+impl ParticleBufferLayout {
+    pub fn with_attributes(schema: ParticleAttributeSchema) -> Self {
+        Self {
+            attribute_stride_bytes: schema.layout().stride_bytes as u64,
+            ..Self::default()
+        }
+    }
+
+    /// Dead stack plus two ping-pong alive lists, each holding one `u32` slot index per particle.
+    pub fn index_list_bytes(&self, max_particles: u32) -> u64 {
+        3 * size_of::<u32>() as u64 * max_particles as u64
+    }
+
+    /// Event count padded to 16 bytes, then room for `max_events` events (at least one).
+    pub fn event_queue_bytes(&self, max_events: u32) -> u64 {
+        16 + self.particle_event_bytes * max_events.max(1) as u64
+    }
 }
 
 impl Default for ParticleBufferLayout {
@@ -46,8 +77,21 @@ impl Default for ParticleBufferLayout {
         Self {
             // position.xyz + age + velocity.xyz + lifetime
             particle_stride_bytes: 32,
+            attribute_stride_bytes: 0,
             // Keep this aligned to 16-byte boundaries for std140-like packing.
-            sim_uniform_bytes: 64,
+            sim_uniform_bytes: 400,
+            // dead/alive counters, list parity and the reserved emit range.
+            counter_bytes: 24,
+            // update, emit and sub-emit workgroup counts for `dispatch_workgroups_indirect`.
+            dispatch_args_bytes: 36,
+            // Per-step telemetry block, padded to 16 bytes.
+            stats_bytes: 48,
+            // `MAX_FORCE_FIELDS` packed fields of 48 bytes each.
+            force_fields_bytes: 16 * 48,
+            // `MAX_COLLIDERS` packed colliders of 64 bytes each.
+            colliders_bytes: 16 * 64,
+            // position + kind, velocity + padding.
+            particle_event_bytes: 32,
         }
     }
 }
@@ -1,9 +1,51 @@
+use super::attributes::ParticleAttributeSchema;
+use super::boids::BoidsConfig;
+use super::burst::BurstSchedule;
+use super::constraints::PbdConfig;
+use super::forces::ForceFieldList;
+use super::integrator::Integrator;
+use super::morph::MorphConfig;
+use super::sph::SphConfig;
+use super::sub_emitter::SubEmitterConfig;
+use super::trails::TrailConfig;
+use super::watchdog::NonFinitePolicy;
+
+/// How particles interact with each other.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleSimMode {
+    /// Every particle moves independently.
+    Ballistic,
+    /// Particles form a fluid held in `SphConfig`'s container.
+    Sph(SphConfig),
+    /// Particles flock, steering by their neighbors instead of following the forces.
+    Boids(BoidsConfig),
+    /// Particles placed by `set_constraints` hold together as strands, sheets and soft bodies.
+    Pbd(PbdConfig),
+    /// Particles spring towards the targets set by `set_morph_targets` until released.
+    Morph(MorphConfig),
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct ParticleSimConfig {
     pub max_particles: u32,
     pub spawn_rate_per_second: f32,
+    /// Exponential velocity decay per second: with no forces, speed falls by `exp(-drag * t)`.
     pub drag: f32,
     pub lifetime_seconds: f32,
+    pub integrator: Integrator,
+    /// Selects the spawn random streams; runs with equal seeds and inputs are reproducible, on
+    /// the CPU and GPU alike.
+    pub seed: u32,
+    /// What the watchdog in the update step does with particles whose state became NaN or
+    /// infinite.
+    pub non_finite_policy: NonFinitePolicy,
+    /// Extra per-particle attributes; empty by default, which keeps the 32-byte `Particle` only.
+    pub attributes: ParticleAttributeSchema,
+    /// Children spawned at the death or collision events of emitted particles.
+    pub sub_emitter: Option<SubEmitterConfig>,
+    /// Per-particle position history for ribbon trails; `None` records nothing.
+    pub trails: Option<TrailConfig>,
+    pub mode: ParticleSimMode,
 }
 
 impl Default for ParticleSimConfig {
@@ -11,25 +53,112 @@ impl Default for ParticleSimConfig {
         Self {
             max_particles: 100_000,
             spawn_rate_per_second: 8_000.0,
-            drag: 0.96,
+            // About the 0.96-per-step multiplier used before drag was per second, at 60 Hz.
+            drag: 2.45,
             lifetime_seconds: 3.0,
+            integrator: Integrator::SemiImplicitEuler,
+            seed: 0,
+            non_finite_policy: NonFinitePolicy::Count,
+            attributes: ParticleAttributeSchema::default(),
+            sub_emitter: None,
+            trails: None,
+            mode: ParticleSimMode::Ballistic,
         }
     }
 }
 
+/// Region particles are emitted from, relative to `EmitterConfig::center`.
+/// Axis and normal vectors do not need to be normalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterShape {
+    Sphere {
+        radius: f32,
+    },
+    Box {
+        half_extents: [f32; 3],
+    },
+    Disc {
+        radius: f32,
+        normal: [f32; 3],
+    },
+    /// Emits from a base disc of `radius`, tilting directions up to `angle_radians` from `axis`
+    /// towards the rim.
+    Cone {
+        radius: f32,
+        angle_radians: f32,
+        axis: [f32; 3],
+    },
+    /// Segment of `length` centered on the emitter, oriented along `direction`.
+    Line {
+        direction: [f32; 3],
+        length: f32,
+    },
+    /// Torus of major `radius` around `normal`; `thickness` is the tube radius.
+    Ring {
+        radius: f32,
+        thickness: f32,
+        normal: [f32; 3],
+    },
+    /// Rectangle of `2 * half_extents` in the XY plane, facing +z, spawning in proportion to the
+    /// weights of the image set with `set_spawn_image` (uniformly until one is set). Image row 0
+    /// is at +y. With `pixel_color`, spawn colors are multiplied by the sampled pixel.
+    Image {
+        half_extents: [f32; 2],
+        pixel_color: bool,
+    },
+}
+
+/// Whether positions fill the shape or stay on its boundary (sphere shell, box faces,
+/// disc/cone rim, ring tube surface). Lines and images behave the same in both modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmissionMode {
+    Volume,
+    Surface,
+}
+
+/// Initial velocity direction of spawned particles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmitterDirection {
+    /// Per-shape rule: outward normal for sphere, box, disc and ring, the cone spread for cones,
+    /// the line axis for lines and +z for images.
+    Shape,
+    /// Every particle leaves along this (not necessarily normalized) direction.
+    Along([f32; 3]),
+    /// Uniformly distributed over the unit sphere.
+    Random,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct EmitterConfig {
     pub center: [f32; 3],
-    pub radius: f32,
+    pub shape: EmitterShape,
+    pub mode: EmissionMode,
+    pub direction: EmitterDirection,
     pub initial_speed: f32,
+    /// Spawn color, when the attribute schema enables `color`; see also `EmitterShape::Image`.
+    pub color: [f32; 4],
+    /// Spawn size, jittered by up to `size_variance` of itself either way.
+    pub size: f32,
+    pub size_variance: f32,
+    /// Spawn angular velocity is uniform in `[-angular_speed, angular_speed]` radians/second.
+    pub angular_speed: f32,
+    /// Extra particles fired at scheduled sim times, on top of `spawn_rate_per_second`.
+    pub bursts: BurstSchedule,
 }
 
 impl Default for EmitterConfig {
     fn default() -> Self {
         Self {
             center: [0.0, 0.0, 0.0],
-            radius: 0.25,
+            shape: EmitterShape::Sphere { radius: 0.25 },
+            mode: EmissionMode::Volume,
+            direction: EmitterDirection::Shape,
             initial_speed: 1.0,
+            color: [1.0; 4],
+            size: 0.02,
+            size_variance: 0.0,
+            angular_speed: 0.0,
+            bursts: BurstSchedule::new(),
         }
     }
 }
@@ -37,18 +166,27 @@ impl Default for EmitterConfig {
 #[derive(Debug, Clone, Copy)]
 pub struct ForceConfig {
     pub gravity: [f32; 3],
-    pub attractor: [f32; 3],
-    pub attractor_strength: f32,
+    /// Attractors, vortices, wind and drag zones, summed on top of gravity and noise.
+    pub fields: ForceFieldList,
+    /// Scale of the per-step curl-noise acceleration; zero disables it.
     pub noise_strength: f32,
+    /// Spatial frequency of the first noise octave, in cycles per world unit.
+    pub noise_frequency: f32,
+    /// Octaves summed at doubling frequency and halving weight, capped at `MAX_NOISE_OCTAVES`.
+    pub noise_octaves: u32,
+    /// How fast the field drifts through noise space, in noise units per second.
+    pub noise_time_scale: f32,
 }
 
 impl Default for ForceConfig {
     fn default() -> Self {
         Self {
             gravity: [0.0, -0.4, 0.0],
-            attractor: [0.0, 0.0, 0.0],
-            attractor_strength: 0.0,
+            fields: ForceFieldList::new(),
             noise_strength: 0.15,
+            noise_frequency: 1.5,
+            noise_octaves: 2,
+            noise_time_scale: 0.25,
         }
     }
 }
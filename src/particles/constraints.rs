@@ -0,0 +1,718 @@
+use std::borrow::Cow;
+use std::mem::size_of;
+use std::ops::Range;
+
+use bytemuck::{bytes_of, cast_slice, Pod, Zeroable};
+
+use super::math::{add, dot, mul_scalar, sub};
+use super::simulation::Particle;
+use super::spatial_grid::{layout_entry, storage_binding, uniform_binding};
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Which `PbdConfig` compliance a constraint uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintKind {
+    /// Neighbors along a strand or the edges of a sheet or block.
+    Stretch = 0,
+    /// Diagonals of sheet quads and block cells, resisting skew.
+    Shear = 1,
+    /// Particles two apart along a strand or sheet row, resisting folding.
+    Bend = 2,
+}
+
+/// Keeps particles `a` and `b` of a `ConstraintTopology` `rest_length` apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceConstraint {
+    pub a: u32,
+    pub b: u32,
+    pub rest_length: f32,
+    pub kind: ConstraintKind,
+}
+
+/// Particles and distance constraints of one or more bodies. `ParticleState::set_constraints`
+/// and `ParticleGpuSim::set_constraints` place particle `i` in pool slot `i`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConstraintTopology {
+    positions: Vec<[f32; 3]>,
+    inverse_masses: Vec<f32>,
+    constraints: Vec<DistanceConstraint>,
+}
+
+impl ConstraintTopology {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `n` particles hanging from the origin along -y, `length` from end to end, with stretch
+    /// and bend constraints. Pin particle 0 to attach the root.
+    pub fn strand(n: u32, length: f32) -> Self {
+        let spacing = length / n.saturating_sub(1).max(1) as f32;
+        let mut topology = Self::new();
+        for i in 0..n {
+            topology.add_particle([0.0, -spacing * i as f32, 0.0], 1.0);
+        }
+        for i in 1..n {
+            topology.connect(i - 1, i, ConstraintKind::Stretch);
+            if i >= 2 {
+                topology.connect(i - 2, i, ConstraintKind::Bend);
+            }
+        }
+        topology
+    }
+
+    /// `w` by `h` particles `spacing` apart in the XZ plane, starting at the origin, with
+    /// stretch edges, shear diagonals and bend constraints along rows and columns. Particle
+    /// `x + z * w` sits at column `x`, row `z`.
+    pub fn grid_sheet(w: u32, h: u32, spacing: f32) -> Self {
+        let mut topology = Self::new();
+        for z in 0..h {
+            for x in 0..w {
+                topology.add_particle([x as f32 * spacing, 0.0, z as f32 * spacing], 1.0);
+            }
+        }
+        let at = |x: u32, z: u32| x + z * w;
+        for z in 0..h {
+            for x in 0..w {
+                if x + 1 < w {
+                    topology.connect(at(x, z), at(x + 1, z), ConstraintKind::Stretch);
+                }
+                if z + 1 < h {
+                    topology.connect(at(x, z), at(x, z + 1), ConstraintKind::Stretch);
+                }
+                if x + 1 < w && z + 1 < h {
+                    topology.connect(at(x, z), at(x + 1, z + 1), ConstraintKind::Shear);
+                    topology.connect(at(x + 1, z), at(x, z + 1), ConstraintKind::Shear);
+                }
+                if x + 2 < w {
+                    topology.connect(at(x, z), at(x + 2, z), ConstraintKind::Bend);
+                }
+                if z + 2 < h {
+                    topology.connect(at(x, z), at(x, z + 2), ConstraintKind::Bend);
+                }
+            }
+        }
+        topology
+    }
+
+    /// `nx` by `ny` by `nz` lattice `spacing` apart from the origin, with stretch edges and
+    /// shear constraints across every face and cell diagonal; a jelly blob with soft shear.
+    pub fn soft_block(nx: u32, ny: u32, nz: u32, spacing: f32) -> Self {
+        let mut topology = Self::new();
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    topology.add_particle(mul_scalar([x as f32, y as f32, z as f32], spacing), 1.0);
+                }
+            }
+        }
+        let at = |x: u32, y: u32, z: u32| x + nx * (y + ny * z);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    // Half of the 26-neighborhood, so every pair is connected once.
+                    for (dx, dy, dz) in HALF_NEIGHBORHOOD {
+                        let (ox, oy, oz) = (x as i32 + dx, y as i32 + dy, z as i32 + dz);
+                        if ox < 0
+                            || oy < 0
+                            || oz < 0
+                            || ox >= nx as i32
+                            || oy >= ny as i32
+                            || oz >= nz as i32
+                        {
+                            continue;
+                        }
+                        let kind = if dx.abs() + dy.abs() + dz.abs() == 1 {
+                            ConstraintKind::Stretch
+                        } else {
+                            ConstraintKind::Shear
+                        };
+                        topology.connect(at(x, y, z), at(ox as u32, oy as u32, oz as u32), kind);
+                    }
+                }
+            }
+        }
+        topology
+    }
+
+    /// Appends a particle; an `inverse_mass` of zero pins it in place.
+    pub fn add_particle(&mut self, position: [f32; 3], inverse_mass: f32) -> u32 {
+        self.positions.push(position);
+        self.inverse_masses.push(inverse_mass);
+        self.positions.len() as u32 - 1
+    }
+
+    /// Constrains `a` and `b` to stay as far apart as they are now.
+    pub fn connect(&mut self, a: u32, b: u32, kind: ConstraintKind) {
+        let offset = sub(self.positions[a as usize], self.positions[b as usize]);
+        self.constraints.push(DistanceConstraint {
+            a,
+            b,
+            rest_length: dot(offset, offset).sqrt(),
+            kind,
+        });
+    }
+
+    /// Fixes particle `index` at its position; constraints still pull its neighbors towards it.
+    pub fn pin(&mut self, index: u32) {
+        self.inverse_masses[index as usize] = 0.0;
+    }
+
+    pub fn translate(&mut self, offset: [f32; 3]) {
+        for position in &mut self.positions {
+            *position = add(*position, offset);
+        }
+    }
+
+    /// Adds the particles and constraints of `other` after this topology's own.
+    pub fn append(&mut self, other: &ConstraintTopology) {
+        let base = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.inverse_masses.extend_from_slice(&other.inverse_masses);
+        self.constraints
+            .extend(other.constraints.iter().map(|c| DistanceConstraint {
+                a: c.a + base,
+                b: c.b + base,
+                ..*c
+            }));
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn positions(&self) -> &[[f32; 3]] {
+        &self.positions
+    }
+
+    pub fn inverse_masses(&self) -> &[f32] {
+        &self.inverse_masses
+    }
+
+    pub fn constraints(&self) -> &[DistanceConstraint] {
+        &self.constraints
+    }
+
+    /// Particles placed in slots `0..len()`, at rest and never expiring.
+    pub(crate) fn particles(&self) -> impl Iterator<Item = Particle> + '_ {
+        self.positions.iter().map(|&position| Particle {
+            position,
+            age_seconds: 0.0,
+            velocity: [0.0; 3],
+            lifetime_seconds: f32::MAX,
+        })
+    }
+}
+
+const HALF_NEIGHBORHOOD: [(i32, i32, i32); 13] = [
+    (1, 0, 0),
+    (0, 1, 0),
+    (0, 0, 1),
+    (1, 1, 0),
+    (1, -1, 0),
+    (1, 0, 1),
+    (1, 0, -1),
+    (0, 1, 1),
+    (0, 1, -1),
+    (1, 1, 1),
+    (1, 1, -1),
+    (1, -1, 1),
+    (1, -1, -1),
+];
+
+/// XPBD distance-constraint solve after every integration step. Compliance is the inverse
+/// stiffness in meters per newton, with particle masses of `1 / inverse_mass`; zero is rigid.
+/// Colliders are resolved by the update before the solve, so heavily loaded bodies can sink
+/// slightly into them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PbdConfig {
+    /// Passes over every constraint per step. Corrections travel about one constraint per
+    /// pass, so long strands under strong forces need more to avoid visible stretch.
+    pub solver_iterations: u32,
+    pub stretch_compliance: f32,
+    pub shear_compliance: f32,
+    pub bend_compliance: f32,
+}
+
+impl Default for PbdConfig {
+    fn default() -> Self {
+        Self {
+            solver_iterations: 8,
+            stretch_compliance: 0.0,
+            shear_compliance: 1.0e-5,
+            bend_compliance: 1.0e-3,
+        }
+    }
+}
+
+impl PbdConfig {
+    fn compliances(&self) -> [f32; 4] {
+        [
+            self.stretch_compliance,
+            self.shear_compliance,
+            self.bend_compliance,
+            0.0,
+        ]
+    }
+}
+
+/// A topology's constraints sorted into batches that share no particle, so each batch can be
+/// solved in parallel with the same result as solving it in order.
+#[derive(Debug, Clone)]
+pub(crate) struct ConstraintSolver {
+    inverse_masses: Vec<f32>,
+    constraints: Vec<DistanceConstraint>,
+    batches: Vec<Range<usize>>,
+}
+
+impl ConstraintSolver {
+    /// Greedy coloring: each constraint takes the lowest batch neither endpoint is in yet.
+    pub(crate) fn new(topology: &ConstraintTopology) -> Self {
+        let mut used: Vec<Vec<usize>> = vec![Vec::new(); topology.len()];
+        let mut colored: Vec<(usize, DistanceConstraint)> = topology
+            .constraints
+            .iter()
+            .map(|&c| {
+                let color = (0..)
+                    .find(|color| {
+                        !used[c.a as usize].contains(color) && !used[c.b as usize].contains(color)
+                    })
+                    .unwrap_or_default();
+                used[c.a as usize].push(color);
+                used[c.b as usize].push(color);
+                (color, c)
+            })
+            .collect();
+        colored.sort_by_key(|&(color, _)| color);
+
+        let mut batches: Vec<Range<usize>> = Vec::new();
+        for (i, &(color, _)) in colored.iter().enumerate() {
+            if color == batches.len() {
+                batches.push(i..i);
+            }
+            batches[color].end = i + 1;
+        }
+        Self {
+            inverse_masses: topology.inverse_masses.clone(),
+            constraints: colored.into_iter().map(|(_, c)| c).collect(),
+            batches,
+        }
+    }
+
+    pub(crate) fn particle_count(&self) -> usize {
+        self.inverse_masses.len()
+    }
+
+    /// CPU reference of the `begin_solve`, `solve` and `finish_solve` kernels in constraints.wgsl.
+    /// `previous` holds slots `0..particle_count()` as they were before the step.
+    pub(crate) fn solve_reference(
+        &self,
+        particles: &mut [Particle],
+        previous: &[Particle],
+        config: &PbdConfig,
+        dt: f32,
+    ) {
+        if dt <= 0.0 {
+            return;
+        }
+        let bodies = &mut particles[..self.particle_count()];
+        for ((p, prev), &w) in bodies.iter_mut().zip(previous).zip(&self.inverse_masses) {
+            if w == 0.0 {
+                p.position = prev.position;
+                p.velocity = [0.0; 3];
+            }
+        }
+
+        let compliances = config.compliances();
+        let mut lambdas = vec![0.0f32; self.constraints.len()];
+        for _ in 0..config.solver_iterations {
+            for batch in &self.batches {
+                for i in batch.clone() {
+                    let c = self.constraints[i];
+                    let alpha = compliances[c.kind as usize] / (dt * dt);
+                    let (a, b) = (c.a as usize, c.b as usize);
+                    let (wa, wb) = (self.inverse_masses[a], self.inverse_masses[b]);
+                    if wa + wb == 0.0 || !bodies[a].is_alive() || !bodies[b].is_alive() {
+                        continue;
+                    }
+                    let offset = sub(bodies[a].position, bodies[b].position);
+                    let length = dot(offset, offset).sqrt();
+                    if length < 1e-6 {
+                        continue;
+                    }
+                    let error = length - c.rest_length;
+                    let delta = (-error - alpha * lambdas[i]) / (wa + wb + alpha);
+                    lambdas[i] += delta;
+                    let push = mul_scalar(offset, delta / length);
+                    bodies[a].position = add(bodies[a].position, mul_scalar(push, wa));
+                    bodies[b].position = sub(bodies[b].position, mul_scalar(push, wb));
+                }
+            }
+        }
+
+        for ((p, prev), &w) in bodies.iter_mut().zip(previous).zip(&self.inverse_masses) {
+            if w > 0.0 && p.is_alive() {
+                p.velocity = mul_scalar(sub(p.position, prev.position), 1.0 / dt);
+            }
+        }
+    }
+}
+
+/// Mirrors `Constraint` in constraints.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuConstraint {
+    a: u32,
+    b: u32,
+    rest_length: f32,
+    kind: u32,
+}
+
+/// Mirrors `SolveParams` in constraints.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuSolveParams {
+    compliance: [f32; 4],
+    dt: f32,
+    particle_count: u32,
+    constraint_count: u32,
+    _pad0: u32,
+}
+
+/// Mirrors `Batch` in constraints.wgsl; one per batch, `batch_stride` bytes apart.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuBatch {
+    first: u32,
+    count: u32,
+    _pad0: [u32; 2],
+}
+
+/// GPU side of `ConstraintSolver`, encoded by `ParticleGpuSim` around the update kernel.
+pub(crate) struct ConstraintPasses {
+    particle_count: u32,
+    constraint_count: u32,
+    batch_stride: u64,
+    batch_sizes: Vec<u32>,
+    previous_buffer: wgpu::Buffer,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    batch_bind_group: wgpu::BindGroup,
+    begin_solve: wgpu::ComputePipeline,
+    solve: wgpu::ComputePipeline,
+    finish_solve: wgpu::ComputePipeline,
+}
+
+impl ConstraintPasses {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particle_buffer: &wgpu::Buffer,
+        solver: &ConstraintSolver,
+    ) -> Self {
+        let particle_count = solver.particle_count() as u32;
+        let storage = |label: &str, contents: &[u8], usage: wgpu::BufferUsages| {
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: (contents.len() as u64).max(16),
+                usage: usage | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            queue.write_buffer(&buffer, 0, contents);
+            buffer
+        };
+        let constraints: Vec<GpuConstraint> = solver
+            .constraints
+            .iter()
+            .map(|c| GpuConstraint {
+                a: c.a,
+                b: c.b,
+                rest_length: c.rest_length,
+                kind: c.kind as u32,
+            })
+            .collect();
+        // Slots `0..particle_count` as they were before the step, copied ahead of `update`.
+        let previous_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.constraints.previous"),
+            size: (size_of::<Particle>() as u64 * particle_count as u64).max(16),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let inverse_mass_buffer = storage(
+            "particles.constraints.inverse_masses",
+            cast_slice(&solver.inverse_masses),
+            wgpu::BufferUsages::STORAGE,
+        );
+        let constraint_buffer = storage(
+            "particles.constraints.constraints",
+            cast_slice(&constraints),
+            wgpu::BufferUsages::STORAGE,
+        );
+        let lambda_buffer = storage(
+            "particles.constraints.lambdas",
+            &vec![0; constraints.len() * size_of::<f32>()],
+            wgpu::BufferUsages::STORAGE,
+        );
+        let params_buffer = storage(
+            "particles.constraints.params",
+            bytes_of(&GpuSolveParams::zeroed()),
+            wgpu::BufferUsages::UNIFORM,
+        );
+
+        let batch_stride = (size_of::<GpuBatch>() as u64)
+            .next_multiple_of(device.limits().min_uniform_buffer_offset_alignment as u64);
+        let mut batch_bytes = vec![0u8; batch_stride as usize * solver.batches.len().max(1)];
+        for (i, batch) in solver.batches.iter().enumerate() {
+            let gpu_batch = GpuBatch {
+                first: batch.start as u32,
+                count: batch.len() as u32,
+                _pad0: [0; 2],
+            };
+            let at = i * batch_stride as usize;
+            batch_bytes[at..at + size_of::<GpuBatch>()].copy_from_slice(bytes_of(&gpu_batch));
+        }
+        let batch_buffer = storage(
+            "particles.constraints.batches",
+            &batch_bytes,
+            wgpu::BufferUsages::UNIFORM,
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.constraints.bgl"),
+            entries: &[
+                layout_entry(0, storage_binding(false)),
+                layout_entry(1, storage_binding(true)),
+                layout_entry(2, storage_binding(true)),
+                layout_entry(3, storage_binding(true)),
+                layout_entry(4, storage_binding(false)),
+                layout_entry(5, uniform_binding()),
+            ],
+        });
+        let batch_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.constraints.batch.bgl"),
+            entries: &[layout_entry(
+                0,
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(size_of::<GpuBatch>() as u64),
+                },
+            )],
+        });
+        let entries: Vec<wgpu::BindGroupEntry> = [
+            particle_buffer,
+            &previous_buffer,
+            &inverse_mass_buffer,
+            &constraint_buffer,
+            &lambda_buffer,
+            &params_buffer,
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.constraints.bg"),
+            layout: &bind_group_layout,
+            entries: &entries,
+        });
+        let batch_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.constraints.batch.bg"),
+            layout: &batch_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &batch_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(size_of::<GpuBatch>() as u64),
+                }),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles.constraints.shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/shaders/constraints.wgsl"
+            )))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles.constraints.pl"),
+            bind_group_layouts: &[&bind_group_layout, &batch_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("particles.constraints.{entry_point}.pipeline")),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            })
+        };
+
+        Self {
+            particle_count,
+            constraint_count: constraints.len() as u32,
+            batch_stride,
+            batch_sizes: solver.batches.iter().map(|b| b.len() as u32).collect(),
+            previous_buffer,
+            params_buffer,
+            bind_group,
+            batch_bind_group,
+            begin_solve: create_pipeline("begin_solve"),
+            solve: create_pipeline("solve"),
+            finish_solve: create_pipeline("finish_solve"),
+        }
+    }
+
+    /// Snapshots the constrained slots; encode before the update kernel.
+    pub(crate) fn encode_snapshot(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        particle_buffer: &wgpu::Buffer,
+    ) {
+        if self.particle_count > 0 {
+            encoder.copy_buffer_to_buffer(
+                particle_buffer,
+                0,
+                &self.previous_buffer,
+                0,
+                size_of::<Particle>() as u64 * self.particle_count as u64,
+            );
+        }
+    }
+
+    /// Solves the constraints; encode after the update and emit kernels.
+    pub(crate) fn encode_solve(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        config: &PbdConfig,
+        dt: f32,
+    ) {
+        if self.particle_count == 0 || dt <= 0.0 {
+            return;
+        }
+        let params = GpuSolveParams {
+            compliance: config.compliances(),
+            dt,
+            particle_count: self.particle_count,
+            constraint_count: self.constraint_count,
+            _pad0: 0,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytes_of(&params));
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particles.constraints.pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_bind_group(1, &self.batch_bind_group, &[0]);
+        pass.set_pipeline(&self.begin_solve);
+        pass.dispatch_workgroups(
+            self.particle_count
+                .max(self.constraint_count)
+                .div_ceil(WORKGROUP_SIZE),
+            1,
+            1,
+        );
+        pass.set_pipeline(&self.solve);
+        for _ in 0..config.solver_iterations {
+            for (batch, &size) in self.batch_sizes.iter().enumerate() {
+                let offset = (batch as u64 * self.batch_stride) as u32;
+                pass.set_bind_group(1, &self.batch_bind_group, &[offset]);
+                pass.dispatch_workgroups(size.div_ceil(WORKGROUP_SIZE), 1, 1);
+            }
+        }
+        pass.set_pipeline(&self.finish_solve);
+        pass.dispatch_workgroups(self.particle_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConstraintKind, ConstraintSolver, ConstraintTopology, PbdConfig};
+    use crate::particles::math::{dot, sub};
+    use crate::particles::{
+        EmitterConfig, ForceConfig, ParticleSimConfig, ParticleSimMode, ParticleState,
+    };
+
+    #[test]
+    fn batches_never_share_a_particle() {
+        let sheet = ConstraintTopology::grid_sheet(6, 5, 0.1);
+        let count = |kind| {
+            sheet
+                .constraints()
+                .iter()
+                .filter(|c| c.kind == kind)
+                .count()
+        };
+        assert_eq!(count(ConstraintKind::Stretch), 5 * 5 + 6 * 4);
+        assert_eq!(count(ConstraintKind::Shear), 2 * 5 * 4);
+        assert_eq!(count(ConstraintKind::Bend), 4 * 5 + 6 * 3);
+
+        let mut bodies = sheet.clone();
+        bodies.append(&ConstraintTopology::soft_block(3, 3, 3, 0.1));
+        let solver = ConstraintSolver::new(&bodies);
+        assert_eq!(solver.constraints.len(), bodies.constraints().len());
+        for batch in &solver.batches {
+            let mut touched = vec![false; bodies.len()];
+            for c in &solver.constraints[batch.clone()] {
+                assert!(!touched[c.a as usize] && !touched[c.b as usize]);
+                touched[c.a as usize] = true;
+                touched[c.b as usize] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_strand_hangs_from_its_root_at_rest_length() {
+        let mut strand = ConstraintTopology::strand(8, 0.4);
+        strand.pin(0);
+        strand.translate([0.0, 0.5, 0.0]);
+        let config = ParticleSimConfig {
+            max_particles: 64,
+            spawn_rate_per_second: 30.0,
+            mode: ParticleSimMode::Pbd(PbdConfig {
+                solver_iterations: 20,
+                ..PbdConfig::default()
+            }),
+            ..ParticleSimConfig::default()
+        };
+        let force = ForceConfig {
+            gravity: [0.0, -9.8, 0.0],
+            ..ForceConfig::default()
+        };
+        let mut state = ParticleState::new(config);
+        assert!(!state.set_constraints(&ConstraintTopology::strand(65, 1.0)));
+        assert!(state.set_constraints(&strand));
+
+        // Swing the strand out sideways, then let it settle.
+        state.particles[7].position[0] = 0.3;
+        for _ in 0..240 {
+            state.step_reference(1.0 / 60.0, config, EmitterConfig::default(), force);
+        }
+        assert_eq!(state.particles[0].position, [0.0, 0.5, 0.0]);
+        assert!(state.alive_count() > strand.len());
+        for c in strand.constraints() {
+            let offset = sub(
+                state.particles[c.a as usize].position,
+                state.particles[c.b as usize].position,
+            );
+            let length = dot(offset, offset).sqrt();
+            if c.kind == ConstraintKind::Stretch {
+                assert!((length / c.rest_length - 1.0).abs() < 0.03, "{length}");
+            }
+        }
+        let tip = state.particles[7].position;
+        assert!(tip[0].abs() < 0.02 && tip[1] < 0.5 - 0.38, "{tip:?}");
+    }
+}
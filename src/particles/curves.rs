@@ -0,0 +1,238 @@
+use super::attributes::ParticleAttributes;
+
+/// Upper bound on the keys of one `Curve`.
+pub const MAX_CURVE_KEYS: usize = 8;
+/// Texels per LUT row; rows are indexed by normalized age in `[0, 1]`.
+pub const LIFETIME_LUT_WIDTH: u32 = 64;
+/// Row 0: color multiplier in rgb, opacity in a. Row 1: size and speed multipliers in x and y.
+pub const LIFETIME_LUT_ROWS: u32 = 2;
+
+/// `sample_lifetime_lut` and the LUT constants, for render shaders that bind
+/// `ParticleGpuSim::lifetime_lut_texture`.
+pub const LIFETIME_LUT_WGSL: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/shaders/lifetime_lut.wgsl"
+));
+
+pub trait CurveValue: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl CurveValue for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl CurveValue for [f32; 3] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        [0, 1, 2].map(|i| self[i].lerp(other[i], t))
+    }
+}
+
+/// Piecewise-linear keys over normalized age, held constant before the first and after the
+/// last key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve<T> {
+    keys: [(f32, T); MAX_CURVE_KEYS],
+    len: usize,
+}
+
+pub type ScalarCurve = Curve<f32>;
+pub type ColorGradient = Curve<[f32; 3]>;
+
+impl<T: CurveValue> Curve<T> {
+    pub fn constant(value: T) -> Self {
+        Self {
+            keys: [(0.0, value); MAX_CURVE_KEYS],
+            len: 1,
+        }
+    }
+
+    pub fn linear(start: T, end: T) -> Self {
+        Self::from_first_and_rest((0.0, start), &[(1.0, end)])
+    }
+
+    /// Keys are `(normalized_age, value)`, clamped to `[0, 1]` and sorted; at most
+    /// `MAX_CURVE_KEYS` are kept. `None` when `keys` is empty.
+    pub fn from_keys(keys: &[(f32, T)]) -> Option<Self> {
+        let (first, rest) = keys.split_first()?;
+        Some(Self::from_first_and_rest(*first, rest))
+    }
+
+    fn from_first_and_rest(first: (f32, T), rest: &[(f32, T)]) -> Self {
+        let mut curve = Self {
+            keys: [first; MAX_CURVE_KEYS],
+            len: (rest.len() + 1).min(MAX_CURVE_KEYS),
+        };
+        for (dst, &(t, value)) in curve
+            .keys
+            .iter_mut()
+            .zip(std::iter::once(&first).chain(rest))
+        {
+            *dst = (t.clamp(0.0, 1.0), value);
+        }
+        curve.keys[..curve.len].sort_by(|a, b| a.0.total_cmp(&b.0));
+        curve
+    }
+
+    pub fn keys(&self) -> &[(f32, T)] {
+        &self.keys[..self.len]
+    }
+
+    pub fn evaluate(&self, normalized_age: f32) -> T {
+        let keys = self.keys();
+        let t = normalized_age.clamp(0.0, 1.0);
+        let Some(next) = keys.iter().position(|&(key_t, _)| key_t > t) else {
+            return keys[keys.len() - 1].1;
+        };
+        if next == 0 {
+            return keys[0].1;
+        }
+        let (t0, v0) = keys[next - 1];
+        let (t1, v1) = keys[next];
+        v0.lerp(v1, (t - t0) / (t1 - t0))
+    }
+}
+
+impl Curve<f32> {
+    /// Shorthand for the common fade: full value until `start`, then linear to zero.
+    pub fn fade_out(start: f32) -> Self {
+        Self::from_first_and_rest((0.0, 1.0), &[(start, 1.0), (1.0, 0.0)])
+    }
+}
+
+/// Multipliers keyed on `age_seconds / lifetime_seconds`. Speed scales the distance travelled
+/// each step in the update kernel; color, opacity and size are applied where particles are
+/// drawn, via `LifetimeLut::apply` or `sample_lifetime_lut`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LifetimeCurves {
+    pub color: ColorGradient,
+    pub opacity: ScalarCurve,
+    pub size: ScalarCurve,
+    pub speed: ScalarCurve,
+}
+
+impl Default for LifetimeCurves {
+    /// Every multiplier is one, which leaves particles unchanged.
+    fn default() -> Self {
+        Self {
+            color: Curve::constant([1.0; 3]),
+            opacity: Curve::constant(1.0),
+            size: Curve::constant(1.0),
+            speed: Curve::constant(1.0),
+        }
+    }
+}
+
+impl LifetimeCurves {
+    pub fn bake(&self) -> LifetimeLut {
+        LifetimeLut::bake(self)
+    }
+}
+
+/// Multipliers at one normalized age.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LifetimeSample {
+    /// RGB multiplier plus opacity in the last channel.
+    pub color: [f32; 4],
+    pub size: f32,
+    pub speed: f32,
+}
+
+/// `LifetimeCurves` baked to `LIFETIME_LUT_ROWS` rows of `LIFETIME_LUT_WIDTH` RGBA texels,
+/// row-major; uploaded as an `Rgba32Float` texture and sampled with manual linear filtering.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifetimeLut {
+    texels: Vec<[f32; 4]>,
+}
+
+impl LifetimeLut {
+    pub fn bake(curves: &LifetimeCurves) -> Self {
+        let width = LIFETIME_LUT_WIDTH as usize;
+        let mut texels = vec![[0.0; 4]; width * LIFETIME_LUT_ROWS as usize];
+        for x in 0..width {
+            let t = x as f32 / (width - 1) as f32;
+            let [r, g, b] = curves.color.evaluate(t);
+            texels[x] = [r, g, b, curves.opacity.evaluate(t)];
+            texels[width + x] = [curves.size.evaluate(t), curves.speed.evaluate(t), 0.0, 0.0];
+        }
+        Self { texels }
+    }
+
+    pub fn texels(&self) -> &[[f32; 4]] {
+        &self.texels
+    }
+
+    /// Mirrors `sample_lifetime_lut` in lifetime_lut.wgsl.
+    fn sample_row(&self, row: u32, normalized_age: f32) -> [f32; 4] {
+        let x = normalized_age.clamp(0.0, 1.0) * (LIFETIME_LUT_WIDTH - 1) as f32;
+        let i0 = (x as u32).min(LIFETIME_LUT_WIDTH - 2);
+        let f = x - i0 as f32;
+        let base = (row * LIFETIME_LUT_WIDTH + i0) as usize;
+        let (a, b) = (self.texels[base], self.texels[base + 1]);
+        [0, 1, 2, 3].map(|i| a[i] * (1.0 - f) + b[i] * f)
+    }
+
+    pub fn sample(&self, normalized_age: f32) -> LifetimeSample {
+        let motion = self.sample_row(1, normalized_age);
+        LifetimeSample {
+            color: self.sample_row(0, normalized_age),
+            size: motion[0],
+            speed: motion[1],
+        }
+    }
+
+    /// Render-time values of a particle: spawn attributes scaled by the curves at its age.
+    pub fn apply(
+        &self,
+        attributes: &ParticleAttributes,
+        normalized_age: f32,
+    ) -> ParticleAttributes {
+        let sample = self.sample(normalized_age);
+        ParticleAttributes {
+            color: [0, 1, 2, 3].map(|i| attributes.color[i] * sample.color[i]),
+            size: attributes.size * sample.size,
+            ..*attributes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Curve, LifetimeCurves, ScalarCurve, LIFETIME_LUT_WIDTH};
+
+    #[test]
+    fn curve_interpolates_between_sorted_keys_and_clamps() {
+        let curve = ScalarCurve::from_keys(&[(1.0, 0.0), (0.0, 2.0), (0.5, 1.0)]).unwrap();
+        assert_eq!(curve.evaluate(-1.0), 2.0);
+        assert_eq!(curve.evaluate(0.25), 1.5);
+        assert_eq!(curve.evaluate(0.75), 0.5);
+        assert_eq!(curve.evaluate(3.0), 0.0);
+        assert!(ScalarCurve::from_keys(&[]).is_none());
+    }
+
+    #[test]
+    fn baked_lut_tracks_the_curves() {
+        let curves = LifetimeCurves {
+            color: Curve::linear([1.0, 0.5, 0.0], [0.0, 0.5, 1.0]),
+            opacity: ScalarCurve::fade_out(0.7),
+            speed: ScalarCurve::linear(2.0, 0.0),
+            ..LifetimeCurves::default()
+        };
+        let lut = curves.bake();
+        assert_eq!(lut.texels().len(), 2 * LIFETIME_LUT_WIDTH as usize);
+        for i in 0..=20 {
+            let t = i as f32 / 20.0;
+            let sample = lut.sample(t);
+            let [r, g, b] = curves.color.evaluate(t);
+            let expected = [r, g, b, curves.opacity.evaluate(t)];
+            for (got, want) in sample.color.iter().zip(expected) {
+                assert!((got - want).abs() < 0.02, "t={t}: {got} vs {want}");
+            }
+            assert!((sample.speed - curves.speed.evaluate(t)).abs() < 1e-4);
+            assert_eq!(sample.size, 1.0);
+        }
+        assert_eq!(lut.sample(1.0).color[3], 0.0);
+    }
+}
@@ -0,0 +1,273 @@
+use std::f32::consts::TAU;
+
+use super::config::{EmissionMode, EmitterConfig, EmitterDirection, EmitterShape};
+use super::math::{add, hash01, mul_scalar, normalize_or_zero};
+use super::spawn_image::SpawnImage;
+
+/// Uniform randoms consumed per spawned particle; see `spawn_randoms`.
+pub(crate) const SPAWN_RANDOM_COUNT: usize = 6;
+const SPAWN_HASH_STRIDES: [u32; SPAWN_RANDOM_COUNT] = [17, 73, 193, 311, 467, 619];
+/// Golden-ratio offset between the hash inputs of successive `ParticleSimConfig::seed`s.
+const SEED_STRIDE: u32 = 0x9e37_79b9;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct EmitterSample {
+    pub position: [f32; 3],
+    /// Unit length, or zero when the shape has no defined direction at the sample.
+    pub direction: [f32; 3],
+    /// Multiplies the emitter color: the sampled pixel for `EmitterShape::Image` with
+    /// `pixel_color`, white otherwise.
+    pub tint: [f32; 4],
+}
+
+/// Hash input for the random stream `stride` of the spawn filling `slot` with `remaining` spawns
+/// still owed; mirrors `spawn_hash_key` in particles_update.wgsl.
+pub(crate) fn spawn_hash_key(seed: u32, slot: u32, remaining: u32, stride: u32) -> u32 {
+    slot.wrapping_add(remaining.wrapping_mul(stride))
+        .wrapping_add(seed.wrapping_mul(SEED_STRIDE))
+}
+
+/// Deterministic per-spawn randoms; mirrors `spawn_randoms` in particles_update.wgsl.
+pub(crate) fn spawn_randoms(seed: u32, slot: u32, remaining: u32) -> [f32; SPAWN_RANDOM_COUNT] {
+    SPAWN_HASH_STRIDES.map(|stride| hash01(spawn_hash_key(seed, slot, remaining, stride)))
+}
+
+/// Maps randoms in [0, 1) to a spawn position and direction; mirrors `sample_emitter` in
+/// particles_update.wgsl. `r[0..3]` place the point, `r[3]` picks a box face or jitters within
+/// an image pixel and `r[4..6]` drive `EmitterDirection::Random`. `image` is only read by
+/// `EmitterShape::Image`.
+pub(crate) fn sample_emitter(
+    emitter: &EmitterConfig,
+    image: &SpawnImage,
+    r: [f32; SPAWN_RANDOM_COUNT],
+) -> EmitterSample {
+    let surface = emitter.mode == EmissionMode::Surface;
+    let mut tint = [1.0; 4];
+    let (offset, normal) = match emitter.shape {
+        EmitterShape::Sphere { radius } => {
+            let dir = unit_sphere(r[0], r[1]);
+            let dist = if surface {
+                radius
+            } else {
+                radius * r[2].cbrt()
+            };
+            (mul_scalar(dir, dist), dir)
+        }
+        EmitterShape::Box { half_extents } => {
+            if surface {
+                box_surface(half_extents, r)
+            } else {
+                let offset = [
+                    (2.0 * r[0] - 1.0) * half_extents[0],
+                    (2.0 * r[1] - 1.0) * half_extents[1],
+                    (2.0 * r[2] - 1.0) * half_extents[2],
+                ];
+                (offset, normalize_or_zero(offset))
+            }
+        }
+        EmitterShape::Disc { radius, normal } => {
+            let radial = circle_direction(axis_or_z(normal), r[0]);
+            let dist = if surface {
+                radius
+            } else {
+                radius * r[1].sqrt()
+            };
+            (mul_scalar(radial, dist), radial)
+        }
+        EmitterShape::Cone {
+            radius,
+            angle_radians,
+            axis,
+        } => {
+            let axis = axis_or_z(axis);
+            let radial = circle_direction(axis, r[0]);
+            let rim_fraction = if surface { 1.0 } else { r[1].sqrt() };
+            let tilt = angle_radians * rim_fraction;
+            let direction = add(mul_scalar(axis, tilt.cos()), mul_scalar(radial, tilt.sin()));
+            (mul_scalar(radial, radius * rim_fraction), direction)
+        }
+        EmitterShape::Line { direction, length } => {
+            let axis = axis_or_z(direction);
+            (mul_scalar(axis, length * (r[0] - 0.5)), axis)
+        }
+        EmitterShape::Ring {
+            radius,
+            thickness,
+            normal,
+        } => {
+            let axis = axis_or_z(normal);
+            let radial = circle_direction(axis, r[0]);
+            let tube = if surface {
+                thickness
+            } else {
+                thickness * r[1].sqrt()
+            };
+            let tube_angle = TAU * r[2];
+            let offset = add(
+                mul_scalar(radial, radius + tube * tube_angle.cos()),
+                mul_scalar(axis, tube * tube_angle.sin()),
+            );
+            (offset, radial)
+        }
+        EmitterShape::Image {
+            half_extents,
+            pixel_color,
+        } => {
+            let ([u, v], color) = image.sample([r[0], r[1], r[2], r[3]]);
+            if pixel_color {
+                tint = color;
+            }
+            let offset = [
+                (2.0 * u - 1.0) * half_extents[0],
+                (1.0 - 2.0 * v) * half_extents[1],
+                0.0,
+            ];
+            (offset, [0.0, 0.0, 1.0])
+        }
+    };
+
+    let direction = match emitter.direction {
+        EmitterDirection::Shape => normal,
+        EmitterDirection::Along(direction) => normalize_or_zero(direction),
+        EmitterDirection::Random => unit_sphere(r[4], r[5]),
+    };
+
+    EmitterSample {
+        position: add(emitter.center, offset),
+        direction,
+        tint,
+    }
+}
+
+fn unit_sphere(a: f32, b: f32) -> [f32; 3] {
+    let z = 1.0 - 2.0 * a;
+    let xy = (1.0 - z * z).max(0.0).sqrt();
+    let phi = TAU * b;
+    [xy * phi.cos(), xy * phi.sin(), z]
+}
+
+fn axis_or_z(v: [f32; 3]) -> [f32; 3] {
+    let axis = normalize_or_zero(v);
+    if axis == [0.0, 0.0, 0.0] {
+        [0.0, 0.0, 1.0]
+    } else {
+        axis
+    }
+}
+
+/// Unit vector in the plane orthogonal to `axis`, at angle `TAU * t`.
+fn circle_direction(axis: [f32; 3], t: f32) -> [f32; 3] {
+    let (tangent, bitangent) = orthonormal_basis(axis);
+    let angle = TAU * t;
+    add(
+        mul_scalar(tangent, angle.cos()),
+        mul_scalar(bitangent, angle.sin()),
+    )
+}
+
+/// Basis from Duff et al., "Building an Orthonormal Basis, Revisited".
+fn orthonormal_basis(n: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let sign = if n[2] >= 0.0 { 1.0 } else { -1.0 };
+    let a = -1.0 / (sign + n[2]);
+    let b = n[0] * n[1] * a;
+    (
+        [1.0 + sign * n[0] * n[0] * a, sign * b, -sign * n[0]],
+        [b, sign + n[1] * n[1] * a, -n[1]],
+    )
+}
+
+/// Area-weighted face pick: `r[3]` selects the face pair, its lower/upper half the side.
+fn box_surface(h: [f32; 3], r: [f32; SPAWN_RANDOM_COUNT]) -> ([f32; 3], [f32; 3]) {
+    let areas = [h[1] * h[2], h[0] * h[2], h[0] * h[1]];
+    let total = areas[0] + areas[1] + areas[2];
+    let mut pick = r[3] * total;
+    let mut axis = 2;
+    for (i, area) in areas.iter().enumerate() {
+        if pick < *area {
+            axis = i;
+            break;
+        }
+        pick -= area;
+    }
+    let side = if areas[axis] > 0.0 {
+        pick / areas[axis]
+    } else {
+        0.0
+    };
+    let sign = if side < 0.5 { -1.0 } else { 1.0 };
+
+    let u_axis = (axis + 1) % 3;
+    let v_axis = (axis + 2) % 3;
+    let mut offset = [0.0; 3];
+    offset[axis] = sign * h[axis];
+    offset[u_axis] = (2.0 * r[0] - 1.0) * h[u_axis];
+    offset[v_axis] = (2.0 * r[1] - 1.0) * h[v_axis];
+    let mut normal = [0.0; 3];
+    normal[axis] = sign;
+    (offset, normal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sample_emitter, spawn_randoms};
+    use crate::particles::config::{EmissionMode, EmitterConfig, EmitterShape};
+    use crate::particles::math::{dot, sub};
+    use crate::particles::spawn_image::SpawnImage;
+
+    fn samples(emitter: EmitterConfig) -> impl Iterator<Item = super::EmitterSample> {
+        let image = SpawnImage::uniform();
+        (0..256).map(move |i| sample_emitter(&emitter, &image, spawn_randoms(0, i, 7)))
+    }
+
+    #[test]
+    fn sphere_surface_stays_on_shell() {
+        let emitter = EmitterConfig {
+            center: [1.0, 2.0, 3.0],
+            shape: EmitterShape::Sphere { radius: 0.5 },
+            mode: EmissionMode::Surface,
+            ..EmitterConfig::default()
+        };
+        for sample in samples(emitter) {
+            let offset = sub(sample.position, emitter.center);
+            assert!((dot(offset, offset).sqrt() - 0.5).abs() < 1e-4);
+            assert!(dot(offset, sample.direction) > 0.0);
+        }
+    }
+
+    #[test]
+    fn box_volume_and_surface_stay_within_extents() {
+        let half_extents = [0.5, 0.25, 1.0];
+        for mode in [EmissionMode::Volume, EmissionMode::Surface] {
+            let emitter = EmitterConfig {
+                shape: EmitterShape::Box { half_extents },
+                mode,
+                ..EmitterConfig::default()
+            };
+            for sample in samples(emitter) {
+                let mut on_face = false;
+                for (coord, half) in sample.position.iter().zip(half_extents) {
+                    assert!(coord.abs() <= half + 1e-5);
+                    on_face |= (coord.abs() - half).abs() < 1e-5;
+                }
+                assert!(mode == EmissionMode::Volume || on_face);
+            }
+        }
+    }
+
+    #[test]
+    fn cone_directions_stay_within_angle() {
+        let angle = 0.4f32;
+        let emitter = EmitterConfig {
+            shape: EmitterShape::Cone {
+                radius: 0.2,
+                angle_radians: angle,
+                axis: [0.0, 2.0, 0.0],
+            },
+            ..EmitterConfig::default()
+        };
+        for sample in samples(emitter) {
+            assert!(dot(sample.direction, [0.0, 1.0, 0.0]) >= angle.cos() - 1e-5);
+            assert!(sample.position[1].abs() < 1e-6);
+        }
+    }
+}
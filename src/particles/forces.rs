@@ -0,0 +1,448 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::math::{
+    add, cross, dot, mul_scalar, normalize_or_zero, quaternion_or_identity, rotate, sub,
+};
+use super::noise::curl_noise_field;
+use super::vector_field::VectorFieldVolume;
+
+/// Capacity of `ForceFieldList`, and of the GPU force-field storage buffer.
+pub const MAX_FORCE_FIELDS: usize = 16;
+
+/// Time scale of the wind turbulence drift, in noise units per second.
+const WIND_GUST_TIME_SCALE: f32 = 1.0;
+
+/// A typed acceleration source. Fields with a `radius` fade as `(1 - d / radius)^falloff`
+/// and have no effect beyond it; a `radius` of zero or less means unbounded with full weight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForceField {
+    /// Pulls towards `position` with positive `strength`, pushes away with negative.
+    Point {
+        position: [f32; 3],
+        strength: f32,
+        radius: f32,
+        falloff: f32,
+    },
+    /// Swirls around the line through `position` along `axis`, counter-clockwise when viewed
+    /// from the tip of `axis`; `inward_strength` pulls towards that line.
+    Vortex {
+        position: [f32; 3],
+        axis: [f32; 3],
+        strength: f32,
+        inward_strength: f32,
+        radius: f32,
+        falloff: f32,
+    },
+    /// Constant push along `direction` plus curl-noise gusts scaled by `turbulence`.
+    Wind {
+        direction: [f32; 3],
+        strength: f32,
+        turbulence: f32,
+        turbulence_frequency: f32,
+    },
+    /// Linear drag `-velocity * drag` (per second) inside a sphere around `position`.
+    DragZone {
+        position: [f32; 3],
+        radius: f32,
+        drag: f32,
+        falloff: f32,
+    },
+    /// The sim's `VectorFieldVolume`, placed in the world by `scale`, then `rotation` (a unit
+    /// quaternion `[x, y, z, w]`), then `position`. Sampled vectors turn with the volume and are
+    /// multiplied by `strength`; no effect outside the volume or while no volume is set.
+    VectorVolume {
+        position: [f32; 3],
+        rotation: [f32; 4],
+        scale: f32,
+        strength: f32,
+        mode: VectorFieldMode,
+    },
+}
+
+/// How a `ForceField::VectorVolume` acts on the particles inside it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VectorFieldMode {
+    /// The vectors are accelerations.
+    Force,
+    /// The vectors are target velocities, approached at `response` per second; a response of
+    /// `1 / dt` or more overrides the velocity outright.
+    Velocity { response: f32 },
+}
+
+impl ForceField {
+    /// Acceleration this field applies to a particle, with no vector field volume set.
+    pub fn acceleration(&self, position: [f32; 3], velocity: [f32; 3], time: f32) -> [f32; 3] {
+        self.acceleration_in(position, velocity, time, None)
+    }
+
+    /// Acceleration this field applies to a particle, sampling `volume` for
+    /// `ForceField::VectorVolume`; mirrors `force_field_accel` in particles_update.wgsl.
+    pub fn acceleration_in(
+        &self,
+        position: [f32; 3],
+        velocity: [f32; 3],
+        time: f32,
+        volume: Option<&VectorFieldVolume>,
+    ) -> [f32; 3] {
+        match *self {
+            ForceField::Point {
+                position: center,
+                strength,
+                radius,
+                falloff,
+            } => {
+                let to_center = sub(center, position);
+                let weight = radial_weight(dot(to_center, to_center).sqrt(), radius, falloff);
+                mul_scalar(normalize_or_zero(to_center), strength * weight)
+            }
+            ForceField::Vortex {
+                position: center,
+                axis,
+                strength,
+                inward_strength,
+                radius,
+                falloff,
+            } => {
+                let axis = normalize_or_zero(axis);
+                let offset = sub(position, center);
+                let radial = sub(offset, mul_scalar(axis, dot(offset, axis)));
+                let weight = radial_weight(dot(radial, radial).sqrt(), radius, falloff);
+                let outward = normalize_or_zero(radial);
+                let tangent = cross(axis, outward);
+                add(
+                    mul_scalar(tangent, strength * weight),
+                    mul_scalar(outward, -inward_strength * weight),
+                )
+            }
+            ForceField::Wind {
+                direction,
+                strength,
+                turbulence,
+                turbulence_frequency,
+            } => {
+                let gust = curl_noise_field(
+                    position,
+                    time * WIND_GUST_TIME_SCALE,
+                    turbulence_frequency,
+                    1,
+                );
+                add(
+                    mul_scalar(normalize_or_zero(direction), strength),
+                    mul_scalar(gust, turbulence),
+                )
+            }
+            ForceField::DragZone {
+                position: center,
+                radius,
+                drag,
+                falloff,
+            } => {
+                let offset = sub(position, center);
+                let weight = radial_weight(dot(offset, offset).sqrt(), radius, falloff);
+                mul_scalar(velocity, -drag * weight)
+            }
+            ForceField::VectorVolume {
+                position: origin,
+                rotation,
+                scale,
+                strength,
+                mode,
+            } => {
+                let rotation = quaternion_or_identity(rotation);
+                let inverse = [-rotation[0], -rotation[1], -rotation[2], rotation[3]];
+                let local = mul_scalar(rotate(inverse, sub(position, origin)), scale.recip());
+                let Some(vector) = volume.and_then(|volume| volume.sample(local)) else {
+                    return [0.0; 3];
+                };
+                let vector = mul_scalar(rotate(rotation, vector), strength);
+                match mode {
+                    VectorFieldMode::Force => vector,
+                    VectorFieldMode::Velocity { response } => {
+                        mul_scalar(sub(vector, velocity), response)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn radial_weight(distance: f32, radius: f32, falloff: f32) -> f32 {
+    if radius <= 0.0 {
+        return 1.0;
+    }
+    let t = 1.0 - distance / radius;
+    if t <= 0.0 {
+        0.0
+    } else {
+        t.powf(falloff)
+    }
+}
+
+/// Fixed-capacity list of force fields, so `ForceConfig` stays `Copy`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForceFieldList {
+    fields: [ForceField; MAX_FORCE_FIELDS],
+    len: usize,
+}
+
+impl ForceFieldList {
+    pub const fn new() -> Self {
+        Self {
+            fields: [ForceField::Point {
+                position: [0.0; 3],
+                strength: 0.0,
+                radius: 0.0,
+                falloff: 1.0,
+            }; MAX_FORCE_FIELDS],
+            len: 0,
+        }
+    }
+
+    /// Takes at most `MAX_FORCE_FIELDS` fields; the rest are dropped.
+    pub fn from_slice(fields: &[ForceField]) -> Self {
+        let mut list = Self::new();
+        for field in fields.iter().take(MAX_FORCE_FIELDS) {
+            list.push(*field);
+        }
+        list
+    }
+
+    /// Returns `false` and leaves the list unchanged when it is full.
+    pub fn push(&mut self, field: ForceField) -> bool {
+        if self.len == MAX_FORCE_FIELDS {
+            return false;
+        }
+        self.fields[self.len] = field;
+        self.len += 1;
+        true
+    }
+
+    pub fn as_slice(&self) -> &[ForceField] {
+        &self.fields[..self.len]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Sum of every field's acceleration at one particle, with no vector field volume set.
+    pub fn acceleration(&self, position: [f32; 3], velocity: [f32; 3], time: f32) -> [f32; 3] {
+        self.acceleration_in(position, velocity, time, None)
+    }
+
+    /// Sum of every field's `ForceField::acceleration_in` at one particle.
+    pub fn acceleration_in(
+        &self,
+        position: [f32; 3],
+        velocity: [f32; 3],
+        time: f32,
+        volume: Option<&VectorFieldVolume>,
+    ) -> [f32; 3] {
+        self.as_slice().iter().fold([0.0; 3], |acc, field| {
+            add(acc, field.acceleration_in(position, velocity, time, volume))
+        })
+    }
+}
+
+impl Default for ForceFieldList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Mirrors `ForceField` in particles_update.wgsl; `kind` uses the `FIELD_*` constants there.
+/// Vortex keeps `inward_strength` in `param0`, wind keeps its direction in `axis` and
+/// turbulence/frequency in `param0`/`param1`, and a drag zone keeps `drag` in `strength`. A
+/// vector volume keeps its rotation in `axis` and `falloff`, `scale` in `radius` and the
+/// velocity `response` in `param0`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub(crate) struct GpuForceField {
+    position: [f32; 3],
+    kind: u32,
+    axis: [f32; 3],
+    strength: f32,
+    radius: f32,
+    falloff: f32,
+    param0: f32,
+    param1: f32,
+}
+
+impl GpuForceField {
+    pub(crate) fn new(field: &ForceField) -> Self {
+        let zeroed = Self::zeroed();
+        match *field {
+            ForceField::Point {
+                position,
+                strength,
+                radius,
+                falloff,
+            } => Self {
+                position,
+                kind: 0,
+                strength,
+                radius,
+                falloff,
+                ..zeroed
+            },
+            ForceField::Vortex {
+                position,
+                axis,
+                strength,
+                inward_strength,
+                radius,
+                falloff,
+            } => Self {
+                position,
+                kind: 1,
+                axis,
+                strength,
+                radius,
+                falloff,
+                param0: inward_strength,
+                ..zeroed
+            },
+            ForceField::Wind {
+                direction,
+                strength,
+                turbulence,
+                turbulence_frequency,
+            } => Self {
+                kind: 2,
+                axis: direction,
+                strength,
+                param0: turbulence,
+                param1: turbulence_frequency,
+                ..zeroed
+            },
+            ForceField::DragZone {
+                position,
+                radius,
+                drag,
+                falloff,
+            } => Self {
+                position,
+                kind: 3,
+                strength: drag,
+                radius,
+                falloff,
+                ..zeroed
+            },
+            ForceField::VectorVolume {
+                position,
+                rotation,
+                scale,
+                strength,
+                mode,
+            } => {
+                let rotation = quaternion_or_identity(rotation);
+                let (kind, response) = match mode {
+                    VectorFieldMode::Force => (4, 0.0),
+                    VectorFieldMode::Velocity { response } => (5, response),
+                };
+                Self {
+                    position,
+                    kind,
+                    axis: [rotation[0], rotation[1], rotation[2]],
+                    strength,
+                    radius: scale,
+                    falloff: rotation[3],
+                    param0: response,
+                    ..zeroed
+                }
+            }
+        }
+    }
+
+    /// Always `MAX_FORCE_FIELDS` entries; the shader only reads the first `list.len()`.
+    pub(crate) fn pack(list: &ForceFieldList) -> [Self; MAX_FORCE_FIELDS] {
+        let mut packed = [Self::zeroed(); MAX_FORCE_FIELDS];
+        for (dst, field) in packed.iter_mut().zip(list.as_slice()) {
+            *dst = Self::new(field);
+        }
+        packed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ForceField, ForceFieldList, VectorFieldMode, MAX_FORCE_FIELDS};
+    use crate::particles::math::dot;
+    use crate::particles::vector_field::VectorFieldVolume;
+
+    #[test]
+    fn point_field_fades_to_zero_at_radius() {
+        let field = ForceField::Point {
+            position: [0.0; 3],
+            strength: -2.0,
+            radius: 1.0,
+            falloff: 2.0,
+        };
+        let near = field.acceleration([0.25, 0.0, 0.0], [0.0; 3], 0.0);
+        assert!(near[0] > 0.0, "negative strength should repel");
+        assert_eq!(field.acceleration([1.5, 0.0, 0.0], [0.0; 3], 0.0), [0.0; 3]);
+    }
+
+    #[test]
+    fn vortex_is_tangential_without_inward_pull() {
+        let field = ForceField::Vortex {
+            position: [0.0; 3],
+            axis: [0.0, 0.0, 1.0],
+            strength: 1.0,
+            inward_strength: 0.0,
+            radius: 0.0,
+            falloff: 1.0,
+        };
+        let p = [0.6, -0.3, 0.2];
+        let accel = field.acceleration(p, [0.0; 3], 0.0);
+        assert!(dot(accel, [p[0], p[1], 0.0]).abs() < 1e-6);
+        assert!(dot(accel, accel) > 0.5);
+    }
+
+    #[test]
+    fn list_rejects_fields_past_capacity() {
+        let wind = ForceField::Wind {
+            direction: [1.0, 0.0, 0.0],
+            strength: 0.1,
+            turbulence: 0.0,
+            turbulence_frequency: 1.0,
+        };
+        let list = ForceFieldList::from_slice(&[wind; MAX_FORCE_FIELDS + 3]);
+        assert_eq!(list.len(), MAX_FORCE_FIELDS);
+        let accel = list.acceleration([0.0; 3], [0.0; 3], 0.0);
+        assert!((accel[0] - 0.1 * MAX_FORCE_FIELDS as f32).abs() < 1e-5);
+    }
+
+    #[test]
+    fn vector_volume_follows_its_transform() {
+        let volume =
+            VectorFieldVolume::from_vectors([1; 3], [-1.0; 3], [1.0; 3], vec![[1.0, 0.0, 0.0]])
+                .unwrap();
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let field = |mode| ForceField::VectorVolume {
+            position: [5.0, 0.0, 0.0],
+            rotation: [0.0, 0.0, half, half],
+            scale: 2.0,
+            strength: 3.0,
+            mode,
+        };
+
+        // A quarter turn about z maps the volume's +x onto world +y, scaled out to 2 units.
+        let force = field(VectorFieldMode::Force);
+        let accel = force.acceleration_in([5.0, 1.5, 0.0], [0.0; 3], 0.0, Some(&volume));
+        assert!((accel[0]).abs() < 1e-5 && (accel[1] - 3.0).abs() < 1e-5);
+        assert_eq!(
+            force.acceleration_in([5.0, 2.5, 0.0], [0.0; 3], 0.0, Some(&volume)),
+            [0.0; 3]
+        );
+        assert_eq!(force.acceleration([5.0, 1.5, 0.0], [0.0; 3], 0.0), [0.0; 3]);
+
+        let steer = field(VectorFieldMode::Velocity { response: 2.0 });
+        let accel = steer.acceleration_in([5.0, -1.0, 0.5], [1.0, 3.0, 0.0], 0.0, Some(&volume));
+        assert!((accel[0] + 2.0).abs() < 1e-5 && accel[1].abs() < 1e-5);
+    }
+}
@@ -1,16 +1,44 @@
 use std::borrow::Cow;
 use std::mem::size_of;
-use std::sync::mpsc;
 
 use bytemuck::{bytes_of, cast_slice, Pod, Zeroable};
 
+use super::attributes::{ParticleAttributeLayout, ParticleAttributes};
+use super::boids::BoidsPasses;
+use super::collision::{ColliderList, GpuCollider, SdfVolume};
 use super::compute::{ParticleBufferLayout, ParticleComputePlan, ParticleWorkgroup};
-use super::config::{ForceConfig, ParticleSimConfig};
+use super::config::{
+    EmissionMode, EmitterConfig, EmitterDirection, EmitterShape, ForceConfig, ParticleSimConfig,
+    ParticleSimMode,
+};
+use super::constraints::{ConstraintPasses, ConstraintSolver, ConstraintTopology};
+use super::curves::{
+    LifetimeCurves, LifetimeLut, LIFETIME_LUT_ROWS, LIFETIME_LUT_WGSL, LIFETIME_LUT_WIDTH,
+};
+use super::forces::GpuForceField;
+use super::morph::{MorphPasses, MorphPhase, MorphTargets};
+use super::noise::{MAX_NOISE_OCTAVES, RANDOM_WGSL};
+use super::readback::{read_buffer_blocking, StagingRing};
 use super::simulation::Particle;
+use super::snapshot::{ParticleSnapshot, SnapshotConfig, SnapshotError};
+use super::spawn_image::SpawnImage;
+use super::sph::SphPasses;
+use super::stats::{GpuSimStats, ParticleSimStats};
+use super::sub_emitter::GpuSubEmitter;
+use super::trails::TrailPasses;
+use super::vector_field::VectorFieldVolume;
+
+/// Stats copies that may be in flight at once before `request_stats` starts refusing.
+const STATS_READBACK_SLOTS: usize = 3;
+/// Particle copies that may be in flight at once before `request_particles` starts refusing.
+const PARTICLE_READBACK_SLOTS: usize = 3;
+/// Marks alive-list entries of sub-emitted particles; same as in particles_update.wgsl.
+const SUB_EMITTED_SLOT_BIT: u32 = 0x8000_0000;
 
 #[derive(Debug, Clone, Copy)]
 pub struct ParticleStepInput {
     pub dt_seconds: f32,
+    pub emitter: EmitterConfig,
     pub force: ForceConfig,
 }
 
@@ -18,16 +46,29 @@ impl Default for ParticleStepInput {
     fn default() -> Self {
         Self {
             dt_seconds: 1.0 / 120.0,
+            emitter: EmitterConfig::default(),
             force: ForceConfig::default(),
         }
     }
 }
 
+/// Leading pool slots copied back by `ParticleGpuSim::request_particles`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleReadback {
+    /// Step the copy was taken after, as counted by `ParticleGpuSim::step_index`.
+    pub step_index: u64,
+    pub particles: Vec<Particle>,
+}
+
 #[derive(Debug)]
 pub enum ParticleGpuError {
     InvalidWorkgroupSize { expected: u32, got: u32 },
     MapFailed,
     ChannelClosed,
+    TooManyConstrainedParticles { capacity: u32, got: usize },
+    SpawnImageTooLarge { extent: u32, limit: u32 },
+    VolumeTooLarge { resolution: [u32; 3], limit: u32 },
+    StorageBufferLimit { required: u32, available: u32 },
 }
 
 impl std::fmt::Display for ParticleGpuError {
@@ -40,6 +81,29 @@ impl std::fmt::Display for ParticleGpuError {
             ),
             Self::MapFailed => write!(f, "failed to map GPU staging buffer"),
             Self::ChannelClosed => write!(f, "staging-map channel closed before completion"),
+            Self::TooManyConstrainedParticles { capacity, got } => write!(
+                f,
+                "constraint topology has {} particles but the pool holds {}",
+                got, capacity
+            ),
+            Self::SpawnImageTooLarge { extent, limit } => write!(
+                f,
+                "spawn image needs {}-texel textures but the device allows {}",
+                extent, limit
+            ),
+            Self::VolumeTooLarge { resolution, limit } => write!(
+                f,
+                "volume of {:?} voxels exceeds the device's 3D texture limit of {}",
+                resolution, limit
+            ),
+            Self::StorageBufferLimit {
+                required,
+                available,
+            } => write!(
+                f,
+                "particle kernels need {} storage buffers per shader stage but the device allows {}",
+                required, available
+            ),
         }
     }
 }
@@ -50,9 +114,58 @@ pub struct ParticleGpuSim {
     config: ParticleSimConfig,
     compute_plan: ParticleComputePlan,
     particle_buffer: wgpu::Buffer,
+    attribute_layout: ParticleAttributeLayout,
+    attribute_buffer: wgpu::Buffer,
     sim_uniform_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    counter_buffer: wgpu::Buffer,
+    dispatch_args_buffer: wgpu::Buffer,
+    stats_buffer: wgpu::Buffer,
+    stats_readback: StagingRing,
+    // Ring behind `request_particles` and the slot count it copies; set by
+    // `set_particle_readback`.
+    particle_readback: Option<(StagingRing, u32)>,
+    force_field_buffer: wgpu::Buffer,
+    collider_buffer: wgpu::Buffer,
+    collider_count: u32,
+    event_buffer: wgpu::Buffer,
+    sdf_texture: wgpu::Texture,
+    vector_field_texture: wgpu::Texture,
+    volume_bounds: VolumeBounds,
+    // Colors and CDFs of the `SpawnImage` sampled by `EmitterShape::Image`.
+    spawn_image_texture: wgpu::Texture,
+    spawn_cdf_texture: wgpu::Texture,
+    lifetime_lut_texture: wgpu::Texture,
+    bind_group_layout: wgpu::BindGroupLayout,
     bind_group: wgpu::BindGroup,
-    pipeline: wgpu::ComputePipeline,
+    // Group 0 of the `begin_*` kernels; its buffers are never replaced.
+    begin_bind_group: wgpu::BindGroup,
+    dispatch_args_bind_group: wgpu::BindGroup,
+    pipelines: ParticlePipelines,
+    // Neighbor grid and SPH kernels, encoded ahead of `update` in `ParticleSimMode::Sph`.
+    sph: Option<SphPasses>,
+    // Neighbor grid and steering kernels, encoded ahead of `update` in `ParticleSimMode::Boids`.
+    boids: Option<BoidsPasses>,
+    // Constraint solve around `update`; set by `set_constraints`, run in `ParticleSimMode::Pbd`.
+    constraints: Option<ConstraintPasses>,
+    // Target pull encoded ahead of `update`; set by `set_morph_targets`, run in
+    // `ParticleSimMode::Morph`.
+    morph: Option<MorphPasses>,
+    morph_phase: MorphPhase,
+    // History ring recorded after every other pass when `config.trails` is set.
+    trails: Option<TrailPasses>,
+    spawn_accumulator: f32,
+    step_index: u64,
+    elapsed_seconds: f32,
+}
+
+struct ParticlePipelines {
+    begin_update: wgpu::ComputePipeline,
+    update: wgpu::ComputePipeline,
+    begin_emit: wgpu::ComputePipeline,
+    emit: wgpu::ComputePipeline,
+    begin_sub_emit: wgpu::ComputePipeline,
+    sub_emit: wgpu::ComputePipeline,
 }
 
 impl ParticleGpuSim {
@@ -68,9 +181,17 @@ impl ParticleGpuSim {
                 got: workgroup.x,
             });
         }
+        let required = storage_buffer_count(&compute_layout_entries());
+        let available = device.limits().max_storage_buffers_per_shader_stage;
+        if available < required {
+            return Err(ParticleGpuError::StorageBufferLimit {
+                required,
+                available,
+            });
+        }
 
         let compute_plan = ParticleComputePlan::new(config.max_particles, workgroup);
-        let layout = ParticleBufferLayout::default();
+        let layout = ParticleBufferLayout::with_attributes(config.attributes);
         let particles_size = layout.particle_stride_bytes * config.max_particles as u64;
         let initial_particles = vec![Particle::dead(); config.max_particles as usize];
 
@@ -84,7 +205,26 @@ impl ParticleGpuSim {
         });
         queue.write_buffer(&particle_buffer, 0, cast_slice(&initial_particles));
 
-        let initial_uniform = GpuSimUniform::new(ParticleStepInput::default(), config);
+        // Parallel to `particle_buffer`; kept non-empty because zero-sized bindings are invalid.
+        let attribute_layout = config.attributes.layout();
+        let attribute_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.attributes"),
+            size: (layout.attribute_stride_bytes * config.max_particles as u64).max(16),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let initial_uniform = GpuSimUniform::new(
+            ParticleStepInput::default(),
+            config,
+            0,
+            0.0,
+            0,
+            VolumeBounds::default(),
+            matches!(config.mode, ParticleSimMode::Boids(_)),
+        );
         let sim_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("particles.uniform"),
             size: layout.sim_uniform_bytes,
@@ -93,45 +233,171 @@ impl ParticleGpuSim {
         });
         queue.write_buffer(&sim_uniform_buffer, 0, bytes_of(&initial_uniform));
 
+        // Dead stack followed by the two ping-pong alive lists, each `max_particles` long.
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.index_lists"),
+            size: layout.index_list_bytes(config.max_particles),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(
+            &index_buffer,
+            0,
+            cast_slice(&initial_dead_list(config.max_particles)),
+        );
+
+        let counter_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.counters"),
+            size: layout.counter_bytes,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(
+            &counter_buffer,
+            0,
+            bytes_of(&GpuParticleCounters::new(config.max_particles)),
+        );
+
+        let dispatch_args_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.dispatch_args"),
+            size: layout.dispatch_args_bytes,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::INDIRECT
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(
+            &dispatch_args_buffer,
+            0,
+            bytes_of(&GpuDispatchArgs::zeroed()),
+        );
+
+        let stats_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.stats"),
+            size: layout.stats_bytes,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(&stats_buffer, 0, bytes_of(&GpuSimStats::reset()));
+
+        let force_field_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.force_fields"),
+            size: layout.force_fields_bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let collider_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.colliders"),
+            size: layout.colliders_bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Death and collision events appended by `update` and consumed by `sub_emit`.
+        let max_events = config.sub_emitter.map_or(0, |sub| sub.max_events_per_step);
+        let event_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.events"),
+            size: layout.event_queue_bytes(max_events),
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sdf_texture = create_sdf_texture(device, queue, None);
+        let vector_field_texture = create_vector_field_texture(device, queue, None);
+        let [spawn_image_texture, spawn_cdf_texture] =
+            create_spawn_image_textures(device, queue, &SpawnImage::uniform());
+        // Starts as the identity curves; see `set_lifetime_curves`.
+        let lifetime_lut_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("particles.lifetime_lut"),
+            size: LIFETIME_LUT_SIZE,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        write_lifetime_lut(
+            queue,
+            &lifetime_lut_texture,
+            &LifetimeCurves::default().bake(),
+        );
+        let stats_readback = StagingRing::new(
+            device,
+            "particles.stats.staging",
+            layout.stats_bytes,
+            STATS_READBACK_SLOTS,
+        );
+
         let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("particles.compute.bgl"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: false },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::COMPUTE,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
+            entries: &compute_layout_entries(),
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("particles.compute.bg"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: particle_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: sim_uniform_buffer.as_entire_binding(),
-                },
+        let bind_group = create_compute_bind_group(
+            device,
+            &bind_group_layout,
+            [
+                &particle_buffer,
+                &sim_uniform_buffer,
+                &index_buffer,
+                &counter_buffer,
+                &stats_buffer,
+                &force_field_buffer,
+                &collider_buffer,
+                &attribute_buffer,
+                &event_buffer,
+            ],
+            [
+                &sdf_texture,
+                &lifetime_lut_texture,
+                &spawn_image_texture,
+                &spawn_cdf_texture,
+                &vector_field_texture,
             ],
+        );
+
+        // The `begin_*` kernels only touch these, so with `dispatch_args` in group 1 they stay
+        // within the per-stage storage buffer limit the update/emit layout already fills.
+        let begin_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.begin.bgl"),
+            entries: &begin_layout_entries(),
+        });
+        let begin_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.begin.bg"),
+            layout: &begin_layout,
+            entries: &BEGIN_BUFFER_BINDINGS
+                .iter()
+                .zip([
+                    &sim_uniform_buffer,
+                    &counter_buffer,
+                    &stats_buffer,
+                    &event_buffer,
+                ])
+                .map(|(&binding, buffer)| wgpu::BindGroupEntry {
+                    binding,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+        let dispatch_args_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("particles.dispatch_args.bgl"),
+                entries: &[storage_entry(0, false)],
+            });
+        let dispatch_args_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.dispatch_args.bg"),
+            layout: &dispatch_args_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: dispatch_args_buffer.as_entire_binding(),
+            }],
         });
 
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -139,31 +405,117 @@ impl ParticleGpuSim {
             bind_group_layouts: &[&bind_group_layout],
             push_constant_ranges: &[],
         });
+        let begin_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("particles.begin.pl"),
+                bind_group_layouts: &[&begin_layout, &dispatch_args_layout],
+                push_constant_ranges: &[],
+            });
 
-        let shader_source = include_str!(concat!(
-            env!("CARGO_MANIFEST_DIR"),
-            "/shaders/particles_update.wgsl"
-        ));
+        let shader_source = attribute_layout.wgsl_source()
+            + LIFETIME_LUT_WGSL
+            + RANDOM_WGSL
+            + include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/shaders/particles_update.wgsl"
+            ));
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("particles.update.shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader_source)),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shader_source)),
         });
 
-        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("particles.update.pipeline"),
-            layout: Some(&pipeline_layout),
-            module: &shader,
-            entry_point: "main",
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
-        });
+        let create_pipeline = |label: &str, layout: &wgpu::PipelineLayout, entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                module: &shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            })
+        };
+        let pipelines = ParticlePipelines {
+            begin_update: create_pipeline(
+                "particles.begin_update.pipeline",
+                &begin_pipeline_layout,
+                "begin_update",
+            ),
+            update: create_pipeline("particles.update.pipeline", &pipeline_layout, "update"),
+            begin_emit: create_pipeline(
+                "particles.begin_emit.pipeline",
+                &begin_pipeline_layout,
+                "begin_emit",
+            ),
+            emit: create_pipeline("particles.emit.pipeline", &pipeline_layout, "emit"),
+            begin_sub_emit: create_pipeline(
+                "particles.begin_sub_emit.pipeline",
+                &begin_pipeline_layout,
+                "begin_sub_emit",
+            ),
+            sub_emit: create_pipeline("particles.sub_emit.pipeline", &pipeline_layout, "sub_emit"),
+        };
+        let sph = match config.mode {
+            ParticleSimMode::Sph(sph) => Some(SphPasses::new(
+                device,
+                queue,
+                &particle_buffer,
+                config.max_particles,
+                sph,
+            )),
+            _ => None,
+        };
+        let boids = match config.mode {
+            ParticleSimMode::Boids(boids) => Some(BoidsPasses::new(
+                device,
+                queue,
+                &particle_buffer,
+                &force_field_buffer,
+                config.max_particles,
+                boids,
+            )),
+            _ => None,
+        };
+
+        let trails = config
+            .trails
+            .map(|trails| TrailPasses::new(device, &particle_buffer, config.max_particles, trails));
 
         Ok(Self {
             config,
             compute_plan,
             particle_buffer,
+            attribute_layout,
+            attribute_buffer,
             sim_uniform_buffer,
+            index_buffer,
+            counter_buffer,
+            dispatch_args_buffer,
+            stats_buffer,
+            stats_readback,
+            particle_readback: None,
+            force_field_buffer,
+            collider_buffer,
+            collider_count: 0,
+            event_buffer,
+            sdf_texture,
+            vector_field_texture,
+            volume_bounds: VolumeBounds::default(),
+            spawn_image_texture,
+            spawn_cdf_texture,
+            lifetime_lut_texture,
+            bind_group_layout,
             bind_group,
-            pipeline,
+            begin_bind_group,
+            dispatch_args_bind_group,
+            pipelines,
+            sph,
+            boids,
+            constraints: None,
+            morph: None,
+            morph_phase: MorphPhase::Hold,
+            trails,
+            spawn_accumulator: 0.0,
+            step_index: 0,
+            elapsed_seconds: 0.0,
         })
     }
 
@@ -175,37 +527,364 @@ impl ParticleGpuSim {
         &self.particle_buffer
     }
 
+    /// Per-slot SPH density and pressure from the latest step as `vec4<f32>` (x and y), or
+    /// `None` outside `ParticleSimMode::Sph`.
+    pub fn sph_density_buffer(&self) -> Option<&wgpu::Buffer> {
+        self.sph.as_ref().map(SphPasses::density_buffer)
+    }
+
+    /// Trail history as `history_len` `vec4<f32>` positions per slot, in a ring shared by all
+    /// slots; `None` without `ParticleSimConfig::trails`. Drawn by `ParticleTrailRenderer`.
+    pub fn trail_history_buffer(&self) -> Option<&wgpu::Buffer> {
+        self.trails.as_ref().map(TrailPasses::history_buffer)
+    }
+
+    pub(crate) fn trails(&self) -> Option<&TrailPasses> {
+        self.trails.as_ref()
+    }
+
+    /// Per-particle attributes laid out by `attribute_layout()`, indexed by particle slot.
+    pub fn attribute_buffer(&self) -> &wgpu::Buffer {
+        &self.attribute_buffer
+    }
+
+    pub fn attribute_layout(&self) -> ParticleAttributeLayout {
+        self.attribute_layout
+    }
+
+    /// Dead stack plus both alive lists; see `ParticleBufferLayout::index_list_bytes`.
+    /// Alive-list entries of sub-emitted particles carry `SUB_EMITTED_SLOT_BIT`.
+    pub fn index_buffer(&self) -> &wgpu::Buffer {
+        &self.index_buffer
+    }
+
+    /// Dead/alive allocation counters.
+    pub fn counter_buffer(&self) -> &wgpu::Buffer {
+        &self.counter_buffer
+    }
+
+    /// Event count followed by the events raised in the latest step, in no particular order.
+    pub fn event_buffer(&self) -> &wgpu::Buffer {
+        &self.event_buffer
+    }
+
+    /// Raw `Stats` block rewritten by every step; prefer `request_stats`/`poll_stats`.
+    pub fn stats_buffer(&self) -> &wgpu::Buffer {
+        &self.stats_buffer
+    }
+
+    /// Number of steps encoded so far.
+    pub fn step_index(&self) -> u64 {
+        self.step_index
+    }
+
+    /// Simulated time so far, accumulated like `ParticleState::elapsed_seconds`.
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed_seconds
+    }
+
+    /// Uploads the colliders resolved after every integration step.
+    pub fn set_colliders(&mut self, queue: &wgpu::Queue, colliders: &ColliderList) {
+        queue.write_buffer(
+            &self.collider_buffer,
+            0,
+            cast_slice(&GpuCollider::pack(colliders)),
+        );
+        self.collider_count = colliders.len() as u32;
+    }
+
+    /// Re-bakes the over-lifetime LUT; takes effect from the next step.
+    pub fn set_lifetime_curves(&self, queue: &wgpu::Queue, curves: &LifetimeCurves) {
+        write_lifetime_lut(queue, &self.lifetime_lut_texture, &curves.bake());
+    }
+
+    /// `Rgba32Float` LUT laid out as described by `LifetimeLut`, for render shaders using
+    /// `LIFETIME_LUT_WGSL`.
+    pub fn lifetime_lut_texture(&self) -> &wgpu::Texture {
+        &self.lifetime_lut_texture
+    }
+
+    /// Empties the pool and places `topology`'s particles in slots `0..topology.len()`, like
+    /// `ParticleState::set_constraints`. Its constraints are solved in `ParticleSimMode::Pbd`.
+    pub fn set_constraints(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        topology: &ConstraintTopology,
+    ) -> Result<(), ParticleGpuError> {
+        let capacity = self.config.max_particles;
+        if topology.len() > capacity as usize {
+            return Err(ParticleGpuError::TooManyConstrainedParticles {
+                capacity,
+                got: topology.len(),
+            });
+        }
+        let body_count = topology.len() as u32;
+
+        let mut particles = vec![Particle::dead(); capacity as usize];
+        for (slot, particle) in topology.particles().enumerate() {
+            particles[slot] = particle;
+        }
+        queue.write_buffer(&self.particle_buffer, 0, cast_slice(&particles));
+        let stride = self.attribute_layout.stride_bytes as usize;
+        if stride > 0 && body_count > 0 {
+            let mut records = vec![0u8; stride * body_count as usize];
+            for record in records.chunks_exact_mut(stride) {
+                self.attribute_layout
+                    .encode(&ParticleAttributes::default(), record);
+            }
+            queue.write_buffer(&self.attribute_buffer, 0, &records);
+        }
+
+        // The dead stack keeps slots `body_count..capacity`, lowest on top, and the bodies go in
+        // the alive list `begin_update` reads next: parity 0 flips to the second list.
+        let mut indices = initial_dead_list(capacity);
+        indices.resize(3 * capacity as usize, 0);
+        for slot in 0..body_count {
+            indices[2 * capacity as usize + slot as usize] = slot;
+        }
+        queue.write_buffer(&self.index_buffer, 0, cast_slice(&indices));
+        let counters = GpuParticleCounters {
+            dead_count: capacity - body_count,
+            alive_next: body_count,
+            ..GpuParticleCounters::zeroed()
+        };
+        queue.write_buffer(&self.counter_buffer, 0, bytes_of(&counters));
+
+        if let Some(trails) = &self.trails {
+            trails.clear(queue);
+        }
+        self.constraints = Some(ConstraintPasses::new(
+            device,
+            queue,
+            &self.particle_buffer,
+            &ConstraintSolver::new(topology),
+        ));
+        Ok(())
+    }
+
+    /// Assigns every pool slot its `MorphTargets::target_for_slot`, like
+    /// `ParticleState::set_morph_targets`, and holds the morph again if it was released.
+    pub fn set_morph_targets(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        targets: &MorphTargets,
+    ) {
+        self.morph = Some(MorphPasses::new(
+            device,
+            queue,
+            &self.particle_buffer,
+            self.config.max_particles,
+            targets,
+        ));
+        self.morph_phase = MorphPhase::Hold;
+    }
+
+    /// Same as `ParticleState::set_morph_released`.
+    pub fn set_morph_released(&mut self, released: bool) {
+        self.morph_phase = self.morph_phase.with_released(released);
+    }
+
+    /// Replaces the volume sampled by `ColliderShape::SdfVolume` colliders; `None` unbinds it.
+    /// Fails, keeping the current volume, if an axis exceeds the device's
+    /// `max_texture_dimension_3d`.
+    pub fn set_sdf_volume(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        volume: Option<&SdfVolume>,
+    ) -> Result<(), ParticleGpuError> {
+        check_volume_fits(device, volume.map(SdfVolume::resolution))?;
+        self.sdf_texture = create_sdf_texture(device, queue, volume);
+        self.volume_bounds.sdf = volume.map(SdfVolume::bounds);
+        self.rebuild_bind_group(device);
+        Ok(())
+    }
+
+    /// Replaces the volume sampled by `ForceField::VectorVolume` fields; `None` unbinds it.
+    /// Fails, keeping the current volume, if an axis exceeds the device's
+    /// `max_texture_dimension_3d`.
+    pub fn set_vector_field(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        volume: Option<&VectorFieldVolume>,
+    ) -> Result<(), ParticleGpuError> {
+        check_volume_fits(device, volume.map(VectorFieldVolume::resolution))?;
+        self.vector_field_texture = create_vector_field_texture(device, queue, volume);
+        self.volume_bounds.vector_field = volume.map(VectorFieldVolume::bounds);
+        self.rebuild_bind_group(device);
+        Ok(())
+    }
+
+    /// Replaces the density and colors sampled by `EmitterShape::Image`, for the emitter and
+    /// sub-emitter; `None` spawns uniformly over the image rectangle. Fails, keeping the current
+    /// image, if its textures exceed the device's `max_texture_dimension_2d`.
+    pub fn set_spawn_image(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: Option<&SpawnImage>,
+    ) -> Result<(), ParticleGpuError> {
+        let uniform = SpawnImage::uniform();
+        let image = image.unwrap_or(&uniform);
+        let limit = device.limits().max_texture_dimension_2d;
+        if image.texture_extent() > limit {
+            return Err(ParticleGpuError::SpawnImageTooLarge {
+                extent: image.texture_extent(),
+                limit,
+            });
+        }
+        [self.spawn_image_texture, self.spawn_cdf_texture] =
+            create_spawn_image_textures(device, queue, image);
+        self.rebuild_bind_group(device);
+        Ok(())
+    }
+
+    fn rebuild_bind_group(&mut self, device: &wgpu::Device) {
+        self.bind_group = create_compute_bind_group(
+            device,
+            &self.bind_group_layout,
+            [
+                &self.particle_buffer,
+                &self.sim_uniform_buffer,
+                &self.index_buffer,
+                &self.counter_buffer,
+                &self.stats_buffer,
+                &self.force_field_buffer,
+                &self.collider_buffer,
+                &self.attribute_buffer,
+                &self.event_buffer,
+            ],
+            [
+                &self.sdf_texture,
+                &self.lifetime_lut_texture,
+                &self.spawn_image_texture,
+                &self.spawn_cdf_texture,
+                &self.vector_field_texture,
+            ],
+        );
+    }
+
     pub fn encode_step(
-        &self,
+        &mut self,
         queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
         input: ParticleStepInput,
     ) {
-        if self.compute_plan.dispatch_x == 0 {
+        if self.compute_plan.particle_count == 0 {
             return;
         }
 
         let clamped_dt = input.dt_seconds.clamp(0.0, 1.0 / 15.0);
+
+        // Same accumulator as `ParticleState::step_reference`, so fractional spawns carry over.
+        self.spawn_accumulator += self.config.spawn_rate_per_second * clamped_dt;
+        let spawn_count = self.spawn_accumulator.floor();
+        self.spawn_accumulator -= spawn_count;
+        let burst_count = input
+            .emitter
+            .bursts
+            .count_between(self.elapsed_seconds, self.elapsed_seconds + clamped_dt);
+
+        let morph = match (self.config.mode, &self.morph) {
+            (ParticleSimMode::Morph(morph), Some(passes)) => Some((morph, passes)),
+            _ => None,
+        };
+        let steered = match morph {
+            Some(_) => self.morph_phase == MorphPhase::Hold,
+            None => matches!(self.config.mode, ParticleSimMode::Boids(_)),
+        };
         let uniform = GpuSimUniform::new(
             ParticleStepInput {
                 dt_seconds: clamped_dt,
                 ..input
             },
             self.config,
+            (spawn_count as u32).saturating_add(burst_count),
+            self.elapsed_seconds,
+            self.collider_count,
+            self.volume_bounds,
+            steered,
         );
         queue.write_buffer(&self.sim_uniform_buffer, 0, bytes_of(&uniform));
+        queue.write_buffer(
+            &self.force_field_buffer,
+            0,
+            cast_slice(&GpuForceField::pack(&input.force.fields)),
+        );
+        self.step_index += 1;
+        self.elapsed_seconds += clamped_dt;
+
+        if let Some(sph) = &self.sph {
+            sph.encode(queue, encoder, clamped_dt);
+        }
+        if let Some(boids) = &self.boids {
+            boids.encode(queue, encoder, input.force.fields.len() as u32, clamped_dt);
+        }
+        if let Some((config, passes)) = morph {
+            passes.encode(queue, encoder, &config, self.morph_phase, clamped_dt);
+            self.morph_phase = self.morph_phase.advanced();
+        }
 
+        let pbd = match (self.config.mode, &self.constraints) {
+            (ParticleSimMode::Pbd(pbd), Some(constraints)) => Some((pbd, constraints)),
+            _ => None,
+        };
+        if let Some((_, constraints)) = pbd {
+            constraints.encode_snapshot(encoder, &self.particle_buffer);
+        }
+
+        // Update and emit only touch live work: the single-thread `begin_*` kernels turn the
+        // alive/dead counters into indirect dispatch sizes, so cost scales with alive particles.
         let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
             label: Some("particles.update.pass"),
             timestamp_writes: None,
         });
-        pass.set_pipeline(&self.pipeline);
-        pass.set_bind_group(0, &self.bind_group, &[]);
-        pass.dispatch_workgroups(self.compute_plan.dispatch_x, 1, 1);
+        pass.set_bind_group(1, &self.dispatch_args_bind_group, &[]);
+        let stages = [
+            (
+                &self.pipelines.begin_update,
+                &self.pipelines.update,
+                GpuDispatchArgs::UPDATE_OFFSET,
+            ),
+            (
+                &self.pipelines.begin_emit,
+                &self.pipelines.emit,
+                GpuDispatchArgs::EMIT_OFFSET,
+            ),
+            (
+                &self.pipelines.begin_sub_emit,
+                &self.pipelines.sub_emit,
+                GpuDispatchArgs::SUB_EMIT_OFFSET,
+            ),
+        ];
+        let stage_count = if self.config.sub_emitter.is_some() {
+            3
+        } else {
+            2
+        };
+        for (begin, kernel, args_offset) in &stages[..stage_count] {
+            pass.set_pipeline(begin);
+            pass.set_bind_group(0, &self.begin_bind_group, &[]);
+            pass.dispatch_workgroups(1, 1, 1);
+            pass.set_pipeline(kernel);
+            pass.set_bind_group(0, &self.bind_group, &[]);
+            pass.dispatch_workgroups_indirect(&self.dispatch_args_buffer, *args_offset);
+        }
+        drop(pass);
+
+        if let Some((pbd, constraints)) = pbd {
+            constraints.encode_solve(queue, encoder, &pbd, clamped_dt);
+        }
+        if let Some(trails) = &self.trails {
+            trails.encode(queue, encoder, self.step_index);
+        }
     }
 
     pub fn step(
-        &self,
+        &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         input: ParticleStepInput,
@@ -217,55 +896,268 @@ impl ParticleGpuSim {
         queue.submit(Some(encoder.finish()))
     }
 
-    pub fn readback_debug_sample(
+    /// Copies the latest step's stats into a free staging slot and starts mapping it.
+    /// Returns `false` when every slot is still in flight; the request is dropped, not queued.
+    pub fn request_stats(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let Some(slot) = self.stats_readback.acquire() else {
+            return false;
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("particles.stats.copy.encoder"),
+        });
+        encoder.copy_buffer_to_buffer(
+            &self.stats_buffer,
+            0,
+            self.stats_readback.buffer(slot),
+            0,
+            self.stats_buffer.size(),
+        );
+        queue.submit(Some(encoder.finish()));
+        self.stats_readback.begin_map(slot, self.step_index);
+        true
+    }
+
+    /// Non-blocking: returns the newest requested stats whose copy has landed, if any.
+    pub fn poll_stats(
+        &mut self,
+        device: &wgpu::Device,
+    ) -> Result<Option<ParticleSimStats>, ParticleGpuError> {
+        let latest = self.stats_readback.poll_latest(device)?;
+        Ok(latest.map(|(step_index, bytes)| {
+            let raw: GpuSimStats = bytemuck::pod_read_unaligned(&bytes);
+            raw.decode(step_index)
+        }))
+    }
+
+    /// Blocking copy of the stats after every submitted step, for tools that cannot wait on
+    /// `request_stats`; per-frame readers should poll.
+    pub fn read_stats_blocking(
         &self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        sample_count: u32,
-    ) -> Result<Vec<Particle>, ParticleGpuError> {
-        let sample_count = sample_count.min(self.compute_plan.particle_count);
-        if sample_count == 0 {
-            return Ok(Vec::new());
+    ) -> Result<ParticleSimStats, ParticleGpuError> {
+        let bytes = read_buffer_blocking(
+            device,
+            queue,
+            &self.stats_buffer,
+            size_of::<GpuSimStats>() as u64,
+        )?;
+        let raw: GpuSimStats = bytemuck::pod_read_unaligned(&bytes);
+        Ok(raw.decode(self.step_index))
+    }
+
+    /// Reads the pool, attributes and slot lists back for a `ParticleSnapshot`, blocking until
+    /// every submitted step has finished.
+    pub fn snapshot(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<ParticleSnapshot, ParticleGpuError> {
+        let capacity = self.config.max_particles as usize;
+        let mut snapshot = ParticleSnapshot {
+            config: SnapshotConfig::new(&self.config),
+            step_index: self.step_index,
+            elapsed_seconds: self.elapsed_seconds,
+            spawn_accumulator: self.spawn_accumulator,
+            particles: Vec::new(),
+            attributes: vec![ParticleAttributes::default(); capacity],
+            sub_emitted: vec![false; capacity],
+            free_slots: Vec::new(),
+        };
+        if capacity == 0 {
+            return Ok(snapshot);
         }
 
-        let bytes_to_copy = (sample_count as u64) * size_of::<Particle>() as u64;
-        let staging = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("particles.debug.staging"),
-            size: bytes_to_copy,
-            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
+        let particle_bytes = (capacity * size_of::<Particle>()) as u64;
+        snapshot.particles = read_pod_vec(&read_buffer_blocking(
+            device,
+            queue,
+            &self.particle_buffer,
+            particle_bytes,
+        )?);
+        let stride = self.attribute_layout.stride_bytes as usize;
+        if stride > 0 {
+            let records = read_buffer_blocking(
+                device,
+                queue,
+                &self.attribute_buffer,
+                (stride * capacity) as u64,
+            )?;
+            snapshot.attributes = records
+                .chunks_exact(stride)
+                .map(|record| self.attribute_layout.decode(record))
+                .collect();
+        }
+
+        let counters: GpuParticleCounters = bytemuck::pod_read_unaligned(&read_buffer_blocking(
+            device,
+            queue,
+            &self.counter_buffer,
+            size_of::<GpuParticleCounters>() as u64,
+        )?);
+        let indices: Vec<u32> = read_pod_vec(&read_buffer_blocking(
+            device,
+            queue,
+            &self.index_buffer,
+            self.index_buffer.size(),
+        )?);
+        snapshot.free_slots = indices[..counters.dead_count as usize].to_vec();
+        // Between steps the alive list `update` last wrote is the one `begin_update` reads next.
+        let alive_base = capacity * (2 - counters.list_parity as usize);
+        for &entry in &indices[alive_base..alive_base + counters.alive_next as usize] {
+            if entry & SUB_EMITTED_SLOT_BIT != 0 {
+                snapshot.sub_emitted[(entry & !SUB_EMITTED_SLOT_BIT) as usize] = true;
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Replaces the pool and spawn state with `snapshot`'s, which must come from a sim with the
+    /// same `max_particles` and attribute schema. Trails restart empty; constraints and morph
+    /// targets are kept.
+    pub fn restore(
+        &mut self,
+        queue: &wgpu::Queue,
+        snapshot: &ParticleSnapshot,
+    ) -> Result<(), SnapshotError> {
+        let capacity = self.config.max_particles as usize;
+        snapshot.check_capacity(capacity)?;
+        if snapshot.config.attributes.layout() != self.attribute_layout {
+            return Err(SnapshotError::AttributeSchemaMismatch);
+        }
+
+        queue.write_buffer(&self.particle_buffer, 0, cast_slice(&snapshot.particles));
+        let stride = self.attribute_layout.stride_bytes as usize;
+        if stride > 0 && capacity > 0 {
+            let mut records = vec![0u8; stride * capacity];
+            for (record, values) in records.chunks_exact_mut(stride).zip(&snapshot.attributes) {
+                self.attribute_layout.encode(values, record);
+            }
+            queue.write_buffer(&self.attribute_buffer, 0, &records);
+        }
+
+        // Same arrangement as `set_constraints`: the dead stack, then the alive slots in the
+        // list `begin_update` reads next.
+        let mut indices = vec![0u32; 3 * capacity];
+        indices[..snapshot.free_slots.len()].copy_from_slice(&snapshot.free_slots);
+        let alive = snapshot
+            .particles
+            .iter()
+            .zip(&snapshot.sub_emitted)
+            .enumerate()
+            .filter(|(_, (particle, _))| particle.is_alive())
+            .map(|(slot, (_, &sub_emitted))| {
+                slot as u32 | if sub_emitted { SUB_EMITTED_SLOT_BIT } else { 0 }
+            });
+        let mut alive_count = 0;
+        for (entry, index) in alive.zip(&mut indices[2 * capacity..]) {
+            *index = entry;
+            alive_count += 1;
+        }
+        if capacity > 0 {
+            queue.write_buffer(&self.index_buffer, 0, cast_slice(&indices));
+        }
+        let counters = GpuParticleCounters {
+            dead_count: snapshot.free_slots.len() as u32,
+            alive_next: alive_count,
+            ..GpuParticleCounters::zeroed()
+        };
+        queue.write_buffer(&self.counter_buffer, 0, bytes_of(&counters));
+
+        if let Some(trails) = &self.trails {
+            trails.clear(queue);
+        }
+        self.spawn_accumulator = snapshot.spawn_accumulator;
+        self.step_index = snapshot.step_index;
+        self.elapsed_seconds = snapshot.elapsed_seconds;
+        Ok(())
+    }
+
+    /// Sizes the staging ring behind `request_particles` to copy the first `sample_count`
+    /// slots, capped at the pool size; zero frees it. Copies still in flight are dropped.
+    pub fn set_particle_readback(&mut self, device: &wgpu::Device, sample_count: u32) {
+        let sample_count = sample_count.min(self.compute_plan.particle_count);
+        self.particle_readback = (sample_count > 0).then(|| {
+            let ring = StagingRing::new(
+                device,
+                "particles.readback.staging",
+                sample_count as u64 * size_of::<Particle>() as u64,
+                PARTICLE_READBACK_SLOTS,
+            );
+            (ring, sample_count)
         });
+    }
+
+    /// Copies the slots chosen by `set_particle_readback` into a free staging buffer after the
+    /// latest step and starts mapping it. Returns `false` when readback is off or every slot is
+    /// still in flight; the request is dropped, not queued.
+    pub fn request_particles(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        let Some((ring, sample_count)) = &mut self.particle_readback else {
+            return false;
+        };
+        let Some(slot) = ring.acquire() else {
+            return false;
+        };
 
         let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("particles.debug.copy.encoder"),
+            label: Some("particles.readback.copy.encoder"),
         });
-        encoder.copy_buffer_to_buffer(&self.particle_buffer, 0, &staging, 0, bytes_to_copy);
+        encoder.copy_buffer_to_buffer(
+            &self.particle_buffer,
+            0,
+            ring.buffer(slot),
+            0,
+            *sample_count as u64 * size_of::<Particle>() as u64,
+        );
         queue.submit(Some(encoder.finish()));
+        ring.begin_map(slot, self.step_index);
+        true
+    }
 
-        let slice = staging.slice(..);
-        let (tx, rx) = mpsc::channel();
-        slice.map_async(wgpu::MapMode::Read, move |result| {
-            let _ = tx.send(result);
-        });
+    /// Non-blocking: returns the newest requested particles whose copy has landed, if any,
+    /// typically a few steps behind `step_index`.
+    pub fn poll_particles(
+        &mut self,
+        device: &wgpu::Device,
+    ) -> Result<Option<ParticleReadback>, ParticleGpuError> {
+        let Some((ring, _)) = &mut self.particle_readback else {
+            return Ok(None);
+        };
+        let latest = ring.poll_latest(device)?;
+        Ok(latest.map(|(step_index, bytes)| ParticleReadback {
+            step_index,
+            particles: read_pod_vec(&bytes),
+        }))
+    }
 
-        #[allow(deprecated)]
-        {
-            device.poll(wgpu::Maintain::Wait);
+    /// Blocking copy of the first `sample_count` slots through a one-off staging buffer, for
+    /// tests and tools; per-frame readers should use `request_particles`.
+    pub fn readback_debug_sample(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        sample_count: u32,
+    ) -> Result<Vec<Particle>, ParticleGpuError> {
+        let sample_count = sample_count.min(self.compute_plan.particle_count);
+        if sample_count == 0 {
+            return Ok(Vec::new());
         }
 
-        let map_result = rx.recv().map_err(|_| ParticleGpuError::ChannelClosed)?;
-        map_result.map_err(|_| ParticleGpuError::MapFailed)?;
-
-        let data = slice.get_mapped_range();
-        let particles: &[Particle] = cast_slice(&data);
-        let out = particles.to_vec();
-        drop(data);
-        staging.unmap();
-
-        Ok(out)
+        let bytes_to_copy = (sample_count as u64) * size_of::<Particle>() as u64;
+        let bytes = read_buffer_blocking(device, queue, &self.particle_buffer, bytes_to_copy)?;
+        Ok(read_pod_vec(&bytes))
     }
 }
 
+/// Bounds of the uploaded volumes, each `None` while its placeholder is bound: world space for
+/// the SDF, the volume's own space for the vector field.
+#[derive(Debug, Clone, Copy, Default)]
+struct VolumeBounds {
+    sdf: Option<([f32; 3], [f32; 3])>,
+    vector_field: Option<([f32; 3], [f32; 3])>,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Pod, Zeroable)]
 struct GpuSimUniform {
@@ -274,36 +1166,567 @@ struct GpuSimUniform {
     spawn_rate: f32,
     lifetime: f32,
     gravity: [f32; 3],
-    _pad0: f32,
-    attractor: [f32; 3],
-    attractor_strength: f32,
+    spawn_count: u32,
     noise_strength: f32,
-    _pad1: [f32; 3],
+    noise_frequency: f32,
+    noise_time_scale: f32,
+    noise_octaves: u32,
+    initial_speed: f32,
+    time: f32,
+    force_field_count: u32,
+    steered: u32,
+    sdf_bounds_min: [f32; 3],
+    collider_count: u32,
+    sdf_bounds_max: [f32; 3],
+    sdf_enabled: u32,
+    container_min: [f32; 3],
+    container_enabled: u32,
+    container_max: [f32; 3],
+    container_restitution: f32,
+    vector_field_bounds_min: [f32; 3],
+    vector_field_enabled: u32,
+    vector_field_bounds_max: [f32; 3],
+    integrator: u32,
+    seed: u32,
+    non_finite_policy: u32,
+    _pad0: [u32; 2],
+    emitter: GpuEmitter,
+    sub_emitter: GpuSubEmitter,
 }
 
 impl GpuSimUniform {
-    fn new(step: ParticleStepInput, config: ParticleSimConfig) -> Self {
+    fn new(
+        step: ParticleStepInput,
+        config: ParticleSimConfig,
+        spawn_count: u32,
+        time_seconds: f32,
+        collider_count: u32,
+        volume_bounds: VolumeBounds,
+        steered: bool,
+    ) -> Self {
+        let (sdf_bounds_min, sdf_bounds_max) = volume_bounds.sdf.unwrap_or_default();
+        let (vector_field_bounds_min, vector_field_bounds_max) =
+            volume_bounds.vector_field.unwrap_or_default();
+        let container = match config.mode {
+            ParticleSimMode::Sph(sph) => Some(sph),
+            _ => None,
+        };
         Self {
             dt: step.dt_seconds,
             drag: config.drag,
             spawn_rate: config.spawn_rate_per_second,
             lifetime: config.lifetime_seconds,
             gravity: step.force.gravity,
-            _pad0: 0.0,
-            attractor: step.force.attractor,
-            attractor_strength: step.force.attractor_strength,
+            spawn_count,
             noise_strength: step.force.noise_strength,
-            _pad1: [0.0; 3],
+            noise_frequency: step.force.noise_frequency,
+            noise_time_scale: step.force.noise_time_scale,
+            noise_octaves: step.force.noise_octaves.min(MAX_NOISE_OCTAVES),
+            initial_speed: step.emitter.initial_speed,
+            time: time_seconds,
+            force_field_count: step.force.fields.len() as u32,
+            steered: steered as u32,
+            sdf_bounds_min,
+            collider_count,
+            sdf_bounds_max,
+            sdf_enabled: volume_bounds.sdf.is_some() as u32,
+            container_min: container.map_or([0.0; 3], |sph| sph.container_min),
+            container_enabled: container.is_some() as u32,
+            container_max: container.map_or([0.0; 3], |sph| sph.container_max),
+            container_restitution: container.map_or(0.0, |sph| sph.container_restitution),
+            vector_field_bounds_min,
+            vector_field_enabled: volume_bounds.vector_field.is_some() as u32,
+            vector_field_bounds_max,
+            integrator: config.integrator.gpu_kind(),
+            seed: config.seed,
+            non_finite_policy: config.non_finite_policy.gpu_kind(),
+            _pad0: [0; 2],
+            emitter: GpuEmitter::new(&step.emitter),
+            sub_emitter: GpuSubEmitter::new(config.sub_emitter.as_ref()),
+        }
+    }
+}
+
+/// Mirrors `Emitter` in particles_update.wgsl; `shape`, `mode` and `direction_kind` use the
+/// `SHAPE_*`, `MODE_*` and `DIRECTION_*` constants there.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub(crate) struct GpuEmitter {
+    center: [f32; 3],
+    shape: u32,
+    params: [f32; 4],
+    axis: [f32; 3],
+    mode: u32,
+    direction: [f32; 3],
+    direction_kind: u32,
+    color: [f32; 4],
+    size: f32,
+    size_variance: f32,
+    angular_speed: f32,
+    pixel_color: u32,
+}
+
+impl GpuEmitter {
+    pub(crate) fn new(emitter: &EmitterConfig) -> Self {
+        let (shape, params, axis) = match emitter.shape {
+            EmitterShape::Sphere { radius } => (0, [radius, 0.0, 0.0, 0.0], [0.0; 3]),
+            EmitterShape::Box { half_extents } => (
+                1,
+                [half_extents[0], half_extents[1], half_extents[2], 0.0],
+                [0.0; 3],
+            ),
+            EmitterShape::Disc { radius, normal } => (2, [radius, 0.0, 0.0, 0.0], normal),
+            EmitterShape::Cone {
+                radius,
+                angle_radians,
+                axis,
+            } => (3, [radius, angle_radians, 0.0, 0.0], axis),
+            EmitterShape::Line { direction, length } => (4, [length, 0.0, 0.0, 0.0], direction),
+            EmitterShape::Ring {
+                radius,
+                thickness,
+                normal,
+            } => (5, [radius, thickness, 0.0, 0.0], normal),
+            EmitterShape::Image { half_extents, .. } => {
+                (6, [half_extents[0], half_extents[1], 0.0, 0.0], [0.0; 3])
+            }
+        };
+        let (direction_kind, direction) = match emitter.direction {
+            EmitterDirection::Shape => (0, [0.0; 3]),
+            EmitterDirection::Along(direction) => (1, direction),
+            EmitterDirection::Random => (2, [0.0; 3]),
+        };
+        Self {
+            center: emitter.center,
+            shape,
+            params,
+            axis,
+            mode: match emitter.mode {
+                EmissionMode::Volume => 0,
+                EmissionMode::Surface => 1,
+            },
+            direction,
+            direction_kind,
+            color: emitter.color,
+            size: emitter.size,
+            size_variance: emitter.size_variance,
+            angular_speed: emitter.angular_speed,
+            pixel_color: matches!(
+                emitter.shape,
+                EmitterShape::Image {
+                    pixel_color: true,
+                    ..
+                }
+            ) as u32,
+        }
+    }
+}
+
+/// Mirrors `Counters` in particles_update.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuParticleCounters {
+    dead_count: u32,
+    alive_count: u32,
+    alive_next: u32,
+    list_parity: u32,
+    emit_count: u32,
+    emit_base: u32,
+}
+
+impl GpuParticleCounters {
+    fn new(capacity: u32) -> Self {
+        Self {
+            dead_count: capacity,
+            ..Self::zeroed()
+        }
+    }
+}
+
+/// Mirrors `DispatchArgs` in particles_update.wgsl: three `(x, y, z)` workgroup triples.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuDispatchArgs {
+    update: [u32; 3],
+    emit: [u32; 3],
+    sub_emit: [u32; 3],
+}
+
+impl GpuDispatchArgs {
+    const UPDATE_OFFSET: u64 = 0;
+    const EMIT_OFFSET: u64 = 12;
+    const SUB_EMIT_OFFSET: u64 = 24;
+}
+
+/// Unaligned `bytes` read back from a buffer, as `T` values.
+fn read_pod_vec<T: Pod>(bytes: &[u8]) -> Vec<T> {
+    bytes
+        .chunks_exact(size_of::<T>())
+        .map(bytemuck::pod_read_unaligned)
+        .collect()
+}
+
+/// Dead stack ordered so the first pops hand out slots 0, 1, 2, ... like the CPU free list.
+fn initial_dead_list(capacity: u32) -> Vec<u32> {
+    (0..capacity).rev().collect()
+}
+
+/// Bindings of the `buffers` passed to `create_compute_bind_group`, in order.
+const COMPUTE_BUFFER_BINDINGS: [u32; 9] = [0, 1, 2, 3, 4, 5, 6, 8, 10];
+const SDF_VOLUME_BINDING: u32 = 7;
+const LIFETIME_LUT_BINDING: u32 = 9;
+const SPAWN_IMAGE_BINDING: u32 = 11;
+const SPAWN_CDF_BINDING: u32 = 12;
+const VECTOR_FIELD_BINDING: u32 = 13;
+/// Uniform and storage bindings of the `begin_*` kernels' group 0: sim, counters, stats, events.
+const BEGIN_BUFFER_BINDINGS: [u32; 4] = [1, 3, 4, 10];
+/// Bindings of the `textures` passed to `create_compute_bind_group`, in order.
+const COMPUTE_TEXTURE_BINDINGS: [u32; 5] = [
+    SDF_VOLUME_BINDING,
+    LIFETIME_LUT_BINDING,
+    SPAWN_IMAGE_BINDING,
+    SPAWN_CDF_BINDING,
+    VECTOR_FIELD_BINDING,
+];
+const LIFETIME_LUT_SIZE: wgpu::Extent3d = wgpu::Extent3d {
+    width: LIFETIME_LUT_WIDTH,
+    height: LIFETIME_LUT_ROWS,
+    depth_or_array_layers: 1,
+};
+
+/// Group 0 of the update/emit kernels: `buffers` fill `COMPUTE_BUFFER_BINDINGS` and `textures`
+/// `COMPUTE_TEXTURE_BINDINGS`.
+fn create_compute_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffers: [&wgpu::Buffer; 9],
+    textures: [&wgpu::Texture; 5],
+) -> wgpu::BindGroup {
+    let views =
+        textures.map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+    let mut entries: Vec<wgpu::BindGroupEntry> = buffers
+        .iter()
+        .zip(COMPUTE_BUFFER_BINDINGS)
+        .map(|(buffer, binding)| wgpu::BindGroupEntry {
+            binding,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+    entries.extend(
+        views
+            .iter()
+            .zip(COMPUTE_TEXTURE_BINDINGS)
+            .map(|(view, binding)| wgpu::BindGroupEntry {
+                binding,
+                resource: wgpu::BindingResource::TextureView(view),
+            }),
+    );
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("particles.compute.bg"),
+        layout,
+        entries: &entries,
+    })
+}
+
+fn write_lifetime_lut(queue: &wgpu::Queue, texture: &wgpu::Texture, lut: &LifetimeLut) {
+    queue.write_texture(
+        texture.as_image_copy(),
+        cast_slice(lut.texels()),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(LIFETIME_LUT_WIDTH * size_of::<[f32; 4]>() as u32),
+            rows_per_image: Some(LIFETIME_LUT_ROWS),
+        },
+        LIFETIME_LUT_SIZE,
+    );
+}
+
+/// `R32Float` 3D texture holding `volume`, or a 1x1x1 placeholder the shader never samples.
+fn create_sdf_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    volume: Option<&SdfVolume>,
+) -> wgpu::Texture {
+    create_volume_texture(
+        device,
+        queue,
+        "particles.sdf_volume",
+        wgpu::TextureFormat::R32Float,
+        volume.map(|volume| (volume.resolution(), cast_slice(volume.distances()))),
+    )
+}
+
+/// `Rgba32Float` 3D texture holding `volume`, or a 1x1x1 placeholder the shader never samples.
+fn create_vector_field_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    volume: Option<&VectorFieldVolume>,
+) -> wgpu::Texture {
+    let texels = volume.map(VectorFieldVolume::texels);
+    create_volume_texture(
+        device,
+        queue,
+        "particles.vector_field",
+        wgpu::TextureFormat::Rgba32Float,
+        volume
+            .zip(texels.as_deref())
+            .map(|(volume, texels)| (volume.resolution(), cast_slice(texels))),
+    )
+}
+
+/// 3D texture of `resolution` filled with `texels`, x varying fastest; 1x1x1 and left zeroed
+/// without contents.
+fn check_volume_fits(
+    device: &wgpu::Device,
+    resolution: Option<[u32; 3]>,
+) -> Result<(), ParticleGpuError> {
+    let limit = device.limits().max_texture_dimension_3d;
+    match resolution {
+        Some(resolution) if resolution.iter().any(|&n| n > limit) => {
+            Err(ParticleGpuError::VolumeTooLarge { resolution, limit })
         }
+        _ => Ok(()),
+    }
+}
+
+fn create_volume_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &str,
+    format: wgpu::TextureFormat,
+    contents: Option<([u32; 3], &[u8])>,
+) -> wgpu::Texture {
+    let [width, height, depth] = contents.map_or([1; 3], |(resolution, _)| resolution);
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: depth,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    if let Some((_, texels)) = contents {
+        let texel_bytes = format.block_copy_size(None).unwrap_or(4);
+        queue.write_texture(
+            texture.as_image_copy(),
+            texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(width * texel_bytes),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+    }
+    texture
+}
+
+/// `Rgba32Float` colors and `R32Float` CDFs of `image`, laid out as `SpawnImage::texels` and
+/// `SpawnImage::cdf_texels` describe.
+fn create_spawn_image_textures(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &SpawnImage,
+) -> [wgpu::Texture; 2] {
+    let (cdf_width, cdf_texels) = image.cdf_texels();
+    let create = |label, size: wgpu::Extent3d, format, texels: &[u8], texel_bytes: u32| {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size.width * texel_bytes),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+        texture
+    };
+    [
+        create(
+            "particles.spawn_image",
+            wgpu::Extent3d {
+                width: image.width(),
+                height: image.height(),
+                depth_or_array_layers: 1,
+            },
+            wgpu::TextureFormat::Rgba32Float,
+            cast_slice(image.texels()),
+            size_of::<[f32; 4]>() as u32,
+        ),
+        create(
+            "particles.spawn_cdf",
+            wgpu::Extent3d {
+                width: cdf_width,
+                height: image.height() + 1,
+                depth_or_array_layers: 1,
+            },
+            wgpu::TextureFormat::R32Float,
+            cast_slice(&cdf_texels),
+            size_of::<f32>() as u32,
+        ),
+    ]
+}
+
+fn texture_entry(
+    binding: u32,
+    view_dimension: wgpu::TextureViewDimension,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+/// Group 0 of the update/emit kernels. Eight storage buffers, the WebGPU default per stage.
+fn compute_layout_entries() -> [wgpu::BindGroupLayoutEntry; 14] {
+    [
+        storage_entry(0, false),
+        uniform_entry(1),
+        storage_entry(2, false),
+        storage_entry(3, false),
+        storage_entry(4, false),
+        storage_entry(5, true),
+        storage_entry(6, true),
+        storage_entry(8, false),
+        storage_entry(10, false),
+        texture_entry(SDF_VOLUME_BINDING, wgpu::TextureViewDimension::D3),
+        texture_entry(LIFETIME_LUT_BINDING, wgpu::TextureViewDimension::D2),
+        texture_entry(SPAWN_IMAGE_BINDING, wgpu::TextureViewDimension::D2),
+        texture_entry(SPAWN_CDF_BINDING, wgpu::TextureViewDimension::D2),
+        texture_entry(VECTOR_FIELD_BINDING, wgpu::TextureViewDimension::D3),
+    ]
+}
+
+/// Group 0 of the `begin_*` kernels, at `BEGIN_BUFFER_BINDINGS`.
+fn begin_layout_entries() -> [wgpu::BindGroupLayoutEntry; 4] {
+    BEGIN_BUFFER_BINDINGS.map(|binding| match binding {
+        1 => uniform_entry(binding),
+        _ => storage_entry(binding, false),
+    })
+}
+
+fn storage_buffer_count(entries: &[wgpu::BindGroupLayoutEntry]) -> u32 {
+    entries
+        .iter()
+        .filter(|entry| {
+            matches!(
+                entry.ty,
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { .. },
+                    ..
+                }
+            )
+        })
+        .count() as u32
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::GpuSimUniform;
+    use super::{
+        begin_layout_entries, compute_layout_entries, initial_dead_list, storage_buffer_count,
+        GpuDispatchArgs, GpuParticleCounters, GpuSimUniform,
+    };
+    use crate::particles::compute::ParticleBufferLayout;
+    use crate::particles::forces::{GpuForceField, MAX_FORCE_FIELDS};
+    use std::mem::{offset_of, size_of};
+
+    #[test]
+    fn sim_uniform_matches_buffer_layout() {
+        let layout = ParticleBufferLayout::default();
+        assert_eq!(size_of::<GpuSimUniform>() as u64, layout.sim_uniform_bytes);
+        assert_eq!(
+            size_of::<[GpuForceField; MAX_FORCE_FIELDS]>() as u64,
+            layout.force_fields_bytes
+        );
+    }
+
+    #[test]
+    fn counters_and_dispatch_args_match_buffer_layout() {
+        let layout = ParticleBufferLayout::default();
+        assert_eq!(
+            size_of::<GpuParticleCounters>() as u64,
+            layout.counter_bytes
+        );
+        assert_eq!(
+            size_of::<GpuDispatchArgs>() as u64,
+            layout.dispatch_args_bytes
+        );
+        assert_eq!(
+            offset_of!(GpuDispatchArgs, update) as u64,
+            GpuDispatchArgs::UPDATE_OFFSET
+        );
+        assert_eq!(
+            offset_of!(GpuDispatchArgs, emit) as u64,
+            GpuDispatchArgs::EMIT_OFFSET
+        );
+        assert_eq!(
+            offset_of!(GpuDispatchArgs, sub_emit) as u64,
+            GpuDispatchArgs::SUB_EMIT_OFFSET
+        );
+    }
+
+    #[test]
+    fn dead_list_pops_lowest_slot_first() {
+        let list = initial_dead_list(4);
+        assert_eq!(list.last(), Some(&0));
+        assert_eq!(list.len(), 4);
+    }
 
     #[test]
-    fn sim_uniform_size_is_64_bytes() {
-        assert_eq!(std::mem::size_of::<GpuSimUniform>(), 64);
+    fn pipelines_stay_within_default_storage_buffer_limit() {
+        let limit = wgpu::Limits::default().max_storage_buffers_per_shader_stage;
+        assert!(storage_buffer_count(&compute_layout_entries()) <= limit);
+        // Leaves room for `dispatch_args` in group 1.
+        assert!(storage_buffer_count(&begin_layout_entries()) < limit);
     }
 }
@@ -0,0 +1,63 @@
+//! Small `[f32; 3]` helpers shared by the CPU reference paths.
+
+pub(crate) fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn mul_scalar(v: [f32; 3], s: f32) -> [f32; 3] {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn normalize_or_zero(v: [f32; 3]) -> [f32; 3] {
+    let len_sq = dot(v, v);
+    if len_sq <= 1e-8 {
+        return [0.0, 0.0, 0.0];
+    }
+    mul_scalar(v, len_sq.sqrt().recip())
+}
+
+pub(crate) fn quaternion_or_identity(q: [f32; 4]) -> [f32; 4] {
+    let len_sq = q.iter().map(|c| c * c).sum::<f32>();
+    if len_sq <= 1e-8 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+    let inv = len_sq.sqrt().recip();
+    q.map(|c| c * inv)
+}
+
+/// Rotates `v` by the unit quaternion `q` (`[x, y, z, w]`); mirrors `rotate` in
+/// particles_update.wgsl.
+pub(crate) fn rotate(q: [f32; 4], v: [f32; 3]) -> [f32; 3] {
+    let axis = [q[0], q[1], q[2]];
+    let t = mul_scalar(cross(axis, v), 2.0);
+    add(add(v, mul_scalar(t, q[3])), cross(axis, t))
+}
+
+/// Must stay bit-identical to `hash_u32` in random.wgsl.
+pub(crate) fn hash_u32(seed: u32) -> u32 {
+    let mut x = seed.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
+    x ^= x >> 16;
+    x = x.wrapping_mul(2_246_822_519);
+    x ^ (x >> 13)
+}
+
+/// `hash_u32` mapped to [0, 1]; must stay bit-identical to `hash01` in random.wgsl.
+pub(crate) fn hash01(seed: u32) -> f32 {
+    (hash_u32(seed) as f32) / (u32::MAX as f32)
+}
@@ -1,9 +1,66 @@
+pub mod attributes;
+pub mod boids;
+pub mod burst;
+pub mod collision;
 pub mod compute;
 pub mod config;
+pub mod constraints;
+pub mod curves;
+mod emitter;
+pub mod forces;
 pub mod gpu;
+pub mod integrator;
+mod math;
+pub mod morph;
+pub mod noise;
+pub mod parity;
+mod readback;
 pub mod simulation;
+pub mod snapshot;
+pub mod spatial_grid;
+pub mod spawn_image;
+pub mod sph;
+pub mod stats;
+pub mod sub_emitter;
+pub mod trails;
+pub mod vector_field;
+pub mod watchdog;
 
+pub use attributes::{
+    ParticleAttributeLayout, ParticleAttributeSchema, ParticleAttributes, MAX_USER_FLOATS,
+};
+pub use boids::BoidsConfig;
+pub use burst::{Burst, BurstSchedule, MAX_BURSTS};
+pub use collision::{Collider, ColliderList, ColliderShape, SdfVolume, MAX_COLLIDERS};
 pub use compute::{ParticleComputePlan, ParticleWorkgroup};
-pub use config::{EmitterConfig, ForceConfig, ParticleSimConfig};
-pub use gpu::{ParticleGpuError, ParticleGpuSim, ParticleStepInput};
+pub use config::{
+    EmissionMode, EmitterConfig, EmitterDirection, EmitterShape, ForceConfig, ParticleSimConfig,
+    ParticleSimMode,
+};
+pub use constraints::{ConstraintKind, ConstraintTopology, DistanceConstraint, PbdConfig};
+pub use curves::{
+    ColorGradient, Curve, LifetimeCurves, LifetimeLut, LifetimeSample, ScalarCurve,
+    LIFETIME_LUT_WGSL,
+};
+pub use forces::{ForceField, ForceFieldList, VectorFieldMode, MAX_FORCE_FIELDS};
+pub use gpu::{ParticleGpuError, ParticleGpuSim, ParticleReadback, ParticleStepInput};
+pub use integrator::Integrator;
+pub use morph::{MorphConfig, MorphTargets};
+pub use noise::{curl_noise, MAX_NOISE_OCTAVES, RANDOM_WGSL};
+pub use parity::{
+    check_random_parity, ParityHarness, ParityMismatch, ParityReport, ParityTolerance, RandomParity,
+};
 pub use simulation::{Particle, ParticleState, SimulationClock};
+pub use snapshot::{
+    ParticleSnapshot, SnapshotConfig, SnapshotError, SNAPSHOT_MAGIC, SNAPSHOT_VERSION,
+};
+pub use spatial_grid::{SpatialGridConfig, SpatialHashGrid, SpatialHashGridGpu};
+pub use spawn_image::{SpawnImage, SpawnImageWeight, MAX_SPAWN_IMAGE_SIZE};
+pub use sph::SphConfig;
+pub use stats::ParticleSimStats;
+pub use sub_emitter::{ParticleEvent, ParticleEventKind, SubEmitterConfig};
+pub use trails::{
+    ribbon_vertices, ParticleTrailRenderer, RibbonVertex, TrailCamera, TrailConfig, TrailHistory,
+};
+pub use vector_field::{FgaError, VectorFieldVolume};
+pub use watchdog::{soak_gpu, soak_reference, NonFinitePolicy, SoakReport};
@@ -0,0 +1,560 @@
+use std::borrow::Cow;
+use std::mem::size_of;
+
+use bytemuck::{bytes_of, cast_slice, Pod, Zeroable};
+
+use super::math::{add, cross, dot, hash01, mul_scalar, normalize_or_zero, sub};
+use super::simulation::Particle;
+use super::spatial_grid::{layout_entry, storage_binding, uniform_binding};
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Particles spring towards the targets set with `set_morph_targets` until released with
+/// `set_morph_released`, then fly off with `release_speed` and move ballistically. While held,
+/// gravity, noise, drag and force fields do not act on them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MorphConfig {
+    /// Spring acceleration per unit of distance to the target.
+    pub stiffness: f32,
+    /// Acceleration opposing the velocity, per unit of speed; `2 * sqrt(stiffness)` is critical.
+    pub damping: f32,
+    /// Speed added on release, away from the centroid of the targets.
+    pub release_speed: f32,
+}
+
+impl Default for MorphConfig {
+    fn default() -> Self {
+        Self {
+            stiffness: 60.0,
+            damping: 12.0,
+            release_speed: 1.5,
+        }
+    }
+}
+
+/// Target positions for `ParticleSimMode::Morph`. Slot `i` of a pool of `capacity` always gets
+/// `target_for_slot(i, capacity)`, so assignment is stable across steps, runs and CPU/GPU.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MorphTargets {
+    points: Vec<[f32; 3]>,
+}
+
+impl MorphTargets {
+    /// Point cloud targets, used as given.
+    pub fn from_points(points: Vec<[f32; 3]>) -> Self {
+        Self { points }
+    }
+
+    /// `count` targets evenly spaced along closed 2D contours, such as glyph outlines, in the XY
+    /// plane. Contours are closed from their last point back to their first.
+    pub fn from_outlines(contours: &[Vec<[f32; 2]>], count: u32) -> Self {
+        let edges: Vec<([f32; 2], [f32; 2])> = contours
+            .iter()
+            .filter(|contour| contour.len() >= 2)
+            .flat_map(|contour| {
+                (0..contour.len()).map(|i| (contour[i], contour[(i + 1) % contour.len()]))
+            })
+            .collect();
+        let lengths: Vec<f32> = edges
+            .iter()
+            .map(|(a, b)| ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt())
+            .collect();
+        let perimeter: f32 = lengths.iter().sum();
+        if perimeter <= 0.0 {
+            return Self::default();
+        }
+
+        let spacing = perimeter / count as f32;
+        let mut points = Vec::with_capacity(count as usize);
+        let (mut edge, mut edge_start) = (0, 0.0);
+        for k in 0..count {
+            let s = (k as f32 + 0.5) * spacing;
+            while edge + 1 < edges.len() && s > edge_start + lengths[edge] {
+                edge_start += lengths[edge];
+                edge += 1;
+            }
+            let (a, b) = edges[edge];
+            let t = ((s - edge_start) / lengths[edge].max(f32::MIN_POSITIVE)).clamp(0.0, 1.0);
+            points.push([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, 0.0]);
+        }
+        Self { points }
+    }
+
+    /// `count` targets scattered over a triangle mesh with uniform density per area, from a fixed
+    /// hash sequence. Triangles indexing past `vertices` are skipped.
+    pub fn from_mesh_surface(vertices: &[[f32; 3]], triangles: &[[u32; 3]], count: u32) -> Self {
+        let corners: Vec<[[f32; 3]; 3]> = triangles
+            .iter()
+            .filter(|t| t.iter().all(|&i| (i as usize) < vertices.len()))
+            .map(|t| t.map(|i| vertices[i as usize]))
+            .collect();
+        let mut area_cdf = Vec::with_capacity(corners.len());
+        let mut total = 0.0;
+        for [a, b, c] in &corners {
+            let n = cross(sub(*b, *a), sub(*c, *a));
+            total += 0.5 * dot(n, n).sqrt();
+            area_cdf.push(total);
+        }
+        if total <= 0.0 {
+            return Self::default();
+        }
+
+        let points = (0..count)
+            .map(|k| {
+                let r = [0, 1, 2].map(|i| hash01(k.wrapping_mul(3).wrapping_add(i)));
+                let pick = r[0] * total;
+                let triangle = area_cdf
+                    .partition_point(|&cdf| cdf <= pick)
+                    .min(corners.len() - 1);
+                let [a, b, c] = corners[triangle];
+                // Uniform barycentrics from two randoms (Osada et al. 2002).
+                let s = r[1].sqrt();
+                add(
+                    add(mul_scalar(a, 1.0 - s), mul_scalar(b, s * (1.0 - r[2]))),
+                    mul_scalar(c, s * r[2]),
+                )
+            })
+            .collect();
+        Self { points }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn points(&self) -> &[[f32; 3]] {
+        &self.points
+    }
+
+    /// Mean of the targets; releases push particles away from it.
+    pub fn centroid(&self) -> [f32; 3] {
+        if self.points.is_empty() {
+            return [0.0; 3];
+        }
+        let sum = self.points.iter().fold([0.0; 3], |sum, &p| add(sum, p));
+        mul_scalar(sum, 1.0 / self.points.len() as f32)
+    }
+
+    /// Target of `slot` in a pool of `capacity`. Slots are spread by their bit-reversed index, so
+    /// the low slots the emitter hands out first already cover the whole shape, and a full pool
+    /// occupies every target when there are no more targets than slots. `None` without targets
+    /// or past the pool.
+    pub fn target_for_slot(&self, slot: u32, capacity: u32) -> Option<[f32; 3]> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let position = spread_position(slot, capacity)?;
+        Some(self.target_at(position, capacity))
+    }
+
+    /// Target for position `position` of `capacity` evenly spaced along the target list.
+    fn target_at(&self, position: u32, capacity: u32) -> [f32; 3] {
+        let index = position as u64 * self.points.len() as u64 / capacity as u64;
+        self.points[index as usize]
+    }
+}
+
+/// Slot `k`'s position among `capacity` evenly spaced ones, for `k` in order: indices
+/// bit-reversed within `capacity.next_power_of_two()`, skipping those past the end. A
+/// permutation of `0..capacity`, so no position is left out when the pool is not a power of two.
+fn spread_positions(capacity: u32) -> impl Iterator<Item = u32> {
+    let bits = spread_bits(capacity);
+    (0..1u64 << bits)
+        .map(move |k| reverse_low_bits(k, bits) as u32)
+        .filter(move |&position| position < capacity)
+}
+
+/// Item `slot` of `spread_positions(capacity)` without walking it: binary searches for the
+/// index with `slot` kept indices before it, in O(log² capacity). `None` past the pool.
+fn spread_position(slot: u32, capacity: u32) -> Option<u32> {
+    if slot >= capacity {
+        return None;
+    }
+    let bits = spread_bits(capacity);
+    let (mut lo, mut hi) = (0u64, (1u64 << bits) - 1);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if spread_kept_before(mid + 1, bits, capacity) > slot as u64 {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    Some(reverse_low_bits(lo, bits) as u32)
+}
+
+/// Indices below `k` that `spread_positions` keeps. `[0, k)` splits into one block per set bit
+/// `i` of `k`: the bits above `i` fixed, bit `i` clear and the bits below free. Reversed, the
+/// free bits become the high ones, so a block keeps the multiples of `2^(bits - i)` that stay
+/// below `capacity` once its reversed fixed bits are added.
+fn spread_kept_before(k: u64, bits: u32, capacity: u32) -> u64 {
+    (0..=bits)
+        .filter(|&i| k >> i & 1 == 1)
+        .map(|i| {
+            let fixed = reverse_low_bits(k >> (i + 1) << (i + 1), bits);
+            (capacity as u64)
+                .saturating_sub(fixed)
+                .div_ceil(1 << (bits - i))
+                .min(1 << i)
+        })
+        .sum()
+}
+
+fn spread_bits(capacity: u32) -> u32 {
+    (capacity as u64).next_power_of_two().trailing_zeros()
+}
+
+/// `value` with its low `bits` bits reversed.
+fn reverse_low_bits(value: u64, bits: u32) -> u64 {
+    if bits == 0 {
+        return 0;
+    }
+    value.reverse_bits() >> (64 - bits)
+}
+
+/// Where a morph stands; see `ParticleState::set_morph_released`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MorphPhase {
+    /// Particles spring towards their targets.
+    Hold = 0,
+    /// Released since the last step: the next step applies the release impulse.
+    Release = 1,
+    /// Particles move ballistically.
+    Released = 2,
+}
+
+impl MorphPhase {
+    /// Phase after `set_morph_released(released)`.
+    pub(crate) fn with_released(self, released: bool) -> Self {
+        match (self, released) {
+            (_, false) => Self::Hold,
+            (Self::Hold, true) => Self::Release,
+            (phase, true) => phase,
+        }
+    }
+
+    /// Phase for the step after this one.
+    pub(crate) fn advanced(self) -> Self {
+        match self {
+            Self::Hold => Self::Hold,
+            Self::Release | Self::Released => Self::Released,
+        }
+    }
+}
+
+/// Per-slot targets of a `MorphTargets` for a pool of `capacity`, as `vec4<f32>` with w = 1, or
+/// all zero without targets.
+pub(crate) fn slot_targets(targets: &MorphTargets, capacity: u32) -> Vec<[f32; 4]> {
+    if targets.is_empty() {
+        return vec![[0.0; 4]; capacity as usize];
+    }
+    spread_positions(capacity)
+        .map(|position| {
+            let t = targets.target_at(position, capacity);
+            [t[0], t[1], t[2], 1.0]
+        })
+        .collect()
+}
+
+impl MorphConfig {
+    /// CPU reference of the `pull` kernel in morph.wgsl.
+    pub(crate) fn apply_reference(
+        &self,
+        particles: &mut [Particle],
+        slot_targets: &[[f32; 4]],
+        centroid: [f32; 3],
+        phase: MorphPhase,
+        dt: f32,
+    ) {
+        for (p, target) in particles.iter_mut().zip(slot_targets) {
+            if !p.is_alive() || target[3] == 0.0 {
+                continue;
+            }
+            match phase {
+                MorphPhase::Hold => {
+                    let offset = sub([target[0], target[1], target[2]], p.position);
+                    let accel = sub(
+                        mul_scalar(offset, self.stiffness),
+                        mul_scalar(p.velocity, self.damping),
+                    );
+                    p.velocity = add(p.velocity, mul_scalar(accel, dt));
+                }
+                MorphPhase::Release => {
+                    let outward = normalize_or_zero(sub(p.position, centroid));
+                    p.velocity = add(p.velocity, mul_scalar(outward, self.release_speed));
+                }
+                MorphPhase::Released => {}
+            }
+        }
+    }
+}
+
+/// Mirrors `MorphParams` in morph.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuMorphParams {
+    centroid: [f32; 3],
+    phase: u32,
+    stiffness: f32,
+    damping: f32,
+    release_speed: f32,
+    dt: f32,
+    particle_count: u32,
+    _pad0: [u32; 3],
+}
+
+/// GPU side of `ParticleSimMode::Morph`, encoded by `ParticleGpuSim` ahead of the update kernel
+/// while the morph holds or is being released.
+pub(crate) struct MorphPasses {
+    particle_count: u32,
+    centroid: [f32; 3],
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pull: wgpu::ComputePipeline,
+}
+
+impl MorphPasses {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particle_buffer: &wgpu::Buffer,
+        particle_count: u32,
+        targets: &MorphTargets,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.morph.params"),
+            size: size_of::<GpuMorphParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let target_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.morph.targets"),
+            size: (size_of::<[f32; 4]>() as u64 * particle_count as u64).max(16),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(
+            &target_buffer,
+            0,
+            cast_slice(&slot_targets(targets, particle_count)),
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.morph.bgl"),
+            entries: &[
+                layout_entry(0, storage_binding(false)),
+                layout_entry(1, storage_binding(true)),
+                layout_entry(2, uniform_binding()),
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.morph.bg"),
+            layout: &bind_group_layout,
+            entries: &[
+                (0, particle_buffer),
+                (1, &target_buffer),
+                (2, &params_buffer),
+            ]
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            }),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles.morph.shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/shaders/morph.wgsl"
+            )))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles.morph.pl"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pull = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("particles.morph.pull.pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "pull",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        });
+
+        Self {
+            particle_count,
+            centroid: targets.centroid(),
+            params_buffer,
+            bind_group,
+            pull,
+        }
+    }
+
+    /// Encodes nothing once released: the particles are plain ballistic ones then.
+    pub(crate) fn encode(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        config: &MorphConfig,
+        phase: MorphPhase,
+        dt: f32,
+    ) {
+        if phase == MorphPhase::Released {
+            return;
+        }
+        let params = GpuMorphParams {
+            centroid: self.centroid,
+            phase: phase as u32,
+            stiffness: config.stiffness,
+            damping: config.damping,
+            release_speed: config.release_speed,
+            dt,
+            particle_count: self.particle_count,
+            _pad0: [0; 3],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytes_of(&params));
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particles.morph.pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_pipeline(&self.pull);
+        pass.dispatch_workgroups(self.particle_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slot_targets, spread_position, spread_positions, MorphConfig, MorphTargets};
+    use crate::particles::math::{dot, sub};
+    use crate::particles::{
+        Burst, BurstSchedule, EmitterConfig, ForceConfig, ParticleSimConfig, ParticleSimMode,
+        ParticleState,
+    };
+
+    #[test]
+    fn outline_targets_are_evenly_spaced_and_low_slots_cover_the_shape() {
+        let square = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        let targets = MorphTargets::from_outlines(&[square], 8);
+        assert_eq!(
+            targets.points(),
+            [
+                [0.25, 0.0, 0.0],
+                [0.75, 0.0, 0.0],
+                [1.0, 0.25, 0.0],
+                [1.0, 0.75, 0.0],
+                [0.75, 1.0, 0.0],
+                [0.25, 1.0, 0.0],
+                [0.0, 0.75, 0.0],
+                [0.0, 0.25, 0.0],
+            ]
+        );
+        // The first 4 slots land on every other target, one per side of the square.
+        let first: Vec<[f32; 3]> = (0..4)
+            .map(|slot| targets.target_for_slot(slot, 8).unwrap())
+            .collect();
+        assert_eq!(
+            first,
+            [
+                [0.25, 0.0, 0.0],
+                [0.75, 1.0, 0.0],
+                [1.0, 0.25, 0.0],
+                [0.0, 0.75, 0.0]
+            ]
+        );
+        assert_eq!(MorphTargets::default().target_for_slot(0, 8), None);
+    }
+
+    #[test]
+    fn full_pools_occupy_every_target_at_any_size() {
+        for (capacity, count) in [(3, 3), (1000, 1000), (5000, 5000), (5000, 1234)] {
+            let targets = MorphTargets {
+                points: (0..count).map(|k| [k as f32, 0.0, 0.0]).collect(),
+            };
+            let mut occupied = vec![false; count];
+            for target in slot_targets(&targets, capacity) {
+                occupied[target[0] as usize] = true;
+            }
+            assert!(
+                occupied.iter().all(|&o| o),
+                "{capacity} slots, {count} targets"
+            );
+            // The per-slot lookup agrees with the bulk one.
+            for (slot, t) in slot_targets(&targets, capacity).iter().enumerate() {
+                assert_eq!(
+                    targets.target_for_slot(slot as u32, capacity),
+                    Some([t[0], t[1], t[2]])
+                );
+            }
+        }
+        for capacity in 1..=70 {
+            for (slot, position) in spread_positions(capacity).enumerate() {
+                assert_eq!(spread_position(slot as u32, capacity), Some(position));
+            }
+            assert_eq!(spread_position(capacity, capacity), None);
+        }
+    }
+
+    #[test]
+    fn particles_settle_on_their_targets_then_scatter_on_release() {
+        let vertices = [
+            [-1.0, -1.0, 0.0],
+            [1.0, -1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [-1.0, 1.0, 0.0],
+        ];
+        let targets = MorphTargets::from_mesh_surface(&vertices, &[[0, 1, 2], [0, 2, 3]], 256);
+        assert!(targets
+            .points()
+            .iter()
+            .all(|p| p[0].abs() <= 1.0 && p[1].abs() <= 1.0 && p[2] == 0.0));
+
+        let config = ParticleSimConfig {
+            max_particles: 128,
+            spawn_rate_per_second: 0.0,
+            lifetime_seconds: 1.0e6,
+            mode: ParticleSimMode::Morph(MorphConfig::default()),
+            ..ParticleSimConfig::default()
+        };
+        let emitter = EmitterConfig {
+            bursts: BurstSchedule::from_slice(&[Burst::once(0.0, 128)]),
+            ..EmitterConfig::default()
+        };
+        let force = ForceConfig::default();
+        let mut state = ParticleState::new(config);
+        state.set_morph_targets(&targets);
+        for _ in 0..240 {
+            state.step_reference(1.0 / 60.0, config, emitter, force);
+        }
+        for (slot, p) in state.particles.iter().enumerate() {
+            let offset = sub(
+                p.position,
+                targets.target_for_slot(slot as u32, 128).unwrap(),
+            );
+            assert!(
+                dot(offset, offset).sqrt() < 1e-3,
+                "slot {slot} off by {offset:?}"
+            );
+        }
+
+        let spread = |state: &ParticleState| {
+            let c = targets.centroid();
+            state
+                .particles
+                .iter()
+                .map(|p| dot(sub(p.position, c), sub(p.position, c)).sqrt())
+                .sum::<f32>()
+                / state.particles.len() as f32
+        };
+        let formed = spread(&state);
+        state.set_morph_released(true);
+        for _ in 0..30 {
+            state.step_reference(1.0 / 60.0, config, emitter, force);
+        }
+        assert!(spread(&state) > formed + 0.3);
+    }
+}
@@ -0,0 +1,161 @@
+use super::config::ForceConfig;
+
+/// Upper bound on `ForceConfig::noise_octaves`, shared with random.wgsl.
+pub const MAX_NOISE_OCTAVES: u32 = 6;
+
+/// `hash_u32`, `hash01` and `curl_noise` in WGSL, matching the CPU reference, for shaders that
+/// need the same randomness as the sim.
+pub const RANDOM_WGSL: &str =
+    include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/random.wgsl"));
+
+// Per-component offsets so the three potential channels are decorrelated, and the direction the
+// sample point drifts through noise space over time.
+const POTENTIAL_OFFSETS: [[f32; 3]; 3] = [
+    [0.0, 0.0, 0.0],
+    [31.416, -17.23, 47.853],
+    [-59.12, 83.71, 12.64],
+];
+const TIME_DRIFT: [f32; 3] = [0.31, 0.71, -0.53];
+
+/// Divergence-free curl of a three-channel value-noise potential, summed over octaves.
+/// Each octave contributes the curl of its own scaled potential, so the sum stays divergence-free.
+/// Mirrors `curl_noise` in random.wgsl; the result is not scaled by `noise_strength`.
+pub fn curl_noise(position: [f32; 3], time_seconds: f32, force: &ForceConfig) -> [f32; 3] {
+    curl_noise_field(
+        position,
+        time_seconds * force.noise_time_scale,
+        force.noise_frequency,
+        force.noise_octaves,
+    )
+}
+
+/// `curl_noise` with explicit parameters; `drift` is time already multiplied by the time scale.
+pub(crate) fn curl_noise_field(
+    position: [f32; 3],
+    drift: f32,
+    base_frequency: f32,
+    octaves: u32,
+) -> [f32; 3] {
+    let octaves = octaves.min(MAX_NOISE_OCTAVES);
+
+    let mut curl = [0.0f32; 3];
+    let mut frequency = base_frequency;
+    let mut amplitude = 1.0f32;
+    let mut total_amplitude = 0.0f32;
+    for octave in 0..octaves {
+        let mut gradients = [[0.0f32; 3]; 3];
+        for (channel, gradient) in gradients.iter_mut().enumerate() {
+            let offset = POTENTIAL_OFFSETS[channel];
+            let p = [
+                position[0] * frequency + offset[0] + TIME_DRIFT[0] * drift,
+                position[1] * frequency + offset[1] + TIME_DRIFT[1] * drift,
+                position[2] * frequency + offset[2] + TIME_DRIFT[2] * drift,
+            ];
+            *gradient = value_noise_gradient(p, octave * 3 + channel as u32);
+        }
+        // curl(psi) = (dpz/dy - dpy/dz, dpx/dz - dpz/dx, dpy/dx - dpx/dy)
+        curl[0] += amplitude * (gradients[2][1] - gradients[1][2]);
+        curl[1] += amplitude * (gradients[0][2] - gradients[2][0]);
+        curl[2] += amplitude * (gradients[1][0] - gradients[0][1]);
+
+        total_amplitude += amplitude;
+        frequency *= 2.0;
+        amplitude *= 0.5;
+    }
+
+    if total_amplitude <= 0.0 {
+        return [0.0; 3];
+    }
+    let norm = total_amplitude.recip();
+    [curl[0] * norm, curl[1] * norm, curl[2] * norm]
+}
+
+/// Analytic gradient of quintic-interpolated lattice value noise in [-1, 1].
+fn value_noise_gradient(p: [f32; 3], seed: u32) -> [f32; 3] {
+    let cell = [p[0].floor(), p[1].floor(), p[2].floor()];
+    let f = [p[0] - cell[0], p[1] - cell[1], p[2] - cell[2]];
+    let u = f.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+    let du = f.map(|t| 30.0 * t * t * (t * (t - 2.0) + 1.0));
+
+    let ix = cell[0] as i32;
+    let iy = cell[1] as i32;
+    let iz = cell[2] as i32;
+    let a = lattice_value(ix, iy, iz, seed);
+    let b = lattice_value(ix + 1, iy, iz, seed);
+    let c = lattice_value(ix, iy + 1, iz, seed);
+    let d = lattice_value(ix + 1, iy + 1, iz, seed);
+    let e = lattice_value(ix, iy, iz + 1, seed);
+    let f1 = lattice_value(ix + 1, iy, iz + 1, seed);
+    let g = lattice_value(ix, iy + 1, iz + 1, seed);
+    let h = lattice_value(ix + 1, iy + 1, iz + 1, seed);
+
+    let k1 = b - a;
+    let k2 = c - a;
+    let k3 = e - a;
+    let k4 = a - b - c + d;
+    let k5 = a - c - e + g;
+    let k6 = a - b - e + f1;
+    let k7 = -a + b + c - d + e - f1 - g + h;
+
+    [
+        du[0] * (k1 + k4 * u[1] + k6 * u[2] + k7 * u[1] * u[2]),
+        du[1] * (k2 + k5 * u[2] + k4 * u[0] + k7 * u[2] * u[0]),
+        du[2] * (k3 + k6 * u[0] + k5 * u[1] + k7 * u[0] * u[1]),
+    ]
+}
+
+fn lattice_value(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343);
+    h ^= (y as u32).wrapping_mul(0xd816_3841);
+    h ^= (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= seed.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^= h >> 15;
+    (h >> 8) as f32 * (2.0 / 16_777_215.0) - 1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::curl_noise;
+    use crate::particles::config::ForceConfig;
+
+    #[test]
+    fn curl_noise_is_divergence_free() {
+        let force = ForceConfig {
+            noise_frequency: 1.3,
+            noise_octaves: 3,
+            ..ForceConfig::default()
+        };
+        let h = 1e-2;
+        for i in 0..16 {
+            let p = [
+                i as f32 * 0.37 - 2.0,
+                i as f32 * 0.11,
+                1.0 - i as f32 * 0.23,
+            ];
+            let mut divergence = 0.0;
+            for axis in 0..3 {
+                let mut plus = p;
+                let mut minus = p;
+                plus[axis] += h;
+                minus[axis] -= h;
+                let delta =
+                    curl_noise(plus, 0.5, &force)[axis] - curl_noise(minus, 0.5, &force)[axis];
+                divergence += delta / (2.0 * h);
+            }
+            assert!(divergence.abs() < 0.05, "divergence {divergence} at {p:?}");
+        }
+    }
+
+    #[test]
+    fn curl_noise_changes_over_time() {
+        let force = ForceConfig::default();
+        let p = [0.2, -0.4, 0.1];
+        let a = curl_noise(p, 0.0, &force);
+        let b = curl_noise(p, 4.0, &force);
+        assert!(a.iter().zip(b).any(|(x, y)| (x - y).abs() > 1e-3));
+    }
+}
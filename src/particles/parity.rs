@@ -0,0 +1,323 @@
+use std::borrow::Cow;
+use std::mem::size_of;
+
+use bytemuck::{cast_slice, Pod, Zeroable};
+
+use super::compute::ParticleWorkgroup;
+use super::config::ParticleSimConfig;
+use super::gpu::{ParticleGpuError, ParticleGpuSim, ParticleStepInput};
+use super::math::{hash01, hash_u32};
+use super::noise::{curl_noise_field, RANDOM_WGSL};
+use super::readback::read_buffer_blocking;
+use super::simulation::{Particle, ParticleState};
+use super::spatial_grid::{layout_entry, storage_binding};
+
+const PROBE_WORKGROUP_SIZE: u32 = 64;
+const PROBE_OCTAVES: u32 = 3;
+const PROBE_FREQUENCY: f32 = 1.3;
+
+/// Largest per-component differences `ParityHarness` accepts between the CPU reference and the
+/// GPU. The defaults leave room for float rounding that compounds over a few hundred steps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParityTolerance {
+    pub position: f32,
+    pub velocity: f32,
+    pub age: f32,
+}
+
+impl Default for ParityTolerance {
+    fn default() -> Self {
+        Self {
+            position: 1e-4,
+            velocity: 1e-4,
+            age: 1e-5,
+        }
+    }
+}
+
+/// A slot whose two states differ beyond tolerance, or that is alive on one side only.
+#[derive(Debug, Clone, Copy)]
+pub struct ParityMismatch {
+    /// 1-based step after which the states were compared.
+    pub step: u32,
+    pub slot: u32,
+    pub cpu: Particle,
+    pub gpu: Particle,
+}
+
+/// Running comparison of every step so far. The maxima cover slots alive on both sides.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParityReport {
+    pub steps: u32,
+    /// Slots alive on both sides, summed over steps.
+    pub compared: usize,
+    pub max_position_error: f32,
+    pub max_velocity_error: f32,
+    pub max_age_error: f32,
+    pub mismatches: usize,
+    pub first_mismatch: Option<ParityMismatch>,
+}
+
+impl ParityReport {
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0
+    }
+
+    fn record(&mut self, tolerance: &ParityTolerance, slot: u32, cpu: Particle, gpu: Particle) {
+        let agrees = match (cpu.is_alive(), gpu.is_alive()) {
+            (false, false) => return,
+            (true, true) => {
+                let position = max_difference(cpu.position, gpu.position);
+                let velocity = max_difference(cpu.velocity, gpu.velocity);
+                let age = (cpu.age_seconds - gpu.age_seconds).abs();
+                self.compared += 1;
+                self.max_position_error = worst(self.max_position_error, position);
+                self.max_velocity_error = worst(self.max_velocity_error, velocity);
+                self.max_age_error = worst(self.max_age_error, age);
+                position <= tolerance.position
+                    && velocity <= tolerance.velocity
+                    && age <= tolerance.age
+            }
+            _ => false,
+        };
+        if !agrees {
+            self.mismatches += 1;
+            self.first_mismatch.get_or_insert(ParityMismatch {
+                step: self.steps,
+                slot,
+                cpu,
+                gpu,
+            });
+        }
+    }
+}
+
+/// Runs `ParticleState::step_reference` and `ParticleGpuSim` side by side from one config, so
+/// both draw the same spawn randoms from `ParticleSimConfig::seed`, and compares the whole pool
+/// slot by slot after every step. Works on any adapter, including software ones such as
+/// llvmpipe.
+///
+/// Slots freed in one step return to the GPU dead stack in scheduling order. When several
+/// particles die in a step and their slots are respawned, the sides can fill slots in different
+/// orders and report mismatches, so parity scenarios should outlive their particles' deaths or
+/// keep lifetimes longer than the run.
+pub struct ParityHarness {
+    config: ParticleSimConfig,
+    cpu: ParticleState,
+    gpu: ParticleGpuSim,
+    tolerance: ParityTolerance,
+    report: ParityReport,
+}
+
+impl ParityHarness {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        config: ParticleSimConfig,
+        tolerance: ParityTolerance,
+    ) -> Result<Self, ParticleGpuError> {
+        Ok(Self {
+            config,
+            cpu: ParticleState::new(config),
+            gpu: ParticleGpuSim::init(device, queue, config, ParticleWorkgroup::default())?,
+            tolerance,
+            report: ParityReport::default(),
+        })
+    }
+
+    /// CPU side, for setup such as colliders and volumes; make the same change on `gpu_mut`.
+    pub fn cpu_mut(&mut self) -> &mut ParticleState {
+        &mut self.cpu
+    }
+
+    pub fn gpu_mut(&mut self) -> &mut ParticleGpuSim {
+        &mut self.gpu
+    }
+
+    /// Steps both sides with `input`, reads the GPU pool back and folds the comparison into
+    /// the report.
+    pub fn step(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        input: ParticleStepInput,
+    ) -> Result<(), ParticleGpuError> {
+        self.gpu.step(device, queue, input);
+        self.cpu
+            .step_reference(input.dt_seconds, self.config, input.emitter, input.force);
+        let gpu = self
+            .gpu
+            .readback_debug_sample(device, queue, self.gpu.particle_count())?;
+        self.report.steps += 1;
+        for (slot, (cpu, gpu)) in self.cpu.particles.iter().zip(&gpu).enumerate() {
+            self.report.record(&self.tolerance, slot as u32, *cpu, *gpu);
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> ParityReport {
+        self.report
+    }
+}
+
+fn max_difference(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).fold(0.0, |max, i| worst(max, (a[i] - b[i]).abs()))
+}
+
+// NaN-propagating `max`, so a non-finite component on one side always exceeds the tolerance and
+// stays visible in the report.
+fn worst(a: f32, b: f32) -> f32 {
+    if a.is_nan() || b.is_nan() {
+        f32::NAN
+    } else {
+        a.max(b)
+    }
+}
+
+/// How the shared randomness evaluated by WGSL compares with the CPU reference.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RandomParity {
+    pub samples: u32,
+    /// `hash_u32` results that differ; the hash is integer-only, so any is a bug.
+    pub hash_mismatches: u32,
+    /// Largest component difference of `curl_noise`.
+    pub max_noise_error: f32,
+}
+
+/// Mirrors `RandomSample` in random_probe.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuRandomSample {
+    noise: [f32; 3],
+    hash: u32,
+}
+
+/// Evaluates `hash_u32` and `curl_noise` from `RANDOM_WGSL` for `samples` inputs on the GPU and
+/// compares them with the CPU functions the reference step uses.
+pub fn check_random_parity(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    samples: u32,
+) -> Result<RandomParity, ParticleGpuError> {
+    let samples = samples.clamp(1, u16::MAX as u32 * PROBE_WORKGROUP_SIZE);
+    let size = samples as u64 * size_of::<GpuRandomSample>() as u64;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particles.random_probe"),
+        size,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("particles.random_probe.bgl"),
+        entries: &[layout_entry(0, storage_binding(false))],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("particles.random_probe.bg"),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("particles.random_probe.shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(
+            RANDOM_WGSL.to_owned()
+                + include_str!(concat!(
+                    env!("CARGO_MANIFEST_DIR"),
+                    "/shaders/random_probe.wgsl"
+                )),
+        )),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("particles.random_probe.pl"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("particles.random_probe.pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "probe",
+        compilation_options: wgpu::PipelineCompilationOptions::default(),
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("particles.random_probe.encoder"),
+    });
+    {
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particles.random_probe.pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_pipeline(&pipeline);
+        pass.dispatch_workgroups(samples.div_ceil(PROBE_WORKGROUP_SIZE), 1, 1);
+    }
+    queue.submit(Some(encoder.finish()));
+
+    let bytes = read_buffer_blocking(device, queue, &buffer, size)?;
+    let gpu: &[GpuRandomSample] = cast_slice(&bytes);
+    let mut parity = RandomParity {
+        samples,
+        ..RandomParity::default()
+    };
+    for (i, sample) in (0..samples).zip(gpu) {
+        let (position, drift, hash_input) = probe_input(i);
+        if hash_u32(hash_input) != sample.hash {
+            parity.hash_mismatches += 1;
+        }
+        let noise = curl_noise_field(position, drift, PROBE_FREQUENCY, PROBE_OCTAVES);
+        parity.max_noise_error = worst(parity.max_noise_error, max_difference(noise, sample.noise));
+    }
+    Ok(parity)
+}
+
+/// Noise position, noise drift and hash input of probe sample `i`; mirrors `probe` in
+/// random_probe.wgsl.
+fn probe_input(i: u32) -> ([f32; 3], f32, u32) {
+    let position = [0, 1, 2].map(|axis| hash01(3 * i + axis) * 16.0 - 8.0);
+    (position, i as f32 * 0.01, i.wrapping_mul(2_654_435_761))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParityReport, ParityTolerance};
+    use crate::particles::Particle;
+
+    #[test]
+    fn report_flags_drift_lone_slots_and_non_finite_state() {
+        let tolerance = ParityTolerance::default();
+        let alive = Particle {
+            position: [1.0, 2.0, 3.0],
+            age_seconds: 0.5,
+            velocity: [0.0, 1.0, 0.0],
+            lifetime_seconds: 2.0,
+        };
+        let mut report = ParityReport {
+            steps: 1,
+            ..ParityReport::default()
+        };
+        report.record(&tolerance, 0, Particle::dead(), Particle::dead());
+        let close = Particle {
+            position: [1.0, 2.0 + 5e-5, 3.0],
+            ..alive
+        };
+        report.record(&tolerance, 1, alive, close);
+        assert!(report.passed());
+        assert_eq!(report.compared, 1);
+
+        report.record(&tolerance, 2, alive, Particle::dead());
+        let diverged = Particle {
+            velocity: [0.0, f32::NAN, 0.0],
+            ..alive
+        };
+        report.record(&tolerance, 3, alive, diverged);
+        assert_eq!(report.mismatches, 2);
+        assert!(report.max_velocity_error.is_nan());
+        assert_eq!(
+            report.first_mismatch.map(|m| (m.step, m.slot)),
+            Some((1, 2))
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::mpsc;
+
+use super::gpu::ParticleGpuError;
+
+type MapReceiver = mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>;
+
+/// Fixed set of reusable `MAP_READ` buffers. A slot is acquired, filled by a copy, mapped with
+/// `map_async` after submission and handed back once the map completes; nothing ever waits.
+pub(crate) struct StagingRing {
+    slots: Vec<StagingSlot>,
+}
+
+struct StagingSlot {
+    buffer: wgpu::Buffer,
+    pending: Option<(u64, MapReceiver)>,
+}
+
+impl StagingRing {
+    pub(crate) fn new(device: &wgpu::Device, label: &str, size: u64, len: usize) -> Self {
+        let slots = (0..len)
+            .map(|_| StagingSlot {
+                buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size,
+                    usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                pending: None,
+            })
+            .collect();
+        Self { slots }
+    }
+
+    /// Index of a slot that is neither mapped nor waiting on a map, if any.
+    pub(crate) fn acquire(&self) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.pending.is_none())
+    }
+
+    pub(crate) fn buffer(&self, slot: usize) -> &wgpu::Buffer {
+        &self.slots[slot].buffer
+    }
+
+    /// Must be called after the copy into `slot` has been submitted.
+    pub(crate) fn begin_map(&mut self, slot: usize, frame: u64) {
+        let (tx, rx) = mpsc::channel();
+        self.slots[slot]
+            .buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = tx.send(result);
+            });
+        self.slots[slot].pending = Some((frame, rx));
+    }
+
+    /// Polls the device without blocking and returns the newest completed slot's bytes.
+    /// Older completed slots are recycled without being reported.
+    pub(crate) fn poll_latest(
+        &mut self,
+        device: &wgpu::Device,
+    ) -> Result<Option<(u64, Vec<u8>)>, ParticleGpuError> {
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut latest: Option<(u64, Vec<u8>)> = None;
+        for slot in &mut self.slots {
+            let Some((frame, rx)) = &slot.pending else {
+                continue;
+            };
+            let frame = *frame;
+            match rx.try_recv() {
+                Ok(Ok(())) => {
+                    if latest.as_ref().is_none_or(|(newest, _)| frame > *newest) {
+                        let data = slot.buffer.slice(..).get_mapped_range().to_vec();
+                        latest = Some((frame, data));
+                    }
+                    slot.buffer.unmap();
+                    slot.pending = None;
+                }
+                Ok(Err(_)) => {
+                    slot.pending = None;
+                    return Err(ParticleGpuError::MapFailed);
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    slot.pending = None;
+                    return Err(ParticleGpuError::ChannelClosed);
+                }
+            }
+        }
+        Ok(latest)
+    }
+}
+
+/// Copies the first `size` bytes of `source` into a one-off staging buffer and waits for the
+/// map; for debug and test readbacks only.
+pub(crate) fn read_buffer_blocking(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    source: &wgpu::Buffer,
+    size: u64,
+) -> Result<Vec<u8>, ParticleGpuError> {
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particles.debug.staging"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("particles.debug.copy.encoder"),
+    });
+    encoder.copy_buffer_to_buffer(source, 0, &staging, 0, size);
+    queue.submit(Some(encoder.finish()));
+
+    let slice = staging.slice(..);
+    let (tx, rx) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = tx.send(result);
+    });
+
+    #[allow(deprecated)]
+    {
+        device.poll(wgpu::Maintain::Wait);
+    }
+
+    let map_result = rx.recv().map_err(|_| ParticleGpuError::ChannelClosed)?;
+    map_result.map_err(|_| ParticleGpuError::MapFailed)?;
+
+    let data = slice.get_mapped_range().to_vec();
+    staging.unmap();
+    Ok(data)
+}
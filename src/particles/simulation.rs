@@ -1,8 +1,26 @@
-use super::config::{EmitterConfig, ForceConfig, ParticleSimConfig};
+use super::attributes::ParticleAttributes;
+use super::collision::{resolve_collisions, ColliderList, CollisionOutcome, SdfVolume};
+use super::config::{EmitterConfig, ForceConfig, ParticleSimConfig, ParticleSimMode};
+use super::constraints::{ConstraintSolver, ConstraintTopology};
+use super::curves::{LifetimeCurves, LifetimeLut};
+use super::emitter::{sample_emitter, spawn_randoms};
+use super::math::{add, mul_scalar};
+use super::morph::{slot_targets, MorphPhase, MorphTargets};
+use super::noise::curl_noise;
+use super::snapshot::{ParticleSnapshot, SnapshotConfig, SnapshotError};
+use super::spawn_image::SpawnImage;
+use super::sph::SphConfig;
+use super::sub_emitter::{ParticleEvent, ParticleEventKind, SubEmitterConfig};
+use super::trails::TrailHistory;
+use super::vector_field::VectorFieldVolume;
+use super::watchdog::NonFinitePolicy;
 use bytemuck::{Pod, Zeroable};
 
+/// Smallest share of the pool worth a thread of its own in `step_reference`.
+const MIN_PARTICLES_PER_WORKER: usize = 8192;
+
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
 pub struct Particle {
     pub position: [f32; 3],
     pub age_seconds: f32,
@@ -28,19 +46,223 @@ impl Particle {
 #[derive(Debug)]
 pub struct ParticleState {
     pub particles: Vec<Particle>,
+    /// Every attribute for every slot, whatever the schema; the GPU stores only enabled ones.
+    pub attributes: Vec<ParticleAttributes>,
     spawn_accumulator: f32,
+    step_index: u64,
+    elapsed_seconds: f32,
+    // Stack of dead slot indices, the CPU twin of the GPU dead list.
+    free_slots: Vec<u32>,
+    // Alive slots, kept in step with `free_slots` rather than rescanned.
+    alive: usize,
+    worker_threads: usize,
+    // Per slot: spawned by the sub-emitter, so it raises no events.
+    sub_emitted: Vec<bool>,
+    events: Vec<ParticleEvent>,
+    sph_densities: Vec<f32>,
+    constraints: Option<ConstraintSolver>,
+    // Per-slot targets and their centroid, set by `set_morph_targets`.
+    morph_targets: Option<(Vec<[f32; 4]>, [f32; 3])>,
+    morph_phase: MorphPhase,
+    trails: Option<TrailHistory>,
+    colliders: ColliderList,
+    sdf_volume: Option<SdfVolume>,
+    vector_field: Option<VectorFieldVolume>,
+    spawn_image: SpawnImage,
+    lifetime_lut: LifetimeLut,
+    non_finite_count: u32,
 }
 
 impl ParticleState {
     pub fn new(config: ParticleSimConfig) -> Self {
         Self {
             particles: vec![Particle::dead(); config.max_particles as usize],
+            attributes: vec![ParticleAttributes::default(); config.max_particles as usize],
             spawn_accumulator: 0.0,
+            step_index: 0,
+            elapsed_seconds: 0.0,
+            free_slots: (0..config.max_particles).rev().collect(),
+            alive: 0,
+            worker_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            sub_emitted: vec![false; config.max_particles as usize],
+            events: Vec::new(),
+            sph_densities: Vec::new(),
+            constraints: None,
+            morph_targets: None,
+            morph_phase: MorphPhase::Hold,
+            trails: config
+                .trails
+                .map(|trails| TrailHistory::new(&trails, config.max_particles)),
+            colliders: ColliderList::new(),
+            sdf_volume: None,
+            vector_field: None,
+            spawn_image: SpawnImage::uniform(),
+            lifetime_lut: LifetimeCurves::default().bake(),
+            non_finite_count: 0,
+        }
+    }
+
+    /// Colliders resolved after every integration step.
+    pub fn set_colliders(&mut self, colliders: ColliderList) {
+        self.colliders = colliders;
+    }
+
+    pub fn colliders(&self) -> &ColliderList {
+        &self.colliders
+    }
+
+    /// Empties the pool and places `topology`'s particles in slots `0..topology.len()`, at rest
+    /// and never expiring; the emitter spawns into the remaining slots. Its constraints are
+    /// solved in `ParticleSimMode::Pbd`. Returns `false`, changing nothing, when the topology
+    /// does not fit in the pool.
+    pub fn set_constraints(&mut self, topology: &ConstraintTopology) -> bool {
+        let capacity = self.particles.len();
+        if topology.len() > capacity {
+            return false;
+        }
+        self.particles.fill(Particle::dead());
+        for (slot, particle) in topology.particles().enumerate() {
+            self.particles[slot] = particle;
+        }
+        self.attributes.fill(ParticleAttributes::default());
+        self.sub_emitted.fill(false);
+        self.free_slots = (topology.len() as u32..capacity as u32).rev().collect();
+        self.alive = topology.len();
+        self.constraints = Some(ConstraintSolver::new(topology));
+        if let Some(trails) = &mut self.trails {
+            trails.clear();
         }
+        true
+    }
+
+    /// Assigns every pool slot its `MorphTargets::target_for_slot`, pulled towards in
+    /// `ParticleSimMode::Morph`, and holds the morph again if it was released.
+    pub fn set_morph_targets(&mut self, targets: &MorphTargets) {
+        let capacity = self.particles.len() as u32;
+        self.morph_targets = Some((slot_targets(targets, capacity), targets.centroid()));
+        self.morph_phase = MorphPhase::Hold;
+    }
+
+    /// Releasing lets the next step push particles away from the targets' centroid, after
+    /// which they move ballistically; un-releasing pulls them back to their targets.
+    pub fn set_morph_released(&mut self, released: bool) {
+        self.morph_phase = self.morph_phase.with_released(released);
+    }
+
+    /// Volume sampled by `ColliderShape::SdfVolume` colliders.
+    pub fn set_sdf_volume(&mut self, volume: Option<SdfVolume>) {
+        self.sdf_volume = volume;
+    }
+
+    /// Volume sampled by `ForceField::VectorVolume` fields.
+    pub fn set_vector_field(&mut self, volume: Option<VectorFieldVolume>) {
+        self.vector_field = volume;
+    }
+
+    /// Density and colors sampled by `EmitterShape::Image`, for the emitter and sub-emitter;
+    /// `None` spawns uniformly over the image rectangle.
+    pub fn set_spawn_image(&mut self, image: Option<SpawnImage>) {
+        self.spawn_image = image.unwrap_or_else(SpawnImage::uniform);
     }
 
+    /// Bakes `curves` into the LUT the step samples, exactly as the GPU sim does.
+    pub fn set_lifetime_curves(&mut self, curves: &LifetimeCurves) {
+        self.lifetime_lut = curves.bake();
+    }
+
+    pub fn lifetime_lut(&self) -> &LifetimeLut {
+        &self.lifetime_lut
+    }
+
+    /// Counted as slots are spawned and freed; writes to `particles` that kill or revive a
+    /// particle directly are not seen.
     pub fn alive_count(&self) -> usize {
-        self.particles.iter().filter(|p| p.is_alive()).count()
+        self.alive
+    }
+
+    /// Threads `step_reference` may split the particle update across, defaulting to the
+    /// available parallelism. Results are identical for any count; small pools use fewer.
+    pub fn set_worker_threads(&mut self, threads: usize) {
+        self.worker_threads = threads.max(1);
+    }
+
+    pub fn worker_threads(&self) -> usize {
+        self.worker_threads
+    }
+
+    /// Particles the last step found with a non-finite position or velocity, whatever
+    /// `ParticleSimConfig::non_finite_policy` then did with them.
+    pub fn non_finite_count(&self) -> u32 {
+        self.non_finite_count
+    }
+
+    /// Events raised during the last step, in slot order; empty without a sub-emitter.
+    pub fn events(&self) -> &[ParticleEvent] {
+        &self.events
+    }
+
+    /// Per-slot SPH densities computed at the start of the last step; empty if it was not an SPH
+    /// step.
+    pub fn sph_densities(&self) -> &[f32] {
+        &self.sph_densities
+    }
+
+    /// Positions recorded at the end of each step; `None` without `ParticleSimConfig::trails`.
+    pub fn trail_history(&self) -> Option<&TrailHistory> {
+        self.trails.as_ref()
+    }
+
+    /// Simulated time so far; drives the curl-noise field.
+    pub fn elapsed_seconds(&self) -> f32 {
+        self.elapsed_seconds
+    }
+
+    /// Number of steps taken so far, counted like `ParticleGpuSim::step_index`.
+    pub fn step_index(&self) -> u64 {
+        self.step_index
+    }
+
+    /// Captures the pool and spawn state; `config` must be the one this state is stepped with.
+    pub fn snapshot(&self, config: &ParticleSimConfig) -> ParticleSnapshot {
+        let layout = config.attributes.layout();
+        let mut record = vec![0u8; layout.stride_bytes as usize];
+        ParticleSnapshot {
+            config: SnapshotConfig::new(config),
+            step_index: self.step_index,
+            elapsed_seconds: self.elapsed_seconds,
+            spawn_accumulator: self.spawn_accumulator,
+            particles: self.particles.clone(),
+            // Round-tripped through the layout so both backends capture the same values.
+            attributes: self
+                .attributes
+                .iter()
+                .map(|values| {
+                    layout.encode(values, &mut record);
+                    layout.decode(&record)
+                })
+                .collect(),
+            sub_emitted: self.sub_emitted.clone(),
+            free_slots: self.free_slots.clone(),
+        }
+    }
+
+    /// Replaces the pool and spawn state with `snapshot`'s, which must hold as many slots.
+    /// Trails restart empty; constraints and morph targets are kept.
+    pub fn restore(&mut self, snapshot: &ParticleSnapshot) -> Result<(), SnapshotError> {
+        snapshot.check_capacity(self.particles.len())?;
+        self.particles.clone_from(&snapshot.particles);
+        self.attributes.clone_from(&snapshot.attributes);
+        self.sub_emitted.clone_from(&snapshot.sub_emitted);
+        self.free_slots.clone_from(&snapshot.free_slots);
+        self.alive = snapshot.particles.iter().filter(|p| p.is_alive()).count();
+        self.spawn_accumulator = snapshot.spawn_accumulator;
+        self.step_index = snapshot.step_index;
+        self.elapsed_seconds = snapshot.elapsed_seconds;
+        self.events.clear();
+        if let Some(trails) = &mut self.trails {
+            trails.clear();
+        }
+        Ok(())
     }
 
     pub fn step_reference(
@@ -50,79 +272,334 @@ impl ParticleState {
         emitter: EmitterConfig,
         force: ForceConfig,
     ) {
-        let clamped_dt = dt.max(0.0).min(1.0 / 15.0);
-
-        for particle in &mut self.particles {
-            if !particle.is_alive() {
-                continue;
-            }
-
-            particle.age_seconds += clamped_dt;
-            if !particle.is_alive() {
-                continue;
+        let clamped_dt = dt.clamp(0.0, 1.0 / 15.0);
+        let time = self.elapsed_seconds;
+        self.events.clear();
+        self.non_finite_count = 0;
+        let sph = match config.mode {
+            ParticleSimMode::Sph(sph) => Some(sph),
+            _ => None,
+        };
+        match &sph {
+            Some(sph) => self.sph_densities = sph.apply_reference(&mut self.particles, clamped_dt),
+            None => self.sph_densities.clear(),
+        }
+        // Boids and held morphs are steered here and skip the forces below.
+        let mut steered = false;
+        if let ParticleSimMode::Boids(boids) = config.mode {
+            boids.steer_reference(&mut self.particles, &force.fields, clamped_dt);
+            steered = true;
+        }
+        if let (ParticleSimMode::Morph(morph), Some((targets, centroid))) =
+            (config.mode, &self.morph_targets)
+        {
+            morph.apply_reference(
+                &mut self.particles,
+                targets,
+                *centroid,
+                self.morph_phase,
+                clamped_dt,
+            );
+            steered = self.morph_phase == MorphPhase::Hold;
+            self.morph_phase = self.morph_phase.advanced();
+        }
+        let pbd = match (config.mode, &self.constraints) {
+            (ParticleSimMode::Pbd(pbd), Some(solver)) => {
+                Some((pbd, self.particles[..solver.particle_count()].to_vec()))
             }
+            _ => None,
+        };
 
-            let to_attractor = sub(force.attractor, particle.position);
-            let attraction = mul_scalar(normalize_or_zero(to_attractor), force.attractor_strength);
-            let accel = add(force.gravity, attraction);
-            particle.velocity = add(
-                mul_scalar(particle.velocity, config.drag),
-                mul_scalar(accel, clamped_dt),
-            );
-            particle.position = add(particle.position, mul_scalar(particle.velocity, clamped_dt));
+        let update = ParticleUpdate {
+            config: &config,
+            emitter: &emitter,
+            force: &force,
+            dt: clamped_dt,
+            time,
+            steered,
+            sph,
+            lifetime_lut: &self.lifetime_lut,
+            colliders: &self.colliders,
+            sdf_volume: self.sdf_volume.as_ref(),
+            vector_field: self.vector_field.as_ref(),
+            spawn_image: &self.spawn_image,
+        };
+        // Chunks are merged in slot order, so events and freed slots come out exactly as a
+        // single pass would produce them, whatever the thread count.
+        let workers = self
+            .worker_threads
+            .min(self.particles.len().div_ceil(MIN_PARTICLES_PER_WORKER))
+            .max(1);
+        let chunk_len = self.particles.len().div_ceil(workers).max(1);
+        let chunks = self
+            .particles
+            .chunks_mut(chunk_len)
+            .zip(self.attributes.chunks_mut(chunk_len))
+            .zip(self.sub_emitted.chunks_mut(chunk_len))
+            .enumerate()
+            .map(|(c, ((particles, attributes), sub_emitted))| {
+                (c * chunk_len, particles, attributes, sub_emitted)
+            });
+        let outcomes: Vec<ChunkOutcome> = if workers == 1 {
+            chunks
+                .map(|(first, particles, attributes, sub_emitted)| {
+                    update.chunk(first, particles, attributes, sub_emitted)
+                })
+                .collect()
+        } else {
+            std::thread::scope(|scope| {
+                let update = &update;
+                let handles: Vec<_> = chunks
+                    .map(|(first, particles, attributes, sub_emitted)| {
+                        scope.spawn(move || update.chunk(first, particles, attributes, sub_emitted))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                    })
+                    .collect()
+            })
+        };
+        let max_events = config
+            .sub_emitter
+            .map_or(0, |sub| sub.max_events_per_step as usize);
+        for outcome in outcomes {
+            self.alive -= outcome.freed.len();
+            self.free_slots.extend(outcome.freed);
+            self.events.extend(outcome.events);
+            self.non_finite_count += outcome.non_finite;
         }
+        self.events.truncate(max_events);
 
         self.spawn_accumulator += config.spawn_rate_per_second * clamped_dt;
         let spawn_count = self.spawn_accumulator.floor() as usize;
         self.spawn_accumulator -= spawn_count as f32;
-        self.spawn(spawn_count, config, emitter, force);
+        let burst_count = emitter
+            .bursts
+            .count_between(self.elapsed_seconds, self.elapsed_seconds + clamped_dt);
+        self.spawn(spawn_count + burst_count as usize, config, emitter);
+        if let Some(sub) = &config.sub_emitter {
+            self.spawn_sub_emitted(sub, config.seed);
+        }
+        if let (Some((pbd, previous)), Some(solver)) = (pbd, &self.constraints) {
+            solver.solve_reference(&mut self.particles, &previous, &pbd, clamped_dt);
+        }
+        if let Some(trails) = &mut self.trails {
+            trails.record(&self.particles);
+        }
+        self.step_index += 1;
+        self.elapsed_seconds += clamped_dt;
     }
 
-    fn spawn(
-        &mut self,
-        mut count: usize,
-        config: ParticleSimConfig,
-        emitter: EmitterConfig,
-        force: ForceConfig,
-    ) {
-        if count == 0 {
-            return;
+    /// Second spawn pass, after the primary emitter, claiming `particles_per_event` dead slots per
+    /// event in event order; mirrors `begin_sub_emit`/`sub_emit` in particles_update.wgsl.
+    fn spawn_sub_emitted(&mut self, sub: &SubEmitterConfig, seed: u32) {
+        let per_event = sub.particles_per_event as usize;
+        let requested = self.events.len() * per_event;
+        for k in 0..requested {
+            let Some(slot) = self.free_slots.pop() else {
+                break;
+            };
+            let (particle, attributes) = sub.spawn(
+                &self.events[k / per_event],
+                &self.spawn_image,
+                seed,
+                slot,
+                (requested - k) as u32,
+            );
+            self.particles[slot as usize] = particle;
+            self.attributes[slot as usize] = attributes;
+            self.sub_emitted[slot as usize] = true;
+            self.alive += 1;
         }
+    }
 
-        for (i, particle) in self.particles.iter_mut().enumerate() {
-            if count == 0 {
+    fn spawn(&mut self, mut count: usize, config: ParticleSimConfig, emitter: EmitterConfig) {
+        while count > 0 {
+            let Some(slot) = self.free_slots.pop() else {
                 break;
+            };
+
+            (
+                self.particles[slot as usize],
+                self.attributes[slot as usize],
+            ) = spawned(&emitter, &self.spawn_image, config, slot, count as u32);
+            self.sub_emitted[slot as usize] = false;
+            self.alive += 1;
+            count -= 1;
+        }
+    }
+}
+
+/// Freed slots, events and watchdog count of one `ParticleUpdate::chunk`, in slot order.
+#[derive(Default)]
+struct ChunkOutcome {
+    freed: Vec<u32>,
+    events: Vec<ParticleEvent>,
+    non_finite: u32,
+}
+
+/// Everything the per-particle part of `step_reference` reads, shared by its worker threads.
+struct ParticleUpdate<'a> {
+    config: &'a ParticleSimConfig,
+    emitter: &'a EmitterConfig,
+    force: &'a ForceConfig,
+    dt: f32,
+    time: f32,
+    steered: bool,
+    sph: Option<SphConfig>,
+    lifetime_lut: &'a LifetimeLut,
+    colliders: &'a ColliderList,
+    sdf_volume: Option<&'a SdfVolume>,
+    vector_field: Option<&'a VectorFieldVolume>,
+    spawn_image: &'a SpawnImage,
+}
+
+impl ParticleUpdate<'_> {
+    /// Everything but drag, `t` seconds into the step; mirrors `acceleration` in
+    /// particles_update.wgsl.
+    fn acceleration(&self, position: [f32; 3], velocity: [f32; 3], t: f32) -> [f32; 3] {
+        let force = self.force;
+        let time = self.time + t;
+        let fields = force
+            .fields
+            .acceleration_in(position, velocity, time, self.vector_field);
+        let swirl = mul_scalar(curl_noise(position, time, force), force.noise_strength);
+        add(add(force.gravity, fields), swirl)
+    }
+
+    /// Ages, moves and collides the particles of slots `first_slot..`; mirrors
+    /// `update_particle` in particles_update.wgsl.
+    fn chunk(
+        &self,
+        first_slot: usize,
+        particles: &mut [Particle],
+        attributes: &mut [ParticleAttributes],
+        sub_emitted: &mut [bool],
+    ) -> ChunkOutcome {
+        let mut out = ChunkOutcome::default();
+        for (k, particle) in particles.iter_mut().enumerate() {
+            let slot = (first_slot + k) as u32;
+            if !particle.is_alive() {
+                continue;
             }
-            if particle.is_alive() {
+            let sub_emitter = self.config.sub_emitter.as_ref().filter(|_| !sub_emitted[k]);
+
+            particle.age_seconds += self.dt;
+            if !particle.is_alive() {
+                out.freed.push(slot);
+                if let Some(sub) = sub_emitter {
+                    sub.record(&mut out.events, ParticleEventKind::Death, particle);
+                }
                 continue;
             }
 
-            // Deterministic pseudo-random sequence for reproducible test runs.
-            let s = hash01(i as u32 + (count as u32 * 17));
-            let t = hash01(i as u32 + (count as u32 * 73));
-            let u = hash01(i as u32 + (count as u32 * 193));
-
-            let angle = s * std::f32::consts::TAU;
-            let radial = emitter.radius * t.sqrt();
-            let offset = [
-                radial * angle.cos(),
-                radial * angle.sin(),
-                (u - 0.5) * emitter.radius,
-            ];
-            let direction = normalize_or_zero(add(offset, [0.001, 0.001, 0.001]));
-            let noise_push = mul_scalar(direction, force.noise_strength);
-
-            *particle = Particle {
-                position: add(emitter.center, offset),
-                age_seconds: 0.0,
-                velocity: add(mul_scalar(direction, emitter.initial_speed), noise_push),
-                lifetime_seconds: config.lifetime_seconds,
-            };
-            count -= 1;
+            let speed = self
+                .lifetime_lut
+                .sample(particle.age_seconds / particle.lifetime_seconds)
+                .speed;
+            if self.steered {
+                particle.position = add(
+                    particle.position,
+                    mul_scalar(particle.velocity, speed * self.dt),
+                );
+            } else {
+                (particle.position, particle.velocity) = self.config.integrator.step(
+                    particle.position,
+                    particle.velocity,
+                    self.dt,
+                    self.config.drag,
+                    speed,
+                    |x, v, t| self.acceleration(x, v, t),
+                );
+            }
+
+            match resolve_collisions(
+                self.colliders,
+                self.sdf_volume,
+                particle.position,
+                particle.velocity,
+            ) {
+                CollisionOutcome::Free => {}
+                CollisionOutcome::Bounced {
+                    position,
+                    velocity,
+                    impact_speed,
+                } => {
+                    particle.position = position;
+                    particle.velocity = velocity;
+                    if let Some(sub) = sub_emitter {
+                        if impact_speed > 0.0 && impact_speed >= sub.min_impact_speed {
+                            sub.record(&mut out.events, ParticleEventKind::Collision, particle);
+                        }
+                    }
+                }
+                CollisionOutcome::Killed => {
+                    particle.age_seconds = particle.lifetime_seconds;
+                    out.freed.push(slot);
+                    if let Some(sub) = sub_emitter {
+                        sub.record(&mut out.events, ParticleEventKind::Collision, particle);
+                    }
+                    continue;
+                }
+            }
+            if let Some(sph) = &self.sph {
+                sph.contain(&mut particle.position, &mut particle.velocity);
+            }
+            let finite = |v: [f32; 3]| v.iter().all(|c| c.is_finite());
+            if !finite(particle.position) || !finite(particle.velocity) {
+                out.non_finite += 1;
+                match self.config.non_finite_policy {
+                    NonFinitePolicy::Count => {}
+                    NonFinitePolicy::Kill => {
+                        particle.age_seconds = particle.lifetime_seconds;
+                        out.freed.push(slot);
+                        continue;
+                    }
+                    NonFinitePolicy::Respawn => {
+                        // Remaining count 0 is a stream regular spawns never draw from.
+                        (*particle, attributes[k]) =
+                            spawned(self.emitter, self.spawn_image, *self.config, slot, 0);
+                        sub_emitted[k] = false;
+                        continue;
+                    }
+                }
+            }
+            attributes[k].advance(self.dt);
         }
+        out
     }
 }
 
+/// Particle and attributes spawned into `slot` by the primary emitter with `remaining` spawns
+/// still owed; mirrors `spawn_primary` in particles_update.wgsl.
+fn spawned(
+    emitter: &EmitterConfig,
+    spawn_image: &SpawnImage,
+    config: ParticleSimConfig,
+    slot: u32,
+    remaining: u32,
+) -> (Particle, ParticleAttributes) {
+    // Deterministic pseudo-random sequence for reproducible test runs.
+    let sample = sample_emitter(
+        emitter,
+        spawn_image,
+        spawn_randoms(config.seed, slot, remaining),
+    );
+    let particle = Particle {
+        position: sample.position,
+        age_seconds: 0.0,
+        velocity: mul_scalar(sample.direction, emitter.initial_speed),
+        lifetime_seconds: config.lifetime_seconds,
+    };
+    let attributes = ParticleAttributes::spawn(emitter, sample.tint, config.seed, slot, remaining);
+    (particle, attributes)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct SimulationClock {
     pub fixed_dt_seconds: f32,
@@ -152,37 +629,10 @@ impl SimulationClock {
     }
 }
 
-fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
-    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
-}
-
-fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
-    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
-}
-
-fn mul_scalar(v: [f32; 3], s: f32) -> [f32; 3] {
-    [v[0] * s, v[1] * s, v[2] * s]
-}
-
-fn normalize_or_zero(v: [f32; 3]) -> [f32; 3] {
-    let len_sq = v[0] * v[0] + v[1] * v[1] + v[2] * v[2];
-    if len_sq <= 1e-8 {
-        return [0.0, 0.0, 0.0];
-    }
-    mul_scalar(v, len_sq.sqrt().recip())
-}
-
-fn hash01(seed: u32) -> f32 {
-    let mut x = seed.wrapping_mul(747_796_405).wrapping_add(2_891_336_453);
-    x ^= x >> 16;
-    x = x.wrapping_mul(224_682_2519);
-    x ^= x >> 13;
-    (x as f32) / (u32::MAX as f32)
-}
-
 #[cfg(test)]
 mod tests {
     use super::{EmitterConfig, ForceConfig, ParticleSimConfig, ParticleState, SimulationClock};
+    use crate::particles::{Burst, BurstSchedule, EmitterDirection, SubEmitterConfig};
 
     #[test]
     fn fixed_clock_caps_steps() {
@@ -191,6 +641,120 @@ mod tests {
         assert_eq!(steps, 8);
     }
 
+    #[test]
+    fn free_list_recycles_expired_slots() {
+        let config = ParticleSimConfig {
+            max_particles: 16,
+            spawn_rate_per_second: 600.0,
+            lifetime_seconds: 0.05,
+            ..ParticleSimConfig::default()
+        };
+        let mut state = ParticleState::new(config);
+        for _ in 0..30 {
+            state.step_reference(
+                1.0 / 60.0,
+                config,
+                EmitterConfig::default(),
+                ForceConfig::default(),
+            );
+            assert_eq!(
+                state.alive_count() + state.free_slots.len(),
+                config.max_particles as usize
+            );
+            let scanned = state.particles.iter().filter(|p| p.is_alive()).count();
+            assert_eq!(state.alive_count(), scanned);
+        }
+        assert!(state.alive_count() > 0);
+    }
+
+    #[test]
+    fn worker_threads_do_not_change_results() {
+        // Short lifetimes and a sub-emitter, so slots are freed and events raised in every chunk.
+        let config = ParticleSimConfig {
+            max_particles: 3 * super::MIN_PARTICLES_PER_WORKER as u32,
+            spawn_rate_per_second: 600_000.0,
+            lifetime_seconds: 0.03,
+            sub_emitter: Some(SubEmitterConfig {
+                max_events_per_step: 1500,
+                ..SubEmitterConfig::default()
+            }),
+            ..ParticleSimConfig::default()
+        };
+        let (emitter, force) = (EmitterConfig::default(), ForceConfig::default());
+        let mut states = [1, 2, 3].map(|threads| {
+            let mut state = ParticleState::new(config);
+            state.set_worker_threads(threads);
+            state
+        });
+        let mut most_events = 0;
+        for _ in 0..8 {
+            for state in &mut states {
+                state.step_reference(1.0 / 120.0, config, emitter, force);
+            }
+            let [serial, rest @ ..] = &states;
+            most_events = most_events.max(serial.events().len());
+            for state in rest {
+                assert_eq!(state.particles, serial.particles);
+                assert_eq!(state.free_slots, serial.free_slots);
+                assert_eq!(state.events(), serial.events());
+                assert_eq!(state.alive_count(), serial.alive_count());
+            }
+        }
+        assert_eq!(most_events, 1500);
+    }
+
+    #[test]
+    fn bursts_spawn_exact_counts_at_their_step() {
+        let config = ParticleSimConfig {
+            max_particles: 512,
+            spawn_rate_per_second: 0.0,
+            ..ParticleSimConfig::default()
+        };
+        let emitter = EmitterConfig {
+            bursts: BurstSchedule::from_slice(&[Burst::once(0.1, 200)]),
+            ..EmitterConfig::default()
+        };
+        let mut state = ParticleState::new(config);
+        let mut alive = Vec::new();
+        for _ in 0..12 {
+            state.step_reference(1.0 / 60.0, config, emitter, ForceConfig::default());
+            alive.push(state.alive_count());
+        }
+        assert_eq!(alive[5], 0);
+        assert_eq!(alive[6], 200);
+        assert_eq!(alive[11], 200);
+    }
+
+    #[test]
+    fn death_events_spawn_children_that_raise_no_events() {
+        let config = ParticleSimConfig {
+            max_particles: 64,
+            spawn_rate_per_second: 0.0,
+            lifetime_seconds: 0.1,
+            sub_emitter: Some(SubEmitterConfig {
+                particles_per_event: 3,
+                lifetime_seconds: 0.1,
+                ..SubEmitterConfig::default()
+            }),
+            ..ParticleSimConfig::default()
+        };
+        let emitter = EmitterConfig {
+            bursts: BurstSchedule::from_slice(&[Burst::once(0.0, 10)]),
+            ..EmitterConfig::default()
+        };
+        let mut state = ParticleState::new(config);
+        let mut history = Vec::new();
+        for _ in 0..16 {
+            state.step_reference(1.0 / 60.0, config, emitter, ForceConfig::default());
+            history.push((state.alive_count(), state.events().len()));
+        }
+        // Parents die on step 7 and are replaced by three children each in that same step.
+        assert_eq!(history[5], (10, 0));
+        assert_eq!(history[6], (30, 10));
+        assert_eq!(history[13], (0, 0));
+        assert_eq!(history[15], (0, 0));
+    }
+
     #[test]
     fn reference_step_spawns_particles() {
         let config = ParticleSimConfig {
@@ -207,4 +771,42 @@ mod tests {
         );
         assert!(state.alive_count() > 0);
     }
+
+    #[test]
+    fn drag_decays_per_second_at_any_step_rate() {
+        let config = ParticleSimConfig {
+            max_particles: 4,
+            spawn_rate_per_second: 0.0,
+            drag: 1.5,
+            ..ParticleSimConfig::default()
+        };
+        let emitter = EmitterConfig {
+            direction: EmitterDirection::Along([1.0, 0.0, 0.0]),
+            initial_speed: 2.0,
+            bursts: BurstSchedule::from_slice(&[Burst::once(0.0, 1)]),
+            ..EmitterConfig::default()
+        };
+        let force = ForceConfig {
+            gravity: [0.0; 3],
+            noise_strength: 0.0,
+            ..ForceConfig::default()
+        };
+        for hz in [60.0, 120.0, 240.0] {
+            let mut clock = SimulationClock::new(1.0 / hz);
+            let mut state = ParticleState::new(config);
+            for _ in 0..60 {
+                for _ in 0..clock.consume_steps(1.0 / 60.0) {
+                    state.step_reference(clock.fixed_dt_seconds, config, emitter, force);
+                }
+            }
+            let p = state.particles[0];
+            let expected = 2.0 * (-config.drag * p.age_seconds).exp();
+            assert!(
+                (p.velocity[0] / expected - 1.0).abs() < 1e-4,
+                "{hz} Hz: speed {} after {} s, expected {expected}",
+                p.velocity[0],
+                p.age_seconds
+            );
+        }
+    }
 }
@@ -0,0 +1,452 @@
+use std::io::{Read, Write};
+use std::mem::size_of;
+
+use super::attributes::{ParticleAttributeSchema, ParticleAttributes};
+use super::config::ParticleSimConfig;
+use super::integrator::Integrator;
+use super::simulation::Particle;
+use super::watchdog::NonFinitePolicy;
+
+/// First bytes of every snapshot file.
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"PTCLSNAP";
+/// Format written by `ParticleSnapshot::write_to`; older or newer files are rejected.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    /// The file ended early or holds inconsistent slot lists.
+    Corrupt,
+    /// The snapshot's pool size differs from the sim restoring it.
+    CapacityMismatch {
+        snapshot: u32,
+        sim: u32,
+    },
+    /// The snapshot's attribute schema differs from the GPU sim restoring it.
+    AttributeSchemaMismatch,
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "snapshot I/O failed: {}", err),
+            Self::BadMagic => write!(f, "not a particle snapshot"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "snapshot version {} is not supported (expected {})",
+                version, SNAPSHOT_VERSION
+            ),
+            Self::Corrupt => write!(f, "snapshot is truncated or inconsistent"),
+            Self::CapacityMismatch { snapshot, sim } => write!(
+                f,
+                "snapshot holds {} particles but the sim holds {}",
+                snapshot, sim
+            ),
+            Self::AttributeSchemaMismatch => {
+                write!(f, "snapshot attribute schema differs from the sim's")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// The `ParticleSimConfig` fields a snapshot records: the pool shape plus the settings that
+/// decide how the saved state evolves. Emitters, forces, modes and volumes stay with the scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SnapshotConfig {
+    pub max_particles: u32,
+    pub attributes: ParticleAttributeSchema,
+    pub spawn_rate_per_second: f32,
+    pub lifetime_seconds: f32,
+    pub drag: f32,
+    pub integrator: Integrator,
+    pub seed: u32,
+    pub non_finite_policy: NonFinitePolicy,
+}
+
+impl SnapshotConfig {
+    pub fn new(config: &ParticleSimConfig) -> Self {
+        Self {
+            max_particles: config.max_particles,
+            attributes: config.attributes,
+            spawn_rate_per_second: config.spawn_rate_per_second,
+            lifetime_seconds: config.lifetime_seconds,
+            drag: config.drag,
+            integrator: config.integrator,
+            seed: config.seed,
+            non_finite_policy: config.non_finite_policy,
+        }
+    }
+
+    /// `base` with the recorded fields overwritten, for creating a sim to restore into.
+    pub fn apply_to(&self, base: ParticleSimConfig) -> ParticleSimConfig {
+        ParticleSimConfig {
+            max_particles: self.max_particles,
+            attributes: self.attributes,
+            spawn_rate_per_second: self.spawn_rate_per_second,
+            lifetime_seconds: self.lifetime_seconds,
+            drag: self.drag,
+            integrator: self.integrator,
+            seed: self.seed,
+            non_finite_policy: self.non_finite_policy,
+            ..base
+        }
+    }
+}
+
+/// Pool contents plus the clock and spawn state needed to continue a run exactly, taken from
+/// `ParticleState::snapshot` or `ParticleGpuSim::snapshot` and restorable into either. Spawn
+/// randoms are hashed from `SnapshotConfig::seed`, the slot and the spawn count, so with the
+/// accumulator and the free-slot order they are all the RNG state there is. Trails, constraint
+/// topologies, morph targets and SPH densities are not recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleSnapshot {
+    pub config: SnapshotConfig,
+    pub step_index: u64,
+    pub elapsed_seconds: f32,
+    pub spawn_accumulator: f32,
+    /// One per slot, alive or not.
+    pub particles: Vec<Particle>,
+    /// One per slot; only the attributes enabled by `config.attributes` are stored, the rest
+    /// read back as `ParticleAttributes::default()`.
+    pub attributes: Vec<ParticleAttributes>,
+    /// Per slot: spawned by the sub-emitter, so it raises no events.
+    pub sub_emitted: Vec<bool>,
+    /// Dead slots as a stack, the next spawn taking the last one.
+    pub free_slots: Vec<u32>,
+}
+
+impl ParticleSnapshot {
+    /// Writes the versioned little-endian file: header, config, clock and spawn state, then the
+    /// particle, attribute, sub-emitted and free-slot arrays.
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        let layout = self.config.attributes.layout();
+        let stride = layout.stride_bytes as usize;
+        let mut out = Vec::with_capacity(
+            64 + self.particles.len() * (size_of::<Particle>() + stride + 1)
+                + 4 * self.free_slots.len(),
+        );
+        out.extend_from_slice(&SNAPSHOT_MAGIC);
+        for word in [
+            SNAPSHOT_VERSION,
+            self.config.max_particles,
+            size_of::<Particle>() as u32,
+            layout.stride_bytes,
+            schema_bits(&self.config.attributes),
+            self.config.attributes.user_floats,
+            self.config.spawn_rate_per_second.to_bits(),
+            self.config.lifetime_seconds.to_bits(),
+            self.config.drag.to_bits(),
+            self.config.integrator.gpu_kind(),
+            self.config.seed,
+            self.config.non_finite_policy.gpu_kind(),
+        ] {
+            out.extend_from_slice(&word.to_le_bytes());
+        }
+        out.extend_from_slice(&self.step_index.to_le_bytes());
+        out.extend_from_slice(&self.elapsed_seconds.to_le_bytes());
+        out.extend_from_slice(&self.spawn_accumulator.to_le_bytes());
+        out.extend_from_slice(&(self.free_slots.len() as u32).to_le_bytes());
+
+        for particle in &self.particles {
+            for word in particle
+                .position
+                .iter()
+                .chain([&particle.age_seconds])
+                .chain(&particle.velocity)
+                .chain([&particle.lifetime_seconds])
+            {
+                out.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        let mut record = vec![0u8; stride];
+        for values in &self.attributes {
+            layout.encode(values, &mut record);
+            out.extend_from_slice(&record);
+        }
+        out.extend(self.sub_emitted.iter().map(|&flag| flag as u8));
+        for slot in &self.free_slots {
+            out.extend_from_slice(&slot.to_le_bytes());
+        }
+        writer.write_all(&out)
+    }
+
+    /// Reads a file written by `write_to`, checking that the free slots are exactly the dead
+    /// ones.
+    pub fn read_from(mut reader: impl Read) -> Result<Self, SnapshotError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        let mut input = SnapshotReader { bytes: &bytes };
+        if input.take(SNAPSHOT_MAGIC.len())? != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = input.u32()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let max_particles = input.u32()?;
+        let particle_stride = input.u32()?;
+        let attribute_stride = input.u32()?;
+        let attributes = schema_from_bits(input.u32()?, input.u32()?);
+        let layout = attributes.layout();
+        if particle_stride != size_of::<Particle>() as u32
+            || attribute_stride != layout.stride_bytes
+        {
+            return Err(SnapshotError::Corrupt);
+        }
+        let config = SnapshotConfig {
+            max_particles,
+            attributes,
+            spawn_rate_per_second: input.f32()?,
+            lifetime_seconds: input.f32()?,
+            drag: input.f32()?,
+            integrator: Integrator::from_gpu_kind(input.u32()?).ok_or(SnapshotError::Corrupt)?,
+            seed: input.u32()?,
+            non_finite_policy: NonFinitePolicy::from_gpu_kind(input.u32()?)
+                .ok_or(SnapshotError::Corrupt)?,
+        };
+        let step_index = u64::from_le_bytes(input.array()?);
+        let elapsed_seconds = input.f32()?;
+        let spawn_accumulator = input.f32()?;
+        let free_count = input.u32()?;
+
+        let capacity = max_particles as usize;
+        if free_count > max_particles {
+            return Err(SnapshotError::Corrupt);
+        }
+        // The header is untrusted: size the arrays it promises before allocating any of them.
+        let payload = (size_of::<Particle>() + attribute_stride as usize + 1)
+            .checked_mul(capacity)
+            .and_then(|bytes| bytes.checked_add(4 * free_count as usize));
+        if payload != Some(input.bytes.len()) {
+            return Err(SnapshotError::Corrupt);
+        }
+        let mut particles = Vec::with_capacity(capacity);
+        for _ in 0..capacity {
+            let mut words = [0.0f32; 8];
+            for word in &mut words {
+                *word = input.f32()?;
+            }
+            particles.push(Particle {
+                position: [words[0], words[1], words[2]],
+                age_seconds: words[3],
+                velocity: [words[4], words[5], words[6]],
+                lifetime_seconds: words[7],
+            });
+        }
+        let attributes = if attribute_stride == 0 {
+            vec![ParticleAttributes::default(); capacity]
+        } else {
+            input
+                .take(capacity * attribute_stride as usize)?
+                .chunks_exact(attribute_stride as usize)
+                .map(|record| layout.decode(record))
+                .collect()
+        };
+        let sub_emitted = input
+            .take(capacity)?
+            .iter()
+            .map(|&flag| flag != 0)
+            .collect();
+        let free_slots = (0..free_count)
+            .map(|_| input.u32())
+            .collect::<Result<Vec<u32>, SnapshotError>>()?;
+
+        let snapshot = Self {
+            config,
+            step_index,
+            elapsed_seconds,
+            spawn_accumulator,
+            particles,
+            attributes,
+            sub_emitted,
+            free_slots,
+        };
+        if !snapshot.free_slots_match_dead_slots() {
+            return Err(SnapshotError::Corrupt);
+        }
+        Ok(snapshot)
+    }
+
+    /// Fails unless the snapshot's pool holds `capacity` slots.
+    pub(crate) fn check_capacity(&self, capacity: usize) -> Result<(), SnapshotError> {
+        if self.particles.len() != capacity {
+            return Err(SnapshotError::CapacityMismatch {
+                snapshot: self.particles.len() as u32,
+                sim: capacity as u32,
+            });
+        }
+        Ok(())
+    }
+
+    // Both backends rely on every dead slot being free exactly once.
+    fn free_slots_match_dead_slots(&self) -> bool {
+        let mut listed = vec![false; self.particles.len()];
+        for &slot in &self.free_slots {
+            match listed.get_mut(slot as usize) {
+                Some(seen) if !*seen => *seen = true,
+                _ => return false,
+            }
+        }
+        self.particles
+            .iter()
+            .zip(&listed)
+            .all(|(particle, &free)| particle.is_alive() != free)
+    }
+}
+
+fn schema_bits(schema: &ParticleAttributeSchema) -> u32 {
+    schema.color as u32
+        | (schema.size as u32) << 1
+        | (schema.rotation as u32) << 2
+        | (schema.seed as u32) << 3
+}
+
+fn schema_from_bits(bits: u32, user_floats: u32) -> ParticleAttributeSchema {
+    ParticleAttributeSchema {
+        color: bits & 1 != 0,
+        size: bits & 2 != 0,
+        rotation: bits & 4 != 0,
+        seed: bits & 8 != 0,
+        user_floats,
+    }
+}
+
+struct SnapshotReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() < len {
+            return Err(SnapshotError::Corrupt);
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], SnapshotError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn f32(&mut self) -> Result<f32, SnapshotError> {
+        self.array().map(f32::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParticleSnapshot, SnapshotConfig, SnapshotError, SNAPSHOT_VERSION};
+    use crate::particles::{
+        EmitterConfig, ForceConfig, ParticleAttributeSchema, ParticleSimConfig, ParticleState,
+    };
+
+    fn config() -> ParticleSimConfig {
+        ParticleSimConfig {
+            max_particles: 256,
+            spawn_rate_per_second: 3000.0,
+            lifetime_seconds: 0.05,
+            seed: 11,
+            attributes: ParticleAttributeSchema {
+                color: true,
+                rotation: true,
+                ..ParticleAttributeSchema::default()
+            },
+            ..ParticleSimConfig::default()
+        }
+    }
+
+    #[test]
+    fn restored_reference_continues_like_the_original() {
+        let config = config();
+        let (emitter, force) = (EmitterConfig::default(), ForceConfig::default());
+        let mut original = ParticleState::new(config);
+        // Long enough for deaths to reorder the free slots.
+        for _ in 0..30 {
+            original.step_reference(1.0 / 120.0, config, emitter, force);
+        }
+
+        let mut bytes = Vec::new();
+        original.snapshot(&config).write_to(&mut bytes).unwrap();
+        let snapshot = ParticleSnapshot::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(snapshot, original.snapshot(&config));
+        let applied = snapshot.config.apply_to(ParticleSimConfig::default());
+        assert_eq!(SnapshotConfig::new(&applied), snapshot.config);
+
+        let mut restored = ParticleState::new(config);
+        restored.restore(&snapshot).unwrap();
+        for _ in 0..30 {
+            original.step_reference(1.0 / 120.0, config, emitter, force);
+            restored.step_reference(1.0 / 120.0, config, emitter, force);
+        }
+        assert_eq!(restored.step_index(), 60);
+        assert_eq!(restored.particles, original.particles);
+        assert_eq!(restored.attributes, original.attributes);
+    }
+
+    #[test]
+    fn rejects_foreign_truncated_and_mismatched_snapshots() {
+        let config = config();
+        let mut state = ParticleState::new(config);
+        state.step_reference(
+            1.0 / 60.0,
+            config,
+            EmitterConfig::default(),
+            ForceConfig::default(),
+        );
+        let mut bytes = Vec::new();
+        state.snapshot(&config).write_to(&mut bytes).unwrap();
+
+        let read = |bytes: &[u8]| ParticleSnapshot::read_from(bytes);
+        assert!(matches!(read(&bytes[1..]), Err(SnapshotError::BadMagic)));
+        assert!(matches!(
+            read(&bytes[..bytes.len() - 1]),
+            Err(SnapshotError::Corrupt)
+        ));
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(SNAPSHOT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read(&newer),
+            Err(SnapshotError::UnsupportedVersion(v)) if v == SNAPSHOT_VERSION + 1
+        ));
+        // The last free slot listed twice.
+        let mut duplicated = bytes.clone();
+        let len = duplicated.len();
+        let previous = duplicated[len - 8..len - 4].to_vec();
+        duplicated[len - 4..].copy_from_slice(&previous);
+        assert!(matches!(read(&duplicated), Err(SnapshotError::Corrupt)));
+
+        // A forged pool size far beyond the bytes present is rejected before allocating.
+        let mut forged = bytes.clone();
+        forged[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(read(&forged), Err(SnapshotError::Corrupt)));
+
+        let smaller = ParticleSimConfig {
+            max_particles: 128,
+            ..config
+        };
+        assert!(matches!(
+            ParticleState::new(smaller).restore(&read(&bytes).unwrap()),
+            Err(SnapshotError::CapacityMismatch {
+                snapshot: 256,
+                sim: 128
+            })
+        ));
+    }
+}
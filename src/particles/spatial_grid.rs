@@ -0,0 +1,506 @@
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::mem::size_of;
+
+use bytemuck::{bytes_of, cast_slice, Pod, Zeroable};
+
+use super::gpu::ParticleGpuError;
+use super::math::{dot, sub};
+use super::readback::read_buffer_blocking;
+use super::simulation::Particle;
+
+/// Neighbor functions appended to `SpatialHashGrid::query_wgsl`.
+const SPATIAL_GRID_WGSL: &str = include_str!(concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/shaders/spatial_grid.wgsl"
+));
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Cells of the 3x3x3 block searched around a query point.
+const NEIGHBOR_CELLS: u32 = 27;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialGridConfig {
+    /// Edge of a cubic cell; neighbor queries reach at most this far.
+    pub cell_size: f32,
+    /// Buckets cells are hashed into. Distinct cells may share a bucket, which only costs extra
+    /// candidates, so size it well above the number of occupied cells.
+    pub table_size: u32,
+}
+
+impl Default for SpatialGridConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 0.05,
+            table_size: 1 << 16,
+        }
+    }
+}
+
+/// Alive particles bucketed by hashed cell, as built on the GPU by `SpatialHashGridGpu`.
+/// Slots of bucket `b` are `sorted_slots[cell_ranges[b][0]..cell_ranges[b][1]]`; here they are in
+/// ascending slot order, on the GPU in no particular order.
+#[derive(Debug, Clone)]
+pub struct SpatialHashGrid {
+    config: SpatialGridConfig,
+    cell_ranges: Vec<[u32; 2]>,
+    sorted_slots: Vec<u32>,
+}
+
+impl SpatialHashGrid {
+    /// CPU reference of `SpatialHashGridGpu::encode_build`.
+    pub fn build(config: SpatialGridConfig, particles: &[Particle]) -> Self {
+        let mut grid = Self {
+            config: SpatialGridConfig {
+                table_size: config.table_size.max(1),
+                ..config
+            },
+            cell_ranges: Vec::new(),
+            sorted_slots: Vec::new(),
+        };
+        let buckets: Vec<Option<u32>> = particles
+            .iter()
+            .map(|p| {
+                p.is_alive()
+                    .then(|| grid.bucket_of(grid.cell_of(p.position)))
+            })
+            .collect();
+
+        let mut counts = vec![0u32; grid.config.table_size as usize];
+        for bucket in buckets.iter().flatten() {
+            counts[*bucket as usize] += 1;
+        }
+        let mut start = 0;
+        grid.cell_ranges = counts
+            .iter()
+            .map(|&n| {
+                start += n;
+                [start - n, start]
+            })
+            .collect();
+
+        let mut cursor: Vec<u32> = grid.cell_ranges.iter().map(|range| range[0]).collect();
+        grid.sorted_slots = vec![0; start as usize];
+        for (slot, bucket) in buckets.iter().enumerate() {
+            if let Some(bucket) = bucket {
+                let at = &mut cursor[*bucket as usize];
+                grid.sorted_slots[*at as usize] = slot as u32;
+                *at += 1;
+            }
+        }
+        grid
+    }
+
+    pub fn config(&self) -> SpatialGridConfig {
+        self.config
+    }
+
+    /// `[start, end)` into `sorted_slots` per bucket.
+    pub fn cell_ranges(&self) -> &[[u32; 2]] {
+        &self.cell_ranges
+    }
+
+    pub fn sorted_slots(&self) -> &[u32] {
+        &self.sorted_slots
+    }
+
+    /// Mirrors `grid_cell_of` in spatial_grid.wgsl.
+    pub fn cell_of(&self, position: [f32; 3]) -> [i32; 3] {
+        position.map(|x| (x / self.config.cell_size).floor() as i32)
+    }
+
+    /// Mirrors `grid_hash` in spatial_grid.wgsl.
+    pub fn bucket_of(&self, cell: [i32; 3]) -> u32 {
+        let [x, y, z] = cell.map(|c| c as u32);
+        (x.wrapping_mul(73_856_093) ^ y.wrapping_mul(19_349_663) ^ z.wrapping_mul(83_492_791))
+            % self.config.table_size
+    }
+
+    /// Calls `visit` once for every alive particle within `radius` of `position`, which must not
+    /// exceed `cell_size`. Walks the same buckets, in the same order, as `grid_neighbor_range`.
+    pub fn for_each_neighbor(
+        &self,
+        particles: &[Particle],
+        position: [f32; 3],
+        radius: f32,
+        mut visit: impl FnMut(u32),
+    ) {
+        let center = self.cell_of(position);
+        let mut seen = [0u32; NEIGHBOR_CELLS as usize];
+        for n in 0..NEIGHBOR_CELLS {
+            let offset = [n % 3, n / 3 % 3, n / 9].map(|o| o as i32 - 1);
+            let bucket = self.bucket_of([0, 1, 2].map(|i| center[i].wrapping_add(offset[i])));
+            seen[n as usize] = bucket;
+            if seen[..n as usize].contains(&bucket) {
+                continue;
+            }
+            let [start, end] = self.cell_ranges[bucket as usize];
+            for &slot in &self.sorted_slots[start as usize..end as usize] {
+                let d = sub(particles[slot as usize].position, position);
+                if dot(d, d) <= radius * radius {
+                    visit(slot);
+                }
+            }
+        }
+    }
+
+    /// WGSL declaring the read-only query bindings `grid`, `grid_cell_ranges` and
+    /// `grid_sorted_slots` at `first_binding..first_binding + 3` of `group`, followed by
+    /// `grid_neighbor_range` and friends. Bind them with `SpatialHashGridGpu::query_entries`.
+    pub fn query_wgsl(group: u32, first_binding: u32) -> String {
+        grid_bindings_wgsl(group, first_binding, "read") + SPATIAL_GRID_WGSL
+    }
+}
+
+fn grid_bindings_wgsl(group: u32, first_binding: u32, access: &str) -> String {
+    let mut source = String::new();
+    let _ = write!(
+        source,
+        "// Generated by `SpatialHashGrid::query_wgsl`; mirrors `GpuGridParams` in spatial_grid.rs.\n\
+         struct GridParams {{\n  cell_size : f32,\n  table_size : u32,\n  \
+         particle_count : u32,\n  _pad0 : u32,\n}}\n\n\
+         @group({group}) @binding({params})\nvar<uniform> grid : GridParams;\n\n\
+         @group({group}) @binding({ranges})\n\
+         var<storage, {access}> grid_cell_ranges : array<vec2<u32>>;\n\n\
+         @group({group}) @binding({slots})\n\
+         var<storage, {access}> grid_sorted_slots : array<u32>;\n\n",
+        params = first_binding,
+        ranges = first_binding + 1,
+        slots = first_binding + 2,
+    );
+    source
+}
+
+/// Mirrors `GridParams` in the generated grid WGSL.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuGridParams {
+    cell_size: f32,
+    table_size: u32,
+    particle_count: u32,
+    _pad0: u32,
+}
+
+/// GPU builder of `SpatialHashGrid` over a particle buffer, rebuilt by `encode_build` whenever
+/// positions change. Other kernels read it through `SpatialHashGrid::query_wgsl`.
+pub struct SpatialHashGridGpu {
+    config: SpatialGridConfig,
+    particle_count: u32,
+    params_buffer: wgpu::Buffer,
+    cell_ranges_buffer: wgpu::Buffer,
+    sorted_slots_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipelines: GridPipelines,
+}
+
+struct GridPipelines {
+    clear_counts: wgpu::ComputePipeline,
+    count: wgpu::ComputePipeline,
+    scan: wgpu::ComputePipeline,
+    scatter: wgpu::ComputePipeline,
+}
+
+impl SpatialHashGridGpu {
+    /// `particle_buffer` holds `particle_count` `Particle`s, e.g.
+    /// `ParticleGpuSim::particle_buffer`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particle_buffer: &wgpu::Buffer,
+        particle_count: u32,
+        config: SpatialGridConfig,
+    ) -> Self {
+        let config = SpatialGridConfig {
+            table_size: config.table_size.max(1),
+            ..config
+        };
+        let slot_bytes = |stride: usize| (stride as u64 * particle_count as u64).max(16);
+        let table_bytes = |stride: usize| stride as u64 * config.table_size as u64;
+        let storage = |label: &str, size: u64| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            })
+        };
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("spatial_grid.params"),
+            size: size_of::<GpuGridParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        queue.write_buffer(
+            &params_buffer,
+            0,
+            bytes_of(&GpuGridParams {
+                cell_size: config.cell_size,
+                table_size: config.table_size,
+                particle_count,
+                _pad0: 0,
+            }),
+        );
+        let cell_ranges_buffer = storage(
+            "spatial_grid.cell_ranges",
+            table_bytes(size_of::<[u32; 2]>()),
+        );
+        let sorted_slots_buffer =
+            storage("spatial_grid.sorted_slots", slot_bytes(size_of::<u32>()));
+        let cell_counts_buffer = storage("spatial_grid.cell_counts", table_bytes(size_of::<u32>()));
+        let slot_entries_buffer = storage(
+            "spatial_grid.slot_entries",
+            slot_bytes(size_of::<[u32; 2]>()),
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("spatial_grid.build.bgl"),
+            entries: &[
+                layout_entry(0, uniform_binding()),
+                layout_entry(1, storage_binding(false)),
+                layout_entry(2, storage_binding(false)),
+                layout_entry(3, storage_binding(true)),
+                layout_entry(4, storage_binding(false)),
+                layout_entry(5, storage_binding(false)),
+            ],
+        });
+        let mut entries = buffer_entries(
+            0,
+            [&params_buffer, &cell_ranges_buffer, &sorted_slots_buffer],
+        )
+        .to_vec();
+        entries.extend(buffer_entries(
+            3,
+            [particle_buffer, &cell_counts_buffer, &slot_entries_buffer],
+        ));
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("spatial_grid.build.bg"),
+            layout: &bind_group_layout,
+            entries: &entries,
+        });
+
+        Self {
+            config,
+            particle_count,
+            params_buffer,
+            cell_ranges_buffer,
+            sorted_slots_buffer,
+            bind_group,
+            pipelines: create_pipelines(device, &bind_group_layout),
+        }
+    }
+
+    pub fn config(&self) -> SpatialGridConfig {
+        self.config
+    }
+
+    /// Layout entries matching `SpatialHashGrid::query_wgsl(_, first_binding)`.
+    pub fn query_layout_entries(first_binding: u32) -> [wgpu::BindGroupLayoutEntry; 3] {
+        [
+            layout_entry(first_binding, uniform_binding()),
+            layout_entry(first_binding + 1, storage_binding(true)),
+            layout_entry(first_binding + 2, storage_binding(true)),
+        ]
+    }
+
+    /// Bind group entries for `query_layout_entries(first_binding)`.
+    pub fn query_entries(&self, first_binding: u32) -> [wgpu::BindGroupEntry<'_>; 3] {
+        buffer_entries(
+            first_binding,
+            [
+                &self.params_buffer,
+                &self.cell_ranges_buffer,
+                &self.sorted_slots_buffer,
+            ],
+        )
+    }
+
+    /// Rebuilds the grid from the particle buffer's current contents.
+    pub fn encode_build(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.particle_count == 0 {
+            return;
+        }
+        let slot_groups = self.particle_count.div_ceil(WORKGROUP_SIZE);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("spatial_grid.build.pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_pipeline(&self.pipelines.clear_counts);
+        pass.dispatch_workgroups(self.config.table_size.div_ceil(WORKGROUP_SIZE), 1, 1);
+        pass.set_pipeline(&self.pipelines.count);
+        pass.dispatch_workgroups(slot_groups, 1, 1);
+        pass.set_pipeline(&self.pipelines.scan);
+        pass.dispatch_workgroups(1, 1, 1);
+        pass.set_pipeline(&self.pipelines.scatter);
+        pass.dispatch_workgroups(slot_groups, 1, 1);
+    }
+
+    /// Blocking copy of the bucket ranges and sorted slots; `sorted_slots` is trimmed to the
+    /// particles actually inserted.
+    pub fn readback_debug_tables(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<(Vec<[u32; 2]>, Vec<u32>), ParticleGpuError> {
+        let ranges_bytes = read_buffer_blocking(
+            device,
+            queue,
+            &self.cell_ranges_buffer,
+            self.cell_ranges_buffer.size(),
+        )?;
+        let cell_ranges: Vec<[u32; 2]> = cast_slice(&ranges_bytes).to_vec();
+        let inserted = cell_ranges.last().map_or(0, |range| range[1]);
+        if inserted == 0 {
+            return Ok((cell_ranges, Vec::new()));
+        }
+        let slots_bytes = read_buffer_blocking(
+            device,
+            queue,
+            &self.sorted_slots_buffer,
+            inserted as u64 * size_of::<u32>() as u64,
+        )?;
+        Ok((cell_ranges, cast_slice(&slots_bytes).to_vec()))
+    }
+}
+
+fn create_pipelines(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> GridPipelines {
+    let shader_source = grid_bindings_wgsl(0, 0, "read_write")
+        + SPATIAL_GRID_WGSL
+        + include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/shaders/spatial_grid_build.wgsl"
+        ));
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("spatial_grid.build.shader"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(shader_source)),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("spatial_grid.build.pl"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    let create_pipeline = |entry_point: &str| {
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(&format!("spatial_grid.{entry_point}.pipeline")),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point,
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        })
+    };
+    GridPipelines {
+        clear_counts: create_pipeline("clear_counts"),
+        count: create_pipeline("count"),
+        scan: create_pipeline("scan"),
+        scatter: create_pipeline("scatter"),
+    }
+}
+
+/// `buffers` bound to consecutive bindings starting at `first_binding`.
+fn buffer_entries<'a>(
+    first_binding: u32,
+    buffers: [&'a wgpu::Buffer; 3],
+) -> [wgpu::BindGroupEntry<'a>; 3] {
+    let mut binding = first_binding;
+    buffers.map(|buffer| {
+        binding += 1;
+        wgpu::BindGroupEntry {
+            binding: binding - 1,
+            resource: buffer.as_entire_binding(),
+        }
+    })
+}
+
+pub(super) fn layout_entry(binding: u32, ty: wgpu::BindingType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty,
+        count: None,
+    }
+}
+
+pub(super) fn uniform_binding() -> wgpu::BindingType {
+    wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    }
+}
+
+pub(super) fn storage_binding(read_only: bool) -> wgpu::BindingType {
+    wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Storage { read_only },
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpatialGridConfig, SpatialHashGrid};
+    use crate::particles::math::{dot, hash01, sub};
+    use crate::particles::Particle;
+
+    fn scattered_particles(count: u32) -> Vec<Particle> {
+        (0..count)
+            .map(|i| {
+                let mut p = Particle::dead();
+                if i % 7 != 0 {
+                    p.position = [0, 1, 2].map(|axis| hash01(i * 3 + axis) * 2.0 - 1.0);
+                    p.age_seconds = 0.0;
+                    p.lifetime_seconds = 1.0;
+                }
+                p
+            })
+            .collect()
+    }
+
+    #[test]
+    fn neighbor_queries_match_brute_force() {
+        let particles = scattered_particles(2_000);
+        // A tiny table forces shared buckets, which must neither drop nor repeat neighbors.
+        let config = SpatialGridConfig {
+            cell_size: 0.2,
+            table_size: 61,
+        };
+        let grid = SpatialHashGrid::build(config, &particles);
+        assert_eq!(
+            grid.sorted_slots().len(),
+            particles.iter().filter(|p| p.is_alive()).count()
+        );
+
+        for query in [[0.0; 3], [0.55, -0.9, 0.31], [-1.0, 1.0, 0.99]] {
+            let mut found = Vec::new();
+            grid.for_each_neighbor(&particles, query, 0.2, |slot| found.push(slot));
+            found.sort_unstable();
+            let expected: Vec<u32> = (0..particles.len() as u32)
+                .filter(|&i| {
+                    let p = &particles[i as usize];
+                    let d = sub(p.position, query);
+                    p.is_alive() && dot(d, d) <= 0.04
+                })
+                .collect();
+            assert!(!expected.is_empty());
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn buckets_list_their_slots_in_ascending_order() {
+        let particles = scattered_particles(300);
+        let grid = SpatialHashGrid::build(SpatialGridConfig::default(), &particles);
+        for (bucket, &[start, end]) in grid.cell_ranges().iter().enumerate() {
+            let slots = &grid.sorted_slots()[start as usize..end as usize];
+            assert!(slots.windows(2).all(|w| w[0] < w[1]));
+            for &slot in slots {
+                let cell = grid.cell_of(particles[slot as usize].position);
+                assert_eq!(grid.bucket_of(cell), bucket as u32);
+            }
+        }
+        let source = SpatialHashGrid::query_wgsl(2, 4);
+        assert!(source.contains("@group(2) @binding(6)\nvar<storage, read> grid_sorted_slots"));
+    }
+}
@@ -0,0 +1,225 @@
+/// wgpu's default 2D texture limit. `SpawnImage::from_rgba8` accepts widths up to it and heights
+/// below it, since the CDF texture has a row more than the image.
+pub const MAX_SPAWN_IMAGE_SIZE: u32 = 8192;
+
+// Largest f32 below 1, so a random of exactly 1 still lands before the final CDF entry.
+const BELOW_ONE: f32 = 1.0 - f32::EPSILON / 2.0;
+
+/// Which pixel channel weights the spawn density of a `SpawnImage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnImageWeight {
+    /// Rec. 709 luminance of the color channels, ignoring alpha.
+    Luminance,
+    /// Alpha only, for coverage masks such as the web demo's mask layer.
+    Alpha,
+}
+
+/// Spawn density and colors for `EmitterShape::Image`, with the CDFs used to pick a pixel in
+/// proportion to its weight: a marginal CDF over rows, then a conditional CDF within the row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnImage {
+    width: u32,
+    height: u32,
+    // Pixel colors in [0, 1], row 0 first.
+    texels: Vec<[f32; 4]>,
+    // Per row: cumulative weight along the row, normalized to end at 1.
+    row_cdfs: Vec<f32>,
+    // Cumulative row weight, normalized to end at 1.
+    marginal_cdf: Vec<f32>,
+}
+
+impl SpawnImage {
+    /// Wraps tightly packed RGBA8 pixels, row 0 at the top as in canvas `ImageData`. `None` if
+    /// the pixel count does not match, a side is 0, the width is above or the height at least
+    /// `MAX_SPAWN_IMAGE_SIZE`, or no pixel has any weight.
+    pub fn from_rgba8(
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+        weight: SpawnImageWeight,
+    ) -> Option<Self> {
+        if width == 0
+            || height == 0
+            || width > MAX_SPAWN_IMAGE_SIZE
+            || height >= MAX_SPAWN_IMAGE_SIZE
+            || pixels.len() != 4 * width as usize * height as usize
+        {
+            return None;
+        }
+        let texels: Vec<[f32; 4]> = pixels
+            .chunks_exact(4)
+            .map(|p| [0, 1, 2, 3].map(|c| p[c] as f32 / 255.0))
+            .collect();
+        let weights: Vec<f32> = texels
+            .iter()
+            .map(|t| match weight {
+                SpawnImageWeight::Luminance => 0.2126 * t[0] + 0.7152 * t[1] + 0.0722 * t[2],
+                SpawnImageWeight::Alpha => t[3],
+            })
+            .collect();
+
+        let mut row_cdfs = Vec::with_capacity(weights.len());
+        let mut row_weights = Vec::with_capacity(height as usize);
+        for row in weights.chunks_exact(width as usize) {
+            row_weights.push(row.iter().map(|&w| w as f64).sum::<f64>() as f32);
+            row_cdfs.extend(normalized_cdf(row));
+        }
+        if row_weights.iter().all(|&w| w <= 0.0) {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            texels,
+            row_cdfs,
+            marginal_cdf: normalized_cdf(&row_weights),
+        })
+    }
+
+    /// One white pixel: `EmitterShape::Image` spawns uniformly over its rectangle until an image
+    /// is set.
+    pub(crate) fn uniform() -> Self {
+        Self {
+            width: 1,
+            height: 1,
+            texels: vec![[1.0; 4]],
+            row_cdfs: vec![1.0],
+            marginal_cdf: vec![1.0],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Picks a pixel from `r[0]` (row) and `r[1]` (column), jittered within it by `r[2]` and
+    /// `r[3]`. Returns the position in [0, 1]², y pointing down the rows, and the pixel color.
+    /// Mirrors `sample_spawn_image` in particles_update.wgsl.
+    pub(crate) fn sample(&self, r: [f32; 4]) -> ([f32; 2], [f32; 4]) {
+        let row = search_cdf(&self.marginal_cdf, r[0]);
+        let start = row * self.width as usize;
+        let column = search_cdf(&self.row_cdfs[start..start + self.width as usize], r[1]);
+        let uv = [
+            (column as f32 + r[2]) / self.width as f32,
+            (row as f32 + r[3]) / self.height as f32,
+        ];
+        (uv, self.texels[start + column])
+    }
+
+    /// Pixel colors as an `Rgba32Float` texture of `width x height`.
+    pub(crate) fn texels(&self) -> &[[f32; 4]] {
+        &self.texels
+    }
+
+    /// Largest side of the textures `cdf_texels` and `texels` fill.
+    pub(crate) fn texture_extent(&self) -> u32 {
+        self.width.max(self.height + 1)
+    }
+
+    /// CDFs as an `R32Float` texture `max(width, height)` texels wide and `height + 1` rows: the
+    /// row CDFs, then the marginal CDF.
+    pub(crate) fn cdf_texels(&self) -> (u32, Vec<f32>) {
+        let stride = self.width.max(self.height) as usize;
+        let mut texels = vec![0.0; stride * (self.height as usize + 1)];
+        for (row, cdf) in self.row_cdfs.chunks_exact(self.width as usize).enumerate() {
+            texels[row * stride..row * stride + cdf.len()].copy_from_slice(cdf);
+        }
+        let marginal = self.height as usize * stride;
+        texels[marginal..marginal + self.marginal_cdf.len()].copy_from_slice(&self.marginal_cdf);
+        (stride as u32, texels)
+    }
+}
+
+/// Inclusive running sum over `total`. From the last positive weight on, entries are exactly 1
+/// so rounding never lets `search_cdf` pick a trailing zero-weight entry. All zero stays zero.
+fn normalized_cdf(weights: &[f32]) -> Vec<f32> {
+    let total: f64 = weights.iter().map(|&w| w as f64).sum();
+    let mut running = 0.0f64;
+    let mut cdf: Vec<f32> = weights
+        .iter()
+        .map(|&w| {
+            running += w as f64;
+            if total > 0.0 {
+                (running / total) as f32
+            } else {
+                0.0
+            }
+        })
+        .collect();
+    if let Some(last) = weights.iter().rposition(|&w| w > 0.0) {
+        cdf[last..].fill(1.0);
+    }
+    cdf
+}
+
+/// First index whose CDF exceeds `u`; zero-weight entries are never picked.
+fn search_cdf(cdf: &[f32], u: f32) -> usize {
+    let u = u.min(BELOW_ONE);
+    let (mut lo, mut hi) = (0, cdf.len() - 1);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if cdf[mid] > u {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    lo
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SpawnImage, SpawnImageWeight, MAX_SPAWN_IMAGE_SIZE};
+    use crate::particles::math::hash01;
+
+    #[test]
+    fn samples_only_weighted_pixels_in_proportion() {
+        // 4x3 mask with two covered pixels, the second three times as dense.
+        let mut pixels = vec![0u8; 4 * 4 * 3];
+        pixels[4 * 5..4 * 6].copy_from_slice(&[255, 0, 0, 85]);
+        pixels[4 * 11..4 * 12].copy_from_slice(&[0, 0, 255, 255]);
+        let image = SpawnImage::from_rgba8(4, 3, &pixels, SpawnImageWeight::Alpha).unwrap();
+
+        let mut hits = [0u32; 2];
+        for i in 0..4000u32 {
+            let r = [0, 1, 2, 3].map(|k| hash01(i * 4 + k));
+            let ([u, v], color) = image.sample(r);
+            let pixel = (v * 3.0) as usize * 4 + (u * 4.0) as usize;
+            match pixel {
+                5 => {
+                    assert_eq!(color, [1.0, 0.0, 0.0, 1.0 / 3.0]);
+                    hits[0] += 1;
+                }
+                11 => {
+                    assert_eq!(color, [0.0, 0.0, 1.0, 1.0]);
+                    hits[1] += 1;
+                }
+                _ => panic!("sampled empty pixel {pixel}"),
+            }
+        }
+        let ratio = hits[1] as f32 / hits[0] as f32;
+        assert!((ratio - 3.0).abs() < 0.3, "density ratio {ratio}");
+        // Randoms of exactly 0 and 1 still land on covered pixels.
+        assert_eq!(image.sample([0.0; 4]).1[3], 1.0 / 3.0);
+        assert_eq!(image.sample([1.0; 4]).1[3], 1.0);
+    }
+
+    #[test]
+    fn rejects_empty_or_mismatched_images() {
+        let dark = [0, 0, 0, 255].repeat(4);
+        assert!(SpawnImage::from_rgba8(2, 2, &dark, SpawnImageWeight::Luminance).is_none());
+        assert!(SpawnImage::from_rgba8(2, 2, &dark, SpawnImageWeight::Alpha).is_some());
+        assert!(SpawnImage::from_rgba8(3, 2, &dark, SpawnImageWeight::Alpha).is_none());
+        assert!(SpawnImage::from_rgba8(0, 0, &[], SpawnImageWeight::Alpha).is_none());
+        // The CDF texture's extra row must still fit the 2D texture limit.
+        let column = [255u8; 4].repeat(MAX_SPAWN_IMAGE_SIZE as usize);
+        let alpha = SpawnImageWeight::Alpha;
+        assert!(SpawnImage::from_rgba8(1, MAX_SPAWN_IMAGE_SIZE, &column, alpha).is_none());
+        let tallest = SpawnImage::from_rgba8(1, MAX_SPAWN_IMAGE_SIZE - 1, &column[4..], alpha);
+        assert_eq!(tallest.unwrap().texture_extent(), MAX_SPAWN_IMAGE_SIZE);
+    }
+}
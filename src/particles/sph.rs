@@ -0,0 +1,447 @@
+use std::borrow::Cow;
+use std::f32::consts::PI;
+use std::mem::size_of;
+
+use bytemuck::{bytes_of, Pod, Zeroable};
+
+use super::math::{add, dot, mul_scalar, sub};
+use super::simulation::Particle;
+use super::spatial_grid::{
+    layout_entry, storage_binding, uniform_binding, SpatialGridConfig, SpatialHashGrid,
+    SpatialHashGridGpu,
+};
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Smoothed-particle hydrodynamics on top of the ballistic update: every step, pressure and
+/// viscosity accelerations from neighbors within `smoothing_radius` are added to the velocity
+/// before gravity, force fields and integration. Uses the Müller et al. 2003 kernels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SphConfig {
+    /// Kernel support; also the cell size of the neighbor grid.
+    pub smoothing_radius: f32,
+    pub particle_mass: f32,
+    pub rest_density: f32,
+    /// Pressure per unit of density above `rest_density`. Pressure is never negative, so the
+    /// fluid does not clump under tension.
+    pub stiffness: f32,
+    pub viscosity: f32,
+    /// Speed cap applied with the SPH acceleration, keeping stiff setups from blowing up.
+    pub max_speed: f32,
+    /// Axis-aligned container; particles are clamped inside and bounce off its walls.
+    pub container_min: [f32; 3],
+    pub container_max: [f32; 3],
+    pub container_restitution: f32,
+    /// Bucket count of the neighbor grid; see `SpatialGridConfig::table_size`.
+    pub grid_table_size: u32,
+}
+
+impl Default for SphConfig {
+    /// Roughly water-like at a particle spacing of half the smoothing radius.
+    fn default() -> Self {
+        Self {
+            smoothing_radius: 0.1,
+            particle_mass: 0.125,
+            rest_density: 1_000.0,
+            stiffness: 40.0,
+            viscosity: 0.5,
+            max_speed: 5.0,
+            container_min: [-1.0; 3],
+            container_max: [1.0; 3],
+            container_restitution: 0.2,
+            grid_table_size: 1 << 14,
+        }
+    }
+}
+
+impl SphConfig {
+    pub fn grid_config(&self) -> SpatialGridConfig {
+        SpatialGridConfig {
+            cell_size: self.smoothing_radius,
+            table_size: self.grid_table_size,
+        }
+    }
+
+    fn kernels(&self) -> SphKernels {
+        let h = self.smoothing_radius;
+        SphKernels {
+            h,
+            poly6: 315.0 / (64.0 * PI * h.powi(9)),
+            spiky_gradient: 45.0 / (PI * h.powi(6)),
+            viscosity_laplacian: 45.0 / (PI * h.powi(6)),
+        }
+    }
+
+    fn pressure(&self, density: f32) -> f32 {
+        (self.stiffness * (density - self.rest_density)).max(0.0)
+    }
+
+    /// Clamps into the container, reflecting the outward velocity component scaled by
+    /// `container_restitution`; mirrors `contain` in particles_update.wgsl.
+    pub(crate) fn contain(&self, position: &mut [f32; 3], velocity: &mut [f32; 3]) {
+        for axis in 0..3 {
+            if position[axis] < self.container_min[axis] {
+                position[axis] = self.container_min[axis];
+                if velocity[axis] < 0.0 {
+                    velocity[axis] *= -self.container_restitution;
+                }
+            } else if position[axis] > self.container_max[axis] {
+                position[axis] = self.container_max[axis];
+                if velocity[axis] > 0.0 {
+                    velocity[axis] *= -self.container_restitution;
+                }
+            }
+        }
+    }
+
+    /// CPU reference of the `density`, `forces` and `apply` passes in sph.wgsl: updates the
+    /// velocity of every alive particle in place and returns the per-slot densities.
+    pub(crate) fn apply_reference(&self, particles: &mut [Particle], dt: f32) -> Vec<f32> {
+        let grid = SpatialHashGrid::build(self.grid_config(), particles);
+        let k = self.kernels();
+        let h2 = k.h * k.h;
+
+        let mut densities = vec![0.0f32; particles.len()];
+        for (i, p) in particles.iter().enumerate() {
+            if !p.is_alive() {
+                continue;
+            }
+            grid.for_each_neighbor(particles, p.position, k.h, |j| {
+                let d = sub(particles[j as usize].position, p.position);
+                let w = h2 - dot(d, d);
+                densities[i] += self.particle_mass * k.poly6 * w * w * w;
+            });
+        }
+
+        let mut accelerations = vec![[0.0f32; 3]; particles.len()];
+        for (i, p) in particles.iter().enumerate() {
+            if !p.is_alive() {
+                continue;
+            }
+            let pressure_i = self.pressure(densities[i]);
+            let mut accel = [0.0; 3];
+            grid.for_each_neighbor(particles, p.position, k.h, |j| {
+                let j = j as usize;
+                if j == i {
+                    return;
+                }
+                let offset = sub(p.position, particles[j].position);
+                let r = dot(offset, offset).sqrt();
+                if r <= 0.0 {
+                    return;
+                }
+                let falloff = k.h - r;
+                let share = self.particle_mass / densities[j];
+                let push = share
+                    * 0.5
+                    * (pressure_i + self.pressure(densities[j]))
+                    * k.spiky_gradient
+                    * falloff
+                    * falloff
+                    / r;
+                let pull = share * self.viscosity * k.viscosity_laplacian * falloff;
+                accel = add(
+                    accel,
+                    add(
+                        mul_scalar(offset, push),
+                        mul_scalar(sub(particles[j].velocity, p.velocity), pull),
+                    ),
+                );
+            });
+            accelerations[i] = mul_scalar(accel, 1.0 / densities[i]);
+        }
+
+        for (p, accel) in particles.iter_mut().zip(&accelerations) {
+            if p.is_alive() {
+                p.velocity = clamp_speed(add(p.velocity, mul_scalar(*accel, dt)), self.max_speed);
+            }
+        }
+        densities
+    }
+}
+
+fn clamp_speed(velocity: [f32; 3], max_speed: f32) -> [f32; 3] {
+    let speed = dot(velocity, velocity).sqrt();
+    if speed > max_speed {
+        mul_scalar(velocity, max_speed / speed)
+    } else {
+        velocity
+    }
+}
+
+struct SphKernels {
+    h: f32,
+    poly6: f32,
+    spiky_gradient: f32,
+    viscosity_laplacian: f32,
+}
+
+/// Mirrors `SphParams` in sph.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuSphParams {
+    smoothing_radius: f32,
+    particle_mass: f32,
+    rest_density: f32,
+    stiffness: f32,
+    viscosity: f32,
+    max_speed: f32,
+    dt: f32,
+    poly6: f32,
+    spiky_gradient: f32,
+    viscosity_laplacian: f32,
+    particle_count: u32,
+    _pad0: u32,
+}
+
+impl GpuSphParams {
+    fn new(config: &SphConfig, particle_count: u32, dt: f32) -> Self {
+        let k = config.kernels();
+        Self {
+            smoothing_radius: k.h,
+            particle_mass: config.particle_mass,
+            rest_density: config.rest_density,
+            stiffness: config.stiffness,
+            viscosity: config.viscosity,
+            max_speed: config.max_speed,
+            dt,
+            poly6: k.poly6,
+            spiky_gradient: k.spiky_gradient,
+            viscosity_laplacian: k.viscosity_laplacian,
+            particle_count,
+            _pad0: 0,
+        }
+    }
+}
+
+/// GPU side of `ParticleSimMode::Sph`, encoded by `ParticleGpuSim` ahead of the update kernel:
+/// rebuilds the neighbor grid, then runs the `density`, `forces` and `apply` kernels.
+pub(crate) struct SphPasses {
+    config: SphConfig,
+    particle_count: u32,
+    grid: SpatialHashGridGpu,
+    params_buffer: wgpu::Buffer,
+    // Per slot: density and pressure in x and y.
+    density_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    density: wgpu::ComputePipeline,
+    forces: wgpu::ComputePipeline,
+    apply: wgpu::ComputePipeline,
+}
+
+impl SphPasses {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        particle_buffer: &wgpu::Buffer,
+        particle_count: u32,
+        config: SphConfig,
+    ) -> Self {
+        let grid = SpatialHashGridGpu::new(
+            device,
+            queue,
+            particle_buffer,
+            particle_count,
+            config.grid_config(),
+        );
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.sph.params"),
+            size: size_of::<GpuSphParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let slot_bytes = (size_of::<[f32; 4]>() as u64 * particle_count as u64).max(16);
+        let density_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.sph.density"),
+            size: slot_bytes,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let acceleration_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.sph.acceleration"),
+            size: slot_bytes,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let mut layout_entries = SpatialHashGridGpu::query_layout_entries(0).to_vec();
+        layout_entries.extend([
+            layout_entry(3, storage_binding(false)),
+            layout_entry(4, storage_binding(false)),
+            layout_entry(5, storage_binding(false)),
+            layout_entry(6, uniform_binding()),
+        ]);
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.sph.bgl"),
+            entries: &layout_entries,
+        });
+        let mut entries = grid.query_entries(0).to_vec();
+        for (binding, buffer) in [
+            (3, particle_buffer),
+            (4, &density_buffer),
+            (5, &acceleration_buffer),
+            (6, &params_buffer),
+        ] {
+            entries.push(wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            });
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.sph.bg"),
+            layout: &bind_group_layout,
+            entries: &entries,
+        });
+
+        let shader_source = SpatialHashGrid::query_wgsl(0, 0)
+            + include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/shaders/sph.wgsl"));
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles.sph.shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(shader_source)),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles.sph.pl"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&format!("particles.sph.{entry_point}.pipeline")),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            })
+        };
+
+        Self {
+            config,
+            particle_count,
+            grid,
+            params_buffer,
+            density_buffer,
+            bind_group,
+            density: create_pipeline("density"),
+            forces: create_pipeline("forces"),
+            apply: create_pipeline("apply"),
+        }
+    }
+
+    /// Per-slot density and pressure from the latest step, as `vec4<f32>` in x and y.
+    pub(crate) fn density_buffer(&self) -> &wgpu::Buffer {
+        &self.density_buffer
+    }
+
+    pub(crate) fn encode(&self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder, dt: f32) {
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytes_of(&GpuSphParams::new(&self.config, self.particle_count, dt)),
+        );
+        self.grid.encode_build(encoder);
+
+        let groups = self.particle_count.div_ceil(WORKGROUP_SIZE);
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particles.sph.pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        for pipeline in [&self.density, &self.forces, &self.apply] {
+            pass.set_pipeline(pipeline);
+            pass.dispatch_workgroups(groups, 1, 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SphConfig;
+    use crate::particles::{
+        Burst, BurstSchedule, EmitterConfig, EmitterShape, ForceConfig, ParticleSimConfig,
+        ParticleSimMode, ParticleState,
+    };
+
+    #[test]
+    fn container_clamps_and_reflects_outward_velocity() {
+        let sph = SphConfig {
+            container_restitution: 0.5,
+            ..SphConfig::default()
+        };
+        let mut position = [1.5, 0.0, -1.25];
+        let mut velocity = [2.0, 1.0, 4.0];
+        sph.contain(&mut position, &mut velocity);
+        assert_eq!(position, [1.0, 0.0, -1.0]);
+        // Only x was moving outward; z was already heading back in.
+        assert_eq!(velocity, [-1.0, 1.0, 4.0]);
+    }
+
+    #[test]
+    fn dam_break_conserves_mass_and_settles_near_rest_density() {
+        let sph = SphConfig {
+            container_min: [-0.2, -0.5, -0.2],
+            container_max: [0.2, 0.5, 0.2],
+            ..SphConfig::default()
+        };
+        let config = ParticleSimConfig {
+            max_particles: 512,
+            spawn_rate_per_second: 0.0,
+            // The 0.96-per-step damping this test was tuned with, at its 120 Hz step.
+            drag: 4.9,
+            lifetime_seconds: 1.0e6,
+            mode: ParticleSimMode::Sph(sph),
+            ..ParticleSimConfig::default()
+        };
+        let emitter = EmitterConfig {
+            center: [0.0, 0.2, 0.0],
+            shape: EmitterShape::Box {
+                half_extents: [0.2; 3],
+            },
+            initial_speed: 0.0,
+            bursts: BurstSchedule::from_slice(&[Burst::once(0.0, 400)]),
+            ..EmitterConfig::default()
+        };
+        let force = ForceConfig {
+            gravity: [0.0, -9.8, 0.0],
+            noise_strength: 0.0,
+            ..ForceConfig::default()
+        };
+
+        let mut state = ParticleState::new(config);
+        for _ in 0..400 {
+            state.step_reference(1.0 / 120.0, config, emitter, force);
+            assert_eq!(state.alive_count(), 400);
+            for p in state.particles.iter().filter(|p| p.is_alive()) {
+                assert!(p.position.iter().chain(&p.velocity).all(|v| v.is_finite()));
+                for axis in 0..3 {
+                    assert!(p.position[axis] >= sph.container_min[axis]);
+                    assert!(p.position[axis] <= sph.container_max[axis]);
+                }
+            }
+        }
+
+        let alive: Vec<usize> = (0..state.particles.len())
+            .filter(|&i| state.particles[i].is_alive())
+            .collect();
+        let mean_density =
+            alive.iter().map(|&i| state.sph_densities()[i]).sum::<f32>() / alive.len() as f32;
+        assert!((mean_density / sph.rest_density - 1.0).abs() < 0.1);
+        // Resting particles keep roughly one step of gravity, g * dt ~ 0.08, in their velocity.
+        let mean_speed = alive
+            .iter()
+            .map(|&i| {
+                let v = state.particles[i].velocity;
+                (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+            })
+            .sum::<f32>()
+            / alive.len() as f32;
+        assert!(mean_speed < 0.25, "still sloshing at {mean_speed}");
+
+        // Switching modes drops the stale densities.
+        let ballistic = ParticleSimConfig {
+            mode: ParticleSimMode::Ballistic,
+            ..config
+        };
+        state.step_reference(1.0 / 120.0, ballistic, emitter, force);
+        assert!(state.sph_densities().is_empty());
+    }
+}
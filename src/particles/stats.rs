@@ -0,0 +1,117 @@
+use bytemuck::{Pod, Zeroable};
+
+/// Per-step telemetry written by the GPU kernels; see `ParticleGpuSim::poll_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ParticleSimStats {
+    /// Simulation step the stats were captured after (1 = first step).
+    pub step_index: u64,
+    pub alive_count: u32,
+    pub spawned_this_step: u32,
+    pub died_this_step: u32,
+    pub max_speed: f32,
+    /// Bounds of finite, alive particles; both corners are zero when there are none.
+    pub bounds_min: [f32; 3],
+    pub bounds_max: [f32; 3],
+    /// Alive particles whose position or velocity held NaN/Inf.
+    pub non_finite_count: u32,
+    /// `non_finite_count` summed over every step since the sim was created, wrapping at
+    /// `u32::MAX`; catches violations between readbacks.
+    pub non_finite_total: u32,
+}
+
+/// Mirrors `Stats` in particles_update.wgsl. Bounds are stored as order-preserving keys so
+/// they can be reduced with `atomicMin`/`atomicMax` on `u32`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub(crate) struct GpuSimStats {
+    alive_count: u32,
+    spawned: u32,
+    died: u32,
+    non_finite: u32,
+    max_speed_bits: u32,
+    bounds_min_keys: [u32; 3],
+    bounds_max_keys: [u32; 3],
+    non_finite_total: u32,
+}
+
+impl GpuSimStats {
+    /// Matches the reset done by `begin_update`, so a sim that never stepped reads back empty.
+    pub(crate) fn reset() -> Self {
+        Self {
+            bounds_min_keys: [u32::MAX; 3],
+            ..Self::zeroed()
+        }
+    }
+
+    pub(crate) fn decode(&self, step_index: u64) -> ParticleSimStats {
+        let empty = (0..3).any(|axis| self.bounds_min_keys[axis] > self.bounds_max_keys[axis]);
+        let (bounds_min, bounds_max) = if empty {
+            ([0.0; 3], [0.0; 3])
+        } else {
+            (
+                self.bounds_min_keys.map(float_from_order_key),
+                self.bounds_max_keys.map(float_from_order_key),
+            )
+        };
+
+        ParticleSimStats {
+            step_index,
+            alive_count: self.alive_count,
+            spawned_this_step: self.spawned,
+            died_this_step: self.died,
+            max_speed: f32::from_bits(self.max_speed_bits),
+            bounds_min,
+            bounds_max,
+            non_finite_count: self.non_finite,
+            non_finite_total: self.non_finite_total,
+        }
+    }
+}
+
+/// Same mapping as `order_key` in particles_update.wgsl.
+#[cfg(test)]
+fn order_key(value: f32) -> u32 {
+    let bits = value.to_bits();
+    if bits & 0x8000_0000 != 0 {
+        !bits
+    } else {
+        bits | 0x8000_0000
+    }
+}
+
+fn float_from_order_key(key: u32) -> f32 {
+    if key & 0x8000_0000 != 0 {
+        f32::from_bits(key & 0x7fff_ffff)
+    } else {
+        f32::from_bits(!key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{float_from_order_key, order_key, GpuSimStats};
+    use crate::particles::compute::ParticleBufferLayout;
+
+    #[test]
+    fn order_keys_sort_like_floats() {
+        let values = [-3.5f32, -0.25, 0.0, 0.125, 7.0];
+        for pair in values.windows(2) {
+            assert!(order_key(pair[0]) < order_key(pair[1]));
+        }
+        for v in values {
+            assert_eq!(float_from_order_key(order_key(v)), v);
+        }
+    }
+
+    #[test]
+    fn reset_stats_decode_to_empty_bounds() {
+        assert_eq!(
+            std::mem::size_of::<GpuSimStats>() as u64,
+            ParticleBufferLayout::default().stats_bytes
+        );
+        let stats = GpuSimStats::reset().decode(0);
+        assert_eq!(stats.alive_count, 0);
+        assert_eq!(stats.bounds_min, [0.0; 3]);
+        assert_eq!(stats.bounds_max, [0.0; 3]);
+    }
+}
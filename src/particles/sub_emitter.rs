@@ -0,0 +1,207 @@
+use bytemuck::{Pod, Zeroable};
+
+use super::attributes::ParticleAttributes;
+use super::config::{EmitterConfig, EmitterDirection, EmitterShape};
+use super::emitter::{sample_emitter, spawn_randoms};
+use super::gpu::GpuEmitter;
+use super::math::{add, mul_scalar};
+use super::simulation::Particle;
+use super::spawn_image::SpawnImage;
+
+/// Why a particle raised an event; `EVENT_DEATH` and `EVENT_COLLISION` in particles_update.wgsl.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParticleEventKind {
+    /// The particle reached the end of its lifetime.
+    Death,
+    /// The particle struck a collider at `SubEmitterConfig::min_impact_speed` or faster, or
+    /// touched a kill collider.
+    Collision,
+}
+
+/// Where and how a particle died or collided; mirrors `ParticleEvent` in particles_update.wgsl.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleEvent {
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub kind: ParticleEventKind,
+}
+
+/// Secondary emitter spawning child particles at the events of primary particles. Children share
+/// the pool, forces, colliders and lifetime curves of their parents but never raise events
+/// themselves, so chains cannot cascade.
+#[derive(Debug, Clone, Copy)]
+pub struct SubEmitterConfig {
+    /// Shape, direction, speed and spawn attributes of the children; `center` is an offset from
+    /// the event position and `bursts` is ignored.
+    pub emitter: EmitterConfig,
+    pub lifetime_seconds: f32,
+    pub particles_per_event: u32,
+    /// Fraction of the parent's velocity added to each child's own initial velocity.
+    pub inherit_velocity: f32,
+    pub on_death: bool,
+    pub on_collision: bool,
+    /// Slower bounces raise no collision event, so particles resting on a collider stay quiet.
+    pub min_impact_speed: f32,
+    /// Events past this many in one step are dropped.
+    pub max_events_per_step: u32,
+}
+
+impl Default for SubEmitterConfig {
+    /// A small puff at the end of each particle's life.
+    fn default() -> Self {
+        Self {
+            emitter: EmitterConfig {
+                shape: EmitterShape::Sphere { radius: 0.0 },
+                direction: EmitterDirection::Random,
+                initial_speed: 0.5,
+                size: 0.01,
+                ..EmitterConfig::default()
+            },
+            lifetime_seconds: 0.5,
+            particles_per_event: 4,
+            inherit_velocity: 0.5,
+            on_death: true,
+            on_collision: false,
+            min_impact_speed: 0.5,
+            max_events_per_step: 1_024,
+        }
+    }
+}
+
+impl SubEmitterConfig {
+    pub fn triggers_on(&self, kind: ParticleEventKind) -> bool {
+        match kind {
+            ParticleEventKind::Death => self.on_death,
+            ParticleEventKind::Collision => self.on_collision,
+        }
+    }
+
+    /// Appends an event for `particle` when `kind` is a trigger and the step has room; mirrors
+    /// `push_event` in particles_update.wgsl.
+    pub(crate) fn record(
+        &self,
+        events: &mut Vec<ParticleEvent>,
+        kind: ParticleEventKind,
+        particle: &Particle,
+    ) {
+        if self.triggers_on(kind) && events.len() < self.max_events_per_step as usize {
+            events.push(ParticleEvent {
+                position: particle.position,
+                velocity: particle.velocity,
+                kind,
+            });
+        }
+    }
+
+    /// Child spawned from `event`; mirrors `spawn_child` in particles_update.wgsl.
+    pub(crate) fn spawn(
+        &self,
+        event: &ParticleEvent,
+        image: &SpawnImage,
+        seed: u32,
+        slot: u32,
+        remaining: u32,
+    ) -> (Particle, ParticleAttributes) {
+        let sample = sample_emitter(&self.emitter, image, spawn_randoms(seed, slot, remaining));
+        let particle = Particle {
+            position: add(event.position, sample.position),
+            age_seconds: 0.0,
+            velocity: add(
+                mul_scalar(event.velocity, self.inherit_velocity),
+                mul_scalar(sample.direction, self.emitter.initial_speed),
+            ),
+            lifetime_seconds: self.lifetime_seconds,
+        };
+        (
+            particle,
+            ParticleAttributes::spawn(&self.emitter, sample.tint, seed, slot, remaining),
+        )
+    }
+}
+
+/// Mirrors `SubEmitter` in particles_update.wgsl. `triggers` has bit `EVENT_*` set for each
+/// enabled event kind; everything is zero without a sub-emitter.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub(crate) struct GpuSubEmitter {
+    emitter: GpuEmitter,
+    lifetime: f32,
+    inherit_velocity: f32,
+    initial_speed: f32,
+    min_impact_speed: f32,
+    particles_per_event: u32,
+    triggers: u32,
+    _pad0: [u32; 2],
+}
+
+impl GpuSubEmitter {
+    pub(crate) fn new(sub_emitter: Option<&SubEmitterConfig>) -> Self {
+        let Some(sub) = sub_emitter else {
+            return Self::zeroed();
+        };
+        Self {
+            emitter: GpuEmitter::new(&sub.emitter),
+            lifetime: sub.lifetime_seconds,
+            inherit_velocity: sub.inherit_velocity,
+            initial_speed: sub.emitter.initial_speed,
+            min_impact_speed: sub.min_impact_speed,
+            particles_per_event: sub.particles_per_event,
+            triggers: u32::from(sub.on_death) | u32::from(sub.on_collision) << 1,
+            _pad0: [0; 2],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParticleEvent, ParticleEventKind, SpawnImage, SubEmitterConfig};
+    use crate::particles::Particle;
+
+    #[test]
+    fn events_respect_triggers_and_capacity() {
+        let sub = SubEmitterConfig {
+            on_death: false,
+            on_collision: true,
+            max_events_per_step: 2,
+            ..SubEmitterConfig::default()
+        };
+        let particle = Particle {
+            position: [1.0, 2.0, 3.0],
+            age_seconds: 0.0,
+            velocity: [0.0, -1.0, 0.0],
+            lifetime_seconds: 1.0,
+        };
+        let mut events = Vec::new();
+        sub.record(&mut events, ParticleEventKind::Death, &particle);
+        assert!(events.is_empty());
+        for _ in 0..3 {
+            sub.record(&mut events, ParticleEventKind::Collision, &particle);
+        }
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].position, [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn children_start_at_the_event_with_inherited_velocity() {
+        let sub = SubEmitterConfig {
+            inherit_velocity: 0.25,
+            ..SubEmitterConfig::default()
+        };
+        let event = ParticleEvent {
+            position: [0.5, 0.0, -0.5],
+            velocity: [4.0, 0.0, 0.0],
+            kind: ParticleEventKind::Death,
+        };
+        let (child, _) = sub.spawn(&event, &SpawnImage::uniform(), 0, 3, 7);
+        assert_eq!(child.position, event.position);
+        assert_eq!(child.lifetime_seconds, sub.lifetime_seconds);
+        // Inherited 1.0 along x plus a random direction at `initial_speed`.
+        let own = [
+            child.velocity[0] - 1.0,
+            child.velocity[1],
+            child.velocity[2],
+        ];
+        let own_speed = own.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((own_speed - sub.emitter.initial_speed).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,514 @@
+use std::borrow::Cow;
+use std::mem::size_of;
+
+use bytemuck::{bytes_of, cast_slice, Pod, Zeroable};
+
+use super::gpu::ParticleGpuSim;
+use super::math::{cross, normalize_or_zero, sub};
+use super::simulation::Particle;
+use super::spatial_grid::{layout_entry, storage_binding, uniform_binding};
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Per-particle position history drawn as camera-facing ribbons. Width and alpha taper linearly
+/// from the newest sample (head) to the oldest (tail).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrailConfig {
+    /// Positions kept per particle, one per step; at least 2.
+    pub history_len: u32,
+    pub head_width: f32,
+    pub tail_width: f32,
+    pub head_alpha: f32,
+    pub tail_alpha: f32,
+    /// Ribbon color; its alpha is multiplied by the tapered alpha.
+    pub color: [f32; 4],
+}
+
+impl Default for TrailConfig {
+    fn default() -> Self {
+        Self {
+            history_len: 16,
+            head_width: 0.02,
+            tail_width: 0.0,
+            head_alpha: 1.0,
+            tail_alpha: 0.0,
+            color: [1.0; 4],
+        }
+    }
+}
+
+impl TrailConfig {
+    fn ring_len(&self) -> u32 {
+        self.history_len.max(2)
+    }
+}
+
+/// Ribbon vertex from `ribbon_vertices`; a triangle strip, two vertices per history sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RibbonVertex {
+    pub position: [f32; 3],
+    pub alpha: f32,
+}
+
+/// CPU mirror of `vs_main` in trail_ribbons.wgsl. `samples` runs newest first; each one becomes a
+/// left/right vertex pair offset across the trail, perpendicular to both the trail and the view
+/// direction from `camera_position`. Fewer than two samples make no ribbon.
+pub fn ribbon_vertices(
+    samples: &[[f32; 3]],
+    camera_position: [f32; 3],
+    config: &TrailConfig,
+) -> Vec<RibbonVertex> {
+    if samples.len() < 2 {
+        return Vec::new();
+    }
+    let last = samples.len() - 1;
+    let mut vertices = Vec::with_capacity(2 * samples.len());
+    for (i, &p) in samples.iter().enumerate() {
+        let tangent = sub(samples[i.saturating_sub(1)], samples[(i + 1).min(last)]);
+        let side = normalize_or_zero(cross(tangent, sub(camera_position, p)));
+        let t = i as f32 / last as f32;
+        let half_width = 0.5 * lerp(config.head_width, config.tail_width, t);
+        let alpha = lerp(config.head_alpha, config.tail_alpha, t);
+        for s in [half_width, -half_width] {
+            vertices.push(RibbonVertex {
+                position: [p[0] + side[0] * s, p[1] + side[1] * s, p[2] + side[2] * s],
+                alpha,
+            });
+        }
+    }
+    vertices
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// CPU twin of the GPU history ring: every slot keeps its last `history_len` positions, recorded
+/// at the end of each step. A slot's trail restarts when its particle dies or respawns.
+#[derive(Debug, Clone)]
+pub struct TrailHistory {
+    history_len: u32,
+    // Ring index written by the latest `record`, shared by every slot.
+    head: u32,
+    positions: Vec<[f32; 3]>,
+    counts: Vec<u32>,
+}
+
+impl TrailHistory {
+    pub fn new(config: &TrailConfig, particle_count: u32) -> Self {
+        let history_len = config.ring_len();
+        Self {
+            history_len,
+            head: history_len - 1,
+            positions: vec![[0.0; 3]; (history_len * particle_count) as usize],
+            counts: vec![0; particle_count as usize],
+        }
+    }
+
+    /// Mirrors `record` in trails.wgsl.
+    pub fn record(&mut self, particles: &[Particle]) {
+        self.head = (self.head + 1) % self.history_len;
+        for (slot, p) in particles.iter().enumerate() {
+            if !p.is_alive() {
+                self.counts[slot] = 0;
+                continue;
+            }
+            // Age zero at the end of a step means the slot was (re)spawned during it.
+            let count = if p.age_seconds == 0.0 {
+                0
+            } else {
+                self.counts[slot]
+            };
+            self.positions[slot * self.history_len as usize + self.head as usize] = p.position;
+            self.counts[slot] = (count + 1).min(self.history_len);
+        }
+    }
+
+    /// Forgets every recorded position.
+    pub fn clear(&mut self) {
+        self.counts.fill(0);
+    }
+
+    /// Recorded positions of `slot`, newest first.
+    pub fn samples(&self, slot: usize) -> Vec<[f32; 3]> {
+        let len = self.history_len as usize;
+        let ring = &self.positions[slot * len..(slot + 1) * len];
+        (0..self.counts[slot] as usize)
+            .map(|i| ring[(self.head as usize + len - i) % len])
+            .collect()
+    }
+}
+
+/// Mirrors `TrailParams` in trails.wgsl and trail_ribbons.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuTrailParams {
+    color: [f32; 4],
+    history_len: u32,
+    head: u32,
+    particle_count: u32,
+    _pad0: u32,
+    head_width: f32,
+    tail_width: f32,
+    head_alpha: f32,
+    tail_alpha: f32,
+}
+
+impl GpuTrailParams {
+    fn new(config: &TrailConfig, particle_count: u32, head: u32) -> Self {
+        Self {
+            color: config.color,
+            history_len: config.ring_len(),
+            head,
+            particle_count,
+            _pad0: 0,
+            head_width: config.head_width,
+            tail_width: config.tail_width,
+            head_alpha: config.head_alpha,
+            tail_alpha: config.tail_alpha,
+        }
+    }
+}
+
+/// GPU side of `ParticleSimConfig::trails`, encoded by `ParticleGpuSim` after every other pass:
+/// appends each live slot's position to its history ring.
+pub(crate) struct TrailPasses {
+    config: TrailConfig,
+    particle_count: u32,
+    params_buffer: wgpu::Buffer,
+    // `history_len` positions per slot as `vec4<f32>`, w = 1.
+    history_buffer: wgpu::Buffer,
+    // Per slot: valid samples in the ring.
+    count_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    record: wgpu::ComputePipeline,
+}
+
+impl TrailPasses {
+    pub(crate) fn new(
+        device: &wgpu::Device,
+        particle_buffer: &wgpu::Buffer,
+        particle_count: u32,
+        config: TrailConfig,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.trails.params"),
+            size: size_of::<GpuTrailParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let history_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.trails.history"),
+            size: (size_of::<[f32; 4]>() as u64 * config.ring_len() as u64 * particle_count as u64)
+                .max(16),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let count_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.trails.counts"),
+            size: (size_of::<u32>() as u64 * particle_count as u64).max(16),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.trails.bgl"),
+            entries: &[
+                layout_entry(0, storage_binding(true)),
+                layout_entry(1, storage_binding(false)),
+                layout_entry(2, storage_binding(false)),
+                layout_entry(3, uniform_binding()),
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.trails.bg"),
+            layout: &bind_group_layout,
+            entries: &[
+                (0, particle_buffer),
+                (1, &history_buffer),
+                (2, &count_buffer),
+                (3, &params_buffer),
+            ]
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            }),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles.trails.shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/shaders/trails.wgsl"
+            )))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles.trails.pl"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let record = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("particles.trails.record.pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "record",
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        });
+
+        Self {
+            config,
+            particle_count,
+            params_buffer,
+            history_buffer,
+            count_buffer,
+            bind_group,
+            record,
+        }
+    }
+
+    /// Ring of `history_len` `vec4<f32>` positions per slot; see `TrailHistory`.
+    pub(crate) fn history_buffer(&self) -> &wgpu::Buffer {
+        &self.history_buffer
+    }
+
+    /// Forgets every recorded position, like `TrailHistory::clear`.
+    pub(crate) fn clear(&self, queue: &wgpu::Queue) {
+        let counts = vec![0u32; self.particle_count as usize];
+        queue.write_buffer(&self.count_buffer, 0, cast_slice(&counts));
+    }
+
+    /// Records the state after step `step_index` (1 for the first step) into ring entry
+    /// `(step_index - 1) % history_len`, matching `TrailHistory::record`.
+    pub(crate) fn encode(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        step_index: u64,
+    ) {
+        let head = ((step_index - 1) % self.config.ring_len() as u64) as u32;
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytes_of(&GpuTrailParams::new(
+                &self.config,
+                self.particle_count,
+                head,
+            )),
+        );
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particles.trails.pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_pipeline(&self.record);
+        pass.dispatch_workgroups(self.particle_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+/// Camera the ribbons face. `view_proj` is column-major, mapping world space to clip space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrailCamera {
+    pub view_proj: [[f32; 4]; 4],
+    pub position: [f32; 3],
+}
+
+/// Mirrors `TrailCamera` in trail_ribbons.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+struct GpuTrailCamera {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 3],
+    _pad0: f32,
+}
+
+/// Draws the trails of a `ParticleGpuSim` as alpha-blended ribbons. Vertices are generated in the
+/// vertex shader straight from the history ring, one triangle strip instance per particle slot.
+pub struct ParticleTrailRenderer {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    camera_buffer: wgpu::Buffer,
+    vertex_count: u32,
+    instance_count: u32,
+}
+
+impl ParticleTrailRenderer {
+    /// Returns `None` when `sim` was created without `ParticleSimConfig::trails`. With a
+    /// `depth_format`, ribbons are depth-tested but do not write depth.
+    pub fn new(
+        device: &wgpu::Device,
+        sim: &ParticleGpuSim,
+        color_format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+    ) -> Option<Self> {
+        let trails = sim.trails()?;
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles.trails.render.camera"),
+            size: size_of::<GpuTrailCamera>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let vertex_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ..layout_entry(binding, ty)
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particles.trails.render.bgl"),
+            entries: &[
+                vertex_entry(0, storage_binding(true)),
+                vertex_entry(1, storage_binding(true)),
+                vertex_entry(2, uniform_binding()),
+                vertex_entry(3, uniform_binding()),
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particles.trails.render.bg"),
+            layout: &bind_group_layout,
+            entries: &[
+                (0, &trails.history_buffer),
+                (1, &trails.count_buffer),
+                (2, &trails.params_buffer),
+                (3, &camera_buffer),
+            ]
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            }),
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("particles.trails.render.shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/shaders/trail_ribbons.wgsl"
+            )))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("particles.trails.render.pl"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("particles.trails.render.pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..wgpu::PrimitiveState::default()
+            },
+            depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Some(Self {
+            pipeline,
+            bind_group,
+            camera_buffer,
+            vertex_count: 2 * trails.config.ring_len(),
+            instance_count: trails.particle_count,
+        })
+    }
+
+    /// Records the ribbon draw into `pass`, facing `camera`.
+    pub fn draw<'a>(
+        &'a self,
+        queue: &wgpu::Queue,
+        pass: &mut wgpu::RenderPass<'a>,
+        camera: &TrailCamera,
+    ) {
+        let camera = GpuTrailCamera {
+            view_proj: camera.view_proj,
+            position: camera.position,
+            _pad0: 0.0,
+        };
+        queue.write_buffer(&self.camera_buffer, 0, bytes_of(&camera));
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..self.vertex_count, 0..self.instance_count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ribbon_vertices, TrailConfig, TrailHistory};
+    use crate::particles::Particle;
+
+    #[test]
+    fn ribbon_faces_the_camera_and_tapers_from_head_to_tail() {
+        let config = TrailConfig {
+            head_width: 0.2,
+            tail_width: 0.0,
+            head_alpha: 1.0,
+            tail_alpha: 0.2,
+            ..TrailConfig::default()
+        };
+        // Newest first, moving along +x, seen from +z.
+        let samples = [[2.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 0.0]];
+        let vertices = ribbon_vertices(&samples, [1.0, 0.0, 10.0], &config);
+        assert_eq!(vertices.len(), 6);
+
+        for (i, pair) in vertices.chunks_exact(2).enumerate() {
+            let width = pair[0].position[1] - pair[1].position[1];
+            // Offsets stay in the plane facing the camera, across the direction of travel.
+            assert!(pair.iter().all(|v| v.position[2] == 0.0));
+            assert!(pair.iter().all(|v| v.position[0] == samples[i][0]));
+            assert!((width.abs() - [0.2, 0.1, 0.0][i]).abs() < 1e-6);
+            assert!((pair[0].alpha - [1.0, 0.6, 0.2][i]).abs() < 1e-6);
+        }
+        assert!(ribbon_vertices(&samples[..1], [0.0; 3], &config).is_empty());
+    }
+
+    #[test]
+    fn history_wraps_and_restarts_when_a_slot_respawns() {
+        let config = TrailConfig {
+            history_len: 3,
+            ..TrailConfig::default()
+        };
+        let mut history = TrailHistory::new(&config, 1);
+        let mut particle = Particle {
+            position: [0.0; 3],
+            age_seconds: 0.0,
+            velocity: [0.0; 3],
+            lifetime_seconds: 10.0,
+        };
+        for step in 0..5 {
+            particle.position = [step as f32, 0.0, 0.0];
+            history.record(&[particle]);
+            particle.age_seconds += 0.1;
+        }
+        assert_eq!(
+            history.samples(0),
+            [[4.0, 0.0, 0.0], [3.0, 0.0, 0.0], [2.0, 0.0, 0.0]]
+        );
+
+        particle.age_seconds = 0.0;
+        particle.position = [-1.0, 0.0, 0.0];
+        history.record(&[particle]);
+        assert_eq!(history.samples(0), [[-1.0, 0.0, 0.0]]);
+
+        history.record(&[Particle::dead()]);
+        assert!(history.samples(0).is_empty());
+    }
+}
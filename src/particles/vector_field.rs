@@ -0,0 +1,232 @@
+/// Most voxels along any axis of a `VectorFieldVolume`, so voxel counts cannot overflow. Devices
+/// may allow fewer; `ParticleGpuSim::set_vector_field` checks their 3D texture limit.
+const MAX_VECTOR_FIELD_RESOLUTION: u32 = 2048;
+
+/// Why `VectorFieldVolume::parse_fga` rejected a file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FgaError {
+    /// Token `index` (0-based, counting every comma- or whitespace-separated value) is not a
+    /// number.
+    InvalidNumber { index: usize },
+    /// The header is cut short, or a resolution is zero, not a whole number or above 2048.
+    InvalidHeader,
+    /// The vector count does not match the resolution in the header.
+    WrongVectorCount { expected: usize, got: usize },
+}
+
+impl std::fmt::Display for FgaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidNumber { index } => write!(f, "FGA value {} is not a number", index),
+            Self::InvalidHeader => write!(f, "FGA header needs a resolution and bounds"),
+            Self::WrongVectorCount { expected, got } => write!(
+                f,
+                "FGA resolution needs {} vectors but the file has {}",
+                expected, got
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FgaError {}
+
+/// Vectors on a regular grid of `resolution` voxels filling `bounds_min..bounds_max`, x varying
+/// fastest, as exported by Unreal and EmberGen in the FGA format. Each vector sits at the
+/// center of its voxel; samples between centers are trilinear and clamp to the outer centers
+/// near the faces. Uploaded as an `Rgba32Float` 3D texture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorFieldVolume {
+    resolution: [u32; 3],
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+    vectors: Vec<[f32; 3]>,
+}
+
+impl VectorFieldVolume {
+    /// Parses FGA text: the resolution, the minimum and maximum bounds, then one vector per
+    /// voxel, every value separated by commas and/or whitespace.
+    pub fn parse_fga(text: &str) -> Result<Self, FgaError> {
+        let values = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|token| !token.is_empty())
+            .enumerate()
+            .map(|(index, token)| {
+                token
+                    .parse::<f32>()
+                    .map_err(|_| FgaError::InvalidNumber { index })
+            })
+            .collect::<Result<Vec<f32>, FgaError>>()?;
+        if values.len() < 9 {
+            return Err(FgaError::InvalidHeader);
+        }
+        let mut resolution = [0u32; 3];
+        for (n, &value) in resolution.iter_mut().zip(&values[..3]) {
+            if value.fract() != 0.0 || !(1.0..=u32::MAX as f32).contains(&value) {
+                return Err(FgaError::InvalidHeader);
+            }
+            *n = value as u32;
+        }
+        let expected = voxel_count(resolution).ok_or(FgaError::InvalidHeader)?;
+        let components = &values[9..];
+        if Some(components.len()) != expected.checked_mul(3) {
+            return Err(FgaError::WrongVectorCount {
+                expected,
+                got: components.len() / 3,
+            });
+        }
+        Ok(Self {
+            resolution,
+            bounds_min: [values[3], values[4], values[5]],
+            bounds_max: [values[6], values[7], values[8]],
+            vectors: components
+                .chunks_exact(3)
+                .map(|v| [v[0], v[1], v[2]])
+                .collect(),
+        })
+    }
+
+    /// Wraps precomputed vectors; `None` if the count does not match `resolution` or an axis
+    /// has no voxels or more than 2048.
+    pub fn from_vectors(
+        resolution: [u32; 3],
+        bounds_min: [f32; 3],
+        bounds_max: [f32; 3],
+        vectors: Vec<[f32; 3]>,
+    ) -> Option<Self> {
+        if Some(vectors.len()) != voxel_count(resolution) {
+            return None;
+        }
+        Some(Self {
+            resolution,
+            bounds_min,
+            bounds_max,
+            vectors,
+        })
+    }
+
+    pub fn resolution(&self) -> [u32; 3] {
+        self.resolution
+    }
+
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        (self.bounds_min, self.bounds_max)
+    }
+
+    pub fn vectors(&self) -> &[[f32; 3]] {
+        &self.vectors
+    }
+
+    /// Vectors padded to the `Rgba32Float` texels of the uploaded texture.
+    pub(crate) fn texels(&self) -> Vec<[f32; 4]> {
+        self.vectors
+            .iter()
+            .map(|v| [v[0], v[1], v[2], 0.0])
+            .collect()
+    }
+
+    fn voxel(&self, x: u32, y: u32, z: u32) -> [f32; 3] {
+        let [nx, ny, _] = self.resolution;
+        self.vectors[(x + nx * (y + ny * z)) as usize]
+    }
+
+    /// Trilinear vector at `position` in the volume's own space; mirrors `sample_vector_field`
+    /// in particles_update.wgsl. `None` outside the bounds.
+    pub(crate) fn sample(&self, position: [f32; 3]) -> Option<[f32; 3]> {
+        let mut cell = [0u32; 3];
+        let mut frac = [0.0f32; 3];
+        for a in 0..3 {
+            let extent = self.bounds_max[a] - self.bounds_min[a];
+            let uvw = (position[a] - self.bounds_min[a]) / extent;
+            if extent <= 0.0 || !(0.0..=1.0).contains(&uvw) {
+                return None;
+            }
+            let last = (self.resolution[a] - 1) as f32;
+            let grid = (uvw * self.resolution[a] as f32 - 0.5).clamp(0.0, last);
+            cell[a] = (grid.floor() as u32).min(self.resolution[a].saturating_sub(2));
+            frac[a] = grid - cell[a] as f32;
+        }
+
+        // Axes with a single voxel have `frac` 0, so the clamped neighbor is never weighted.
+        let [x, y, z] = cell;
+        let [x1, y1, z1] = [0, 1, 2].map(|a| (cell[a] + 1).min(self.resolution[a] - 1));
+        let [fx, fy, fz] = frac;
+        let mut vector = [0.0; 3];
+        for (c, out) in vector.iter_mut().enumerate() {
+            *out = lerp(
+                lerp(
+                    lerp(self.voxel(x, y, z)[c], self.voxel(x1, y, z)[c], fx),
+                    lerp(self.voxel(x, y1, z)[c], self.voxel(x1, y1, z)[c], fx),
+                    fy,
+                ),
+                lerp(
+                    lerp(self.voxel(x, y, z1)[c], self.voxel(x1, y, z1)[c], fx),
+                    lerp(self.voxel(x, y1, z1)[c], self.voxel(x1, y1, z1)[c], fx),
+                    fy,
+                ),
+                fz,
+            );
+        }
+        Some(vector)
+    }
+}
+
+/// Voxels in a grid of `resolution`, or `None` if an axis is empty or too large for the texture.
+fn voxel_count(resolution: [u32; 3]) -> Option<usize> {
+    resolution.iter().try_fold(1usize, |count, &n| {
+        if n == 0 || n > MAX_VECTOR_FIELD_RESOLUTION {
+            return None;
+        }
+        count.checked_mul(n as usize)
+    })
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FgaError, VectorFieldVolume};
+
+    #[test]
+    fn parses_fga_and_interpolates_between_voxel_centers() {
+        let text = "2,1,1,\n0.0,0.0,0.0,\n2.0,1.0,1.0,\n1.0,0.0,0.0,\n3.0,0.0,-2.0,\n";
+        let volume = VectorFieldVolume::parse_fga(text).unwrap();
+        assert_eq!(volume.resolution(), [2, 1, 1]);
+        assert_eq!(volume.bounds(), ([0.0; 3], [2.0, 1.0, 1.0]));
+
+        // Voxel centers sit at x = 0.5 and 1.5; outside them the edge vectors hold.
+        assert_eq!(volume.sample([0.5, 0.5, 0.5]), Some([1.0, 0.0, 0.0]));
+        assert_eq!(volume.sample([1.0, 0.2, 0.9]), Some([2.0, 0.0, -1.0]));
+        assert_eq!(volume.sample([1.9, 0.0, 1.0]), Some([3.0, 0.0, -2.0]));
+        assert_eq!(volume.sample([2.1, 0.5, 0.5]), None);
+    }
+
+    #[test]
+    fn rejects_malformed_fga() {
+        assert_eq!(
+            VectorFieldVolume::parse_fga("1,1,1, 0,0,0, 1,1,1, 0,x,0"),
+            Err(FgaError::InvalidNumber { index: 10 })
+        );
+        assert_eq!(
+            VectorFieldVolume::parse_fga("2,2,1, 0,0,0, 1,1,1, 0,0,0"),
+            Err(FgaError::WrongVectorCount {
+                expected: 4,
+                got: 1
+            })
+        );
+        assert_eq!(
+            VectorFieldVolume::parse_fga("0,1,1, 0,0,0, 1,1,1"),
+            Err(FgaError::InvalidHeader)
+        );
+        // Would overflow the voxel count, and no 3D texture holds it anyway.
+        assert_eq!(
+            VectorFieldVolume::parse_fga("4000000000,4000000000,4000000000, 0,0,0, 1,1,1, 0,0,0"),
+            Err(FgaError::InvalidHeader)
+        );
+        assert_eq!(
+            VectorFieldVolume::from_vectors([2049, 1, 1], [0.0; 3], [1.0; 3], vec![[0.0; 3]; 2049]),
+            None
+        );
+    }
+}
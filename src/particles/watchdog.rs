@@ -0,0 +1,213 @@
+use super::config::{EmitterConfig, ForceConfig, ParticleSimConfig};
+use super::gpu::{ParticleGpuError, ParticleGpuSim, ParticleStepInput};
+use super::simulation::ParticleState;
+
+/// Steps between stats readbacks in `soak_gpu`, one simulated second at the default 120 Hz.
+const SOAK_GPU_CHECK_STEPS: u64 = 120;
+
+/// What the update step does with an alive particle whose position or velocity became NaN or
+/// infinite. Every policy counts it in `ParticleSimStats::non_finite_count`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Leave it in the pool, outside the stats bounds and max speed; it is counted again every
+    /// step until it expires.
+    #[default]
+    Count,
+    /// Free its slot without raising sub-emitter events.
+    Kill,
+    /// Spawn a fresh particle from the primary emitter into the same slot.
+    Respawn,
+}
+
+impl NonFinitePolicy {
+    /// `non_finite_policy` in particles_update.wgsl; `NON_FINITE_*` there.
+    pub(crate) fn gpu_kind(self) -> u32 {
+        match self {
+            Self::Count => 0,
+            Self::Kill => 1,
+            Self::Respawn => 2,
+        }
+    }
+
+    /// Inverse of `gpu_kind`.
+    pub(crate) fn from_gpu_kind(kind: u32) -> Option<Self> {
+        match kind {
+            0 => Some(Self::Count),
+            1 => Some(Self::Kill),
+            2 => Some(Self::Respawn),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of `soak_reference` or `soak_gpu`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SoakReport {
+    pub steps: u64,
+    pub simulated_seconds: f32,
+    /// Non-finite particles seen, summed over steps.
+    pub violations: u64,
+    /// Simulated time of the step the first violation was seen in. `soak_gpu` only knows the
+    /// end of the readback interval it fell in.
+    pub first_violation_seconds: Option<f32>,
+    pub final_alive_count: u32,
+}
+
+impl SoakReport {
+    pub fn passed(&self) -> bool {
+        self.violations == 0
+    }
+
+    fn record(&mut self, violations: u64, seconds: f32) {
+        if violations > 0 {
+            self.violations += violations;
+            self.first_violation_seconds.get_or_insert(seconds);
+        }
+    }
+}
+
+/// Steps `state` with the CPU reference for `minutes` of simulated time at `dt` per step, and
+/// reports every particle the watchdog caught.
+pub fn soak_reference(
+    state: &mut ParticleState,
+    config: ParticleSimConfig,
+    emitter: EmitterConfig,
+    force: ForceConfig,
+    dt: f32,
+    minutes: f32,
+) -> SoakReport {
+    let mut report = SoakReport::default();
+    for _ in 0..soak_steps(dt, minutes) {
+        state.step_reference(dt, config, emitter, force);
+        report.steps += 1;
+        report.record(state.non_finite_count() as u64, state.elapsed_seconds());
+    }
+    report.simulated_seconds = state.elapsed_seconds();
+    report.final_alive_count = state.alive_count() as u32;
+    report
+}
+
+/// Steps `sim` with `input` for `minutes` of simulated time, reading `non_finite_total` back
+/// once per simulated second and at the end. Blocks on each readback.
+pub fn soak_gpu(
+    sim: &mut ParticleGpuSim,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    input: ParticleStepInput,
+    minutes: f32,
+) -> Result<SoakReport, ParticleGpuError> {
+    let mut report = SoakReport::default();
+    let mut total = sim.read_stats_blocking(device, queue)?.non_finite_total;
+    let steps = soak_steps(input.dt_seconds, minutes);
+    while report.steps < steps {
+        let batch = SOAK_GPU_CHECK_STEPS.min(steps - report.steps);
+        for _ in 0..batch {
+            sim.step(device, queue, input);
+        }
+        report.steps += batch;
+        let stats = sim.read_stats_blocking(device, queue)?;
+        report.record(
+            stats.non_finite_total.wrapping_sub(total) as u64,
+            sim.elapsed_seconds(),
+        );
+        total = stats.non_finite_total;
+        report.final_alive_count = stats.alive_count;
+    }
+    report.simulated_seconds = sim.elapsed_seconds();
+    Ok(report)
+}
+
+/// Steps needed to cover `minutes`, at `dt` clamped the way both step paths clamp it.
+fn soak_steps(dt: f32, minutes: f32) -> u64 {
+    let dt = dt.clamp(0.0, 1.0 / 15.0);
+    if dt <= 0.0 {
+        return 0;
+    }
+    (minutes.max(0.0) as f64 * 60.0 / dt as f64).ceil() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{soak_reference, NonFinitePolicy};
+    use crate::particles::{EmitterConfig, ForceConfig, ParticleSimConfig, ParticleState};
+
+    fn config(non_finite_policy: NonFinitePolicy) -> ParticleSimConfig {
+        ParticleSimConfig {
+            max_particles: 64,
+            spawn_rate_per_second: 1200.0,
+            lifetime_seconds: 10.0,
+            non_finite_policy,
+            ..ParticleSimConfig::default()
+        }
+    }
+
+    #[test]
+    fn policies_count_kill_or_respawn_non_finite_particles() {
+        let emitter = EmitterConfig::default();
+        let force = ForceConfig::default();
+        let poisoned = ForceConfig {
+            gravity: [f32::NAN, 0.0, 0.0],
+            ..force
+        };
+        let finite = |state: &ParticleState| {
+            state
+                .particles
+                .iter()
+                .filter(|p| p.is_alive())
+                .all(|p| p.position.iter().chain(&p.velocity).all(|c| c.is_finite()))
+        };
+        for policy in [
+            NonFinitePolicy::Count,
+            NonFinitePolicy::Kill,
+            NonFinitePolicy::Respawn,
+        ] {
+            let config = config(policy);
+            let mut state = ParticleState::new(config);
+            state.step_reference(1.0 / 60.0, config, emitter, force);
+            let alive = state.alive_count();
+            assert_eq!(alive, 20);
+
+            // Spawning comes after the update, so this step's new particles stay finite.
+            state.step_reference(1.0 / 60.0, config, emitter, poisoned);
+            assert_eq!(state.non_finite_count() as usize, alive, "{policy:?}");
+            let expected_alive = match policy {
+                NonFinitePolicy::Kill => 20,
+                _ => 40,
+            };
+            assert_eq!(state.alive_count(), expected_alive, "{policy:?}");
+            assert_eq!(
+                finite(&state),
+                policy != NonFinitePolicy::Count,
+                "{policy:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn soak_reports_the_first_violation() {
+        let config = config(NonFinitePolicy::Kill);
+        let emitter = EmitterConfig::default();
+        let mut state = ParticleState::new(config);
+        let clean = soak_reference(
+            &mut state,
+            config,
+            emitter,
+            ForceConfig::default(),
+            1.0 / 60.0,
+            0.05,
+        );
+        assert_eq!(clean.steps, 180);
+        assert!(clean.passed());
+
+        let poisoned = ForceConfig {
+            gravity: [0.0, f32::INFINITY, 0.0],
+            ..ForceConfig::default()
+        };
+        let mut state = ParticleState::new(config);
+        let report = soak_reference(&mut state, config, emitter, poisoned, 1.0 / 60.0, 0.05);
+        assert!(!report.passed());
+        // Nothing is alive during the first step's update.
+        assert_eq!(report.first_violation_seconds, Some(2.0 / 60.0));
+        assert_eq!(report.final_alive_count, 20);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::particles::ForceConfig;
+use crate::particles::{Burst, BurstSchedule, ForceConfig, ForceField, ForceFieldList};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PassId {
@@ -37,6 +37,8 @@ pub struct PassPreset {
     pub profile: FxProfile,
     pub particle_spawn_multiplier: f32,
     pub force: ForceConfig,
+    /// Bursts timed from the start of this pass; see `VideoTimeline::burst_schedule`.
+    pub bursts: BurstSchedule,
 }
 
 #[derive(Debug, Clone)]
@@ -61,10 +63,26 @@ impl VideoTimeline {
                 particle_spawn_multiplier: 0.8,
                 force: ForceConfig {
                     gravity: [0.0, -0.25, 0.0],
-                    attractor: [0.15, 0.05, 0.0],
-                    attractor_strength: 0.09,
+                    fields: ForceFieldList::from_slice(&[
+                        ForceField::Point {
+                            position: [0.15, 0.05, 0.0],
+                            strength: 0.09,
+                            radius: 0.0,
+                            falloff: 1.0,
+                        },
+                        ForceField::Wind {
+                            direction: [1.0, 0.2, 0.0],
+                            strength: 0.04,
+                            turbulence: 0.05,
+                            turbulence_frequency: 0.8,
+                        },
+                    ]),
                     noise_strength: 0.2,
+                    noise_frequency: 1.2,
+                    noise_octaves: 2,
+                    noise_time_scale: 0.2,
                 },
+                bursts: BurstSchedule::new(),
             },
             PassPreset {
                 id: PassId::MtsdfPass02,
@@ -79,10 +97,33 @@ impl VideoTimeline {
                 particle_spawn_multiplier: 1.1,
                 force: ForceConfig {
                     gravity: [0.0, -0.05, 0.0],
-                    attractor: [-0.2, 0.0, 0.0],
-                    attractor_strength: 0.3,
+                    fields: ForceFieldList::from_slice(&[
+                        ForceField::Point {
+                            position: [-0.2, 0.0, 0.0],
+                            strength: 0.3,
+                            radius: 0.0,
+                            falloff: 1.0,
+                        },
+                        ForceField::Vortex {
+                            position: [-0.2, 0.0, 0.0],
+                            axis: [0.0, 0.0, 1.0],
+                            strength: 0.5,
+                            inward_strength: 0.1,
+                            radius: 1.2,
+                            falloff: 1.5,
+                        },
+                    ]),
                     noise_strength: 0.45,
+                    noise_frequency: 2.0,
+                    noise_octaves: 3,
+                    noise_time_scale: 0.6,
                 },
+                bursts: BurstSchedule::from_slice(&[Burst {
+                    time_seconds: 0.4,
+                    count: 250,
+                    interval_seconds: 0.6,
+                    cycles: 0,
+                }]),
             },
             PassPreset {
                 id: PassId::MtsdfPass03,
@@ -97,10 +138,34 @@ impl VideoTimeline {
                 particle_spawn_multiplier: 1.35,
                 force: ForceConfig {
                     gravity: [0.0, -0.15, 0.0],
-                    attractor: [0.0, -0.1, 0.0],
-                    attractor_strength: 0.42,
+                    fields: ForceFieldList::from_slice(&[
+                        ForceField::Point {
+                            position: [0.0, -0.1, 0.0],
+                            strength: 0.42,
+                            radius: 0.0,
+                            falloff: 1.0,
+                        },
+                        ForceField::Point {
+                            position: [0.0, -0.1, 0.0],
+                            strength: -0.8,
+                            radius: 0.25,
+                            falloff: 2.0,
+                        },
+                        ForceField::Vortex {
+                            position: [0.0, -0.1, 0.0],
+                            axis: [0.0, 1.0, 0.0],
+                            strength: 0.35,
+                            inward_strength: 0.0,
+                            radius: 0.8,
+                            falloff: 1.0,
+                        },
+                    ]),
                     noise_strength: 0.35,
+                    noise_frequency: 2.6,
+                    noise_octaves: 3,
+                    noise_time_scale: 0.9,
                 },
+                bursts: BurstSchedule::from_slice(&[Burst::once(0.0, 1_500)]),
             },
             PassPreset {
                 id: PassId::Recovery,
@@ -115,10 +180,26 @@ impl VideoTimeline {
                 particle_spawn_multiplier: 0.5,
                 force: ForceConfig {
                     gravity: [0.0, -0.35, 0.0],
-                    attractor: [0.0, 0.0, 0.0],
-                    attractor_strength: 0.02,
+                    fields: ForceFieldList::from_slice(&[
+                        ForceField::Point {
+                            position: [0.0, 0.0, 0.0],
+                            strength: 0.02,
+                            radius: 0.0,
+                            falloff: 1.0,
+                        },
+                        ForceField::DragZone {
+                            position: [0.0, 0.0, 0.0],
+                            radius: 0.6,
+                            drag: 1.5,
+                            falloff: 1.0,
+                        },
+                    ]),
                     noise_strength: 0.1,
+                    noise_frequency: 1.0,
+                    noise_octaves: 1,
+                    noise_time_scale: 0.1,
                 },
+                bursts: BurstSchedule::new(),
             },
         ];
         let total_duration = passes.iter().map(|p| p.duration_seconds).sum();
@@ -136,6 +217,38 @@ impl VideoTimeline {
         &self.passes
     }
 
+    /// Every pass's bursts shifted to timeline time and repeating with the timeline loop.
+    /// Repeating bursts stop at the end of their pass; bursts past `MAX_BURSTS` are dropped.
+    pub fn burst_schedule(&self) -> BurstSchedule {
+        let mut schedule = BurstSchedule::new().with_period(self.total_duration);
+        let mut pass_start = 0.0;
+        for pass in &self.passes {
+            for burst in pass.bursts.as_slice() {
+                if burst.time_seconds >= pass.duration_seconds {
+                    continue;
+                }
+                let mut cycles = burst.cycles;
+                if burst.interval_seconds > 0.0 {
+                    let in_pass = ((pass.duration_seconds - burst.time_seconds)
+                        / burst.interval_seconds)
+                        .ceil() as u32;
+                    cycles = if cycles == 0 {
+                        in_pass
+                    } else {
+                        cycles.min(in_pass)
+                    };
+                }
+                schedule.push(Burst {
+                    time_seconds: pass_start + burst.time_seconds,
+                    cycles,
+                    ..*burst
+                });
+            }
+            pass_start += pass.duration_seconds;
+        }
+        schedule
+    }
+
     pub fn sample(&self, time_seconds: f32) -> TimelineSample {
         let mut t = time_seconds.max(0.0);
         if self.total_duration > 0.0 {
@@ -205,6 +318,30 @@ mod tests {
         assert_eq!(sample.current.id, PassId::MtsdfPass01);
     }
 
+    #[test]
+    fn pass03_burst_fires_at_pass_start_every_loop() {
+        let timeline = VideoTimeline::mtsdf_reference_sequence();
+        let schedule = timeline.burst_schedule();
+        let pass03_start: f32 = timeline
+            .passes()
+            .iter()
+            .take_while(|p| p.id != PassId::MtsdfPass03)
+            .map(|p| p.duration_seconds)
+            .sum();
+        let window = |t: f32| schedule.count_between(t - 0.001, t + 0.001);
+        assert_eq!(window(pass03_start), 1_500);
+        assert_eq!(window(pass03_start + timeline.total_duration()), 1_500);
+
+        // Pass 02 repeats every 0.6s from 0.4s in, but only while it is current.
+        let pass02_start = timeline.passes()[0].duration_seconds;
+        let pass02 = schedule.count_between(pass02_start, pass03_start);
+        assert_eq!(pass02, 250 * 4);
+        assert_eq!(
+            schedule.count_between(pass03_start + 0.01, pass03_start + 2.0),
+            0
+        );
+    }
+
     #[test]
     fn loops_at_total_duration() {
         let timeline = VideoTimeline::mtsdf_reference_sequence();